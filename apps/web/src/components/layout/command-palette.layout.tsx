@@ -28,14 +28,17 @@ export const CommandPalette = () => {
         }
 
         tx.blocks?.forEach((block: any) => {
-          if (block.type === 'markdown' && block.content.toLowerCase().includes(query.toLowerCase())) {
+          if ('content' in block && block.content.toLowerCase().includes(query.toLowerCase())) {
             // Avoid duplicates if we already matched the transaction
             if (!matches.some(m => m.id === tx.id)) {
                matches.push({ type: 'doc', id: tx.id, title: 'Reasoning match...', subtitle: tx.description });
             }
           }
-          if (block.type === 'file' && block.file.path.toLowerCase().includes(query.toLowerCase())) {
-            matches.push({ type: 'file', id: tx.id, title: block.file.path, subtitle: tx.description });
+        });
+
+        tx.files?.forEach((file) => {
+          if (file.path.toLowerCase().includes(query.toLowerCase())) {
+            matches.push({ type: 'file', id: tx.id, title: file.path, subtitle: tx.description });
           }
         });
       });
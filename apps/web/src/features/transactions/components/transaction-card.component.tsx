@@ -22,10 +22,11 @@ import {
 } from 'lucide-react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { cn } from "@/utils/cn.util";
-import { TransactionStatus, TransactionBlock, TransactionFile, STATUS_CONFIG, FILE_STATUS_CONFIG, FileApplyStatus } from "@/types/app.types";
+import { TransactionStatus, TransactionBlock, TransactionFile, STATUS_CONFIG, FILE_STATUS_CONFIG, FileApplyStatus, Cost, TokenUsage } from "@/types/app.types";
 import { StatusBadge } from "@/components/ui/status-badge.ui";
 import { useStore } from "@/store/root.store";
 import { calculateTotalStats } from "@/utils/diff.util";
+import { formatCost, formatTokens } from "@/utils/format.util";
 import { DiffStat } from "@/components/ui/diff-stat.ui";
 import { FileSection } from "./file-section.component";
 import { Metric } from "@/components/ui/metric.ui";
@@ -38,8 +39,8 @@ interface TransactionCardProps {
   timestamp: string;
   provider: string;
   model: string;
-  tokens: string;
-  cost: string;
+  tokens: TokenUsage;
+  cost: Cost;
   blocks?: TransactionBlock[];
   files?: TransactionFile[];
   isNew?: boolean;
@@ -47,6 +48,20 @@ interface TransactionCardProps {
   parentId?: string | null;
 }
 
+// Non-file blocks are shown as markdown
+const blockMarkdown = (block: Exclude<TransactionBlock, { type: 'file' }>): string => {
+  switch (block.type) {
+    case 'command':
+      return `\`\`\`sh\n$ ${block.command}${block.output ? `\n${block.output}` : ''}\n\`\`\``;
+    case 'plan-step':
+      return `- [${block.done ? 'x' : ' '}] ${block.content}`;
+    case 'note':
+      return `> ${block.content}`;
+    default:
+      return block.content;
+  }
+};
+
 // Helper to get file info with original block index
 interface FileInfo {
   file: TransactionFile | null | undefined;
@@ -108,7 +123,7 @@ export const TransactionCard = memo(({
       blocks.forEach((block, blockIdx) => {
         if (block.type === 'file') {
           infos.push({
-            file: block.file,
+            file: filesProp?.find(f => f.id === block.fileId),
             blockIndex: blockIdx,
             fileIndex: fileCount++
           });
@@ -337,8 +352,8 @@ export const TransactionCard = memo(({
             {/* Observability Strip */}
             <div className="flex items-center gap-6 px-8 py-3 bg-zinc-950 border-b border-zinc-900/50 overflow-x-auto scrollbar-hide">
                <Metric icon={Cpu} label="Engine" value={`${provider} / ${model}`} color="text-indigo-400" />
-               <Metric icon={Terminal} label="Context" value={`${tokens} tokens`} color="text-emerald-400" />
-               <Metric icon={Coins} label="Cost" value={cost} color="text-amber-400" />
+               <Metric icon={Terminal} label="Context" value={`${formatTokens(tokens)} tokens`} color="text-emerald-400" />
+               <Metric icon={Coins} label="Cost" value={formatCost(cost)} color="text-amber-400" />
                <div className="ml-auto hidden md:flex items-center gap-2 text-[10px] text-zinc-500 font-mono">
                   <ExternalLink className="w-3 h-3" />
                   <span>Report v2.4</span>
@@ -446,12 +461,12 @@ export const TransactionCard = memo(({
                   {blocks && blocks.length > 0 ? (
                     // Render blocks with interleaved markdown and files
                     blocks.map((block, blockIdx) => {
-                      if (block.type === 'markdown') {
+                      if (block.type !== 'file') {
                         if (hideReasoning) return null;
                         return (
                           <div key={blockIdx} className="prose prose-zinc prose-invert prose-sm max-w-none px-4">
                             <ReactMarkdown remarkPlugins={[remarkGfm]}>
-                              {block.content}
+                              {blockMarkdown(block)}
                             </ReactMarkdown>
                           </div>
                         );
@@ -459,7 +474,7 @@ export const TransactionCard = memo(({
                       // Find the file index for this block
                       const fileInfo = fileInfos.find(f => f.blockIndex === blockIdx);
                       const fileIndex = fileInfo?.fileIndex ?? 0;
-                      if (!block.file) return null;
+                      if (!fileInfo?.file) return null;
                       return (
                         <div 
                           key={blockIdx}
@@ -469,7 +484,7 @@ export const TransactionCard = memo(({
                           data-file-index={fileIndex}
                         >
                           <FileSection 
-                            file={block.file} 
+                            file={fileInfo.file} 
                             isApplying={status === 'APPLYING'} 
                             forceCollapsed={allCodeblocksCollapsed}
                             transactionId={id}
@@ -15,7 +15,7 @@ export interface SimulationEvent {
 export interface FileStatusEvent {
   transactionId: string;
   filePath: string;
-  applyStatus: 'PENDING' | 'APPLYING' | 'APPLIED' | 'FAILED';
+  applyStatus: 'PENDING' | 'APPLYING' | 'APPLIED' | 'FAILED' | 'SKIPPED';
   errorMessage?: string | null;
   timestamp: string;
 }
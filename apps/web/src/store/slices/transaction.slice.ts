@@ -14,11 +14,6 @@ function updateTransactionFile(
   return {
     ...transaction,
     files: updateFiles(transaction.files || []),
-    blocks: transaction.blocks?.map(block =>
-      block.type === 'file' && block.file?.path === filePath
-        ? { ...block, file: { ...block.file, ...updates } }
-        : block
-    )
   };
 }
 
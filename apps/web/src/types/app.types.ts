@@ -21,6 +21,8 @@ export type Prompt = components["schemas"]["Prompt"];
 export type TransactionBlock = components["schemas"]["TransactionBlock"];
 export type Transaction = components["schemas"]["Transaction"];
 export type FileApplyStatus = components["schemas"]["FileApplyStatus"];
+export type Cost = components["schemas"]["Cost"];
+export type TokenUsage = components["schemas"]["TokenUsage"];
 
 export const STATUS_CONFIG: Record<TransactionStatus, { 
   icon: LucideIcon; 
@@ -54,6 +56,7 @@ export const FILE_APPLY_STATUS_CONFIG: Record<FileApplyStatus, {
   APPLYING: { icon: Loader2, color: 'text-indigo-400', bgColor: 'bg-indigo-500', animate: true },
   APPLIED:  { icon: CheckCircle2, color: 'text-emerald-400', bgColor: 'bg-emerald-500' },
   FAILED:   { icon: AlertCircle, color: 'text-red-400', bgColor: 'bg-red-500' },
+  SKIPPED:  { icon: XCircle, color: 'text-zinc-500', bgColor: 'bg-zinc-600' },
 };
 
 export type GroupByStrategy = 'prompt' | 'date' | 'author' | 'status' | 'files' | 'none';
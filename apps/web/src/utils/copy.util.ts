@@ -1,4 +1,5 @@
 import { Transaction } from "@/types/app.types";
+import { formatCost, formatTokens } from "@/utils/format.util";
 
 export interface ReportOptions {
   description: boolean;
@@ -23,7 +24,7 @@ export const generateGranularReport = (
       if (options.description) parts.push(`### ${t.description} (${t.id})`);
       if (options.metadata) {
         parts.push(`**Status**: ${t.status} | **Author**: ${t.author} | **Time**: ${t.timestamp}`);
-        if (t.tokens) parts.push(`**Tokens**: ${t.tokens} | **Cost**: ${t.cost}`);
+        if (t.tokens.totalTokens) parts.push(`**Tokens**: ${formatTokens(t.tokens)} | **Cost**: ${formatCost(t.cost)}`);
       }
       if (parts.length) section += parts.join('\n') + '\n\n';
     }
@@ -40,13 +41,10 @@ export const generateGranularReport = (
     }
 
     // File Content / Diffs
-    if (options.diffs && t.blocks) {
-      const content = t.blocks.map(b => {
-        if (b.type === 'file' && b.file) {
-          return `File: ${b.file.path}\n\`\`\`${b.file.language}\n${b.file.diff}\n\`\`\``;
-        }
-        return '';
-      }).filter(Boolean).join('\n\n');
+    if (options.diffs && t.files) {
+      const content = t.files.map(f =>
+        `File: ${f.path}\n\`\`\`${f.language}\n${f.diff}\n\`\`\``
+      ).join('\n\n');
       
       if (content) section += `#### Changes:\n${content}\n\n`;
     }
@@ -0,0 +1,14 @@
+import { Cost, TokenUsage } from "@/types/app.types";
+
+export const formatCost = (cost: Cost): string => {
+  const amount = new Intl.NumberFormat('en-US', {
+    style: 'currency',
+    currency: cost.currency,
+    minimumFractionDigits: 2,
+    maximumFractionDigits: 4,
+  }).format(cost.amount);
+  return cost.estimated ? `~${amount}` : amount;
+};
+
+export const formatTokens = (tokens: TokenUsage): string =>
+  tokens.totalTokens.toLocaleString('en-US');
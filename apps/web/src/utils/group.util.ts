@@ -51,7 +51,7 @@ export function groupTransactions(
     author: (tx) => ({ key: tx.author || '?', label: tx.author ? `@${tx.author}` : 'Unknown' }),
     status: (tx) => ({ key: tx.status, label: tx.status.charAt(0) + tx.status.slice(1).toLowerCase() }),
     files:  (tx) => {
-      const firstFile = tx.files?.[0] || tx.blocks?.find(b => b.type === 'file')?.file;
+      const firstFile = tx.files?.[0];
       return { key: firstFile?.path || '?', label: firstFile?.path || 'No Files' };
     },
     none:   () => ({ key: 'all', label: 'All' }),
@@ -14,18 +14,54 @@ use utoipa_scalar::{Scalar, Servable};
 #[openapi(
     paths(
         routes::transactions::list_transactions,
+        routes::transactions::create_transaction,
+        routes::transactions::import_transactions,
         routes::transactions::update_transaction_status,
         routes::transactions::bulk_update_transactions,
         routes::transactions::reapply_single_file,
+        routes::transactions::update_file_diff,
         routes::transactions::reapply_all_failed_files,
+        routes::transactions::get_transaction_diagnostics,
+        routes::transactions::get_transaction_handoff,
+        routes::transactions::get_transaction_conflicts,
+        routes::transactions::get_transaction_chain,
+        routes::transactions::apply_transaction_chain,
+        routes::transactions::revert_transaction_chain,
+        routes::transactions::get_chain_diff,
         routes::prompts::list_prompts,
         routes::events::events_stream,
         routes::dev::reset_mock_data,
+        routes::config::get_config,
+        routes::config::update_config,
+        routes::config::get_config_schema,
+        routes::batches::list_batches,
+        routes::batches::get_batch,
+        routes::batches::approve_batch_members,
+        routes::watcher::get_watcher_status,
+        routes::watcher::pause_watcher,
+        routes::watcher::resume_watcher,
+        routes::patches::detect_patch,
+        routes::review::list_hunks,
+        routes::review::review_hunks,
+        routes::review::review_files,
+        routes::repair::repair_file,
+        routes::repair::repair_failed_files,
+        routes::context::pack_context,
+        routes::system_prompt::get_system_prompt,
+        routes::system_prompt::update_system_prompt,
+        routes::system_prompt::list_system_prompt_versions,
+        routes::system_prompt::restore_system_prompt_version,
+        routes::system_prompt::preview_system_prompt,
+        routes::commit_message::generate_commit_message,
+        routes::commit_message::accept_commit_message,
+        routes::analytics::get_analytics,
     ),
     components(
         schemas(
             relaycode_schema::Transaction,
             relaycode_schema::TransactionStatus,
+            relaycode_schema::TokenUsage,
+            relaycode_schema::Cost,
             relaycode_schema::TransactionBlock,
             relaycode_schema::TransactionFile,
             relaycode_schema::FileStatus,
@@ -39,6 +75,86 @@ use utoipa_scalar::{Scalar, Servable};
             relaycode_schema::UpdateStatusRequest,
             relaycode_schema::SimulationScenario,
             relaycode_schema::ReapplyFileRequest,
+            relaycode_schema::RelayConfig,
+            relaycode_schema::CoreConfig,
+            relaycode_schema::WatcherConfig,
+            relaycode_schema::WatchSource,
+            relaycode_schema::PatchConfig,
+            relaycode_schema::GitConfig,
+            relaycode_schema::LogLevel,
+            relaycode_schema::PatchStrategy,
+            relaycode_schema::ApprovalMode,
+            relaycode_schema::GitBranchTemplate,
+            relaycode_schema::ValidationErrors,
+            relaycode_schema::ValidationRun,
+            relaycode_schema::ValidationKind,
+            relaycode_schema::OutputStream,
+            relaycode_schema::CommandOutputEvent,
+            relaycode_schema::ApprovalMethod,
+            relaycode_schema::ApprovalRule,
+            relaycode_schema::ApprovalRecord,
+            relaycode_schema::ApprovalRejection,
+            relaycode_schema::NewTransaction,
+            relaycode_schema::ImportPatchRequest,
+            relaycode_schema::Diagnostic,
+            relaycode_schema::DiagnosticSeverity,
+            relaycode_schema::LintSummary,
+            relaycode_schema::DiagnosticsReport,
+            relaycode_schema::ConflictKind,
+            relaycode_schema::LineRange,
+            relaycode_schema::FileConflict,
+            relaycode_schema::TransactionConflict,
+            relaycode_schema::ConflictReport,
+            relaycode_schema::ChainNode,
+            relaycode_schema::TransactionChain,
+            relaycode_schema::ChainFileDiff,
+            relaycode_schema::ChainDiff,
+            relaycode_schema::Batch,
+            relaycode_schema::FileOverlap,
+            relaycode_schema::WatcherState,
+            relaycode_schema::WatcherStatus,
+            relaycode_schema::PatchFormat,
+            relaycode_schema::PatchSegment,
+            relaycode_schema::PatchDetection,
+            relaycode_schema::DetectPatchRequest,
+            relaycode_schema::ReviewDecision,
+            relaycode_schema::HunkDecision,
+            relaycode_schema::HunkReview,
+            relaycode_schema::FileHunks,
+            relaycode_schema::HunkDecisionUpdate,
+            relaycode_schema::ReviewHunksRequest,
+            relaycode_schema::FileReview,
+            relaycode_schema::ReviewFilesRequest,
+            relaycode_schema::DiffRevision,
+            relaycode_schema::UpdateFileDiffRequest,
+            relaycode_schema::AiProviderConfig,
+            relaycode_schema::RepairStage,
+            relaycode_schema::RepairEvent,
+            relaycode_schema::RepairFileRequest,
+            relaycode_schema::RepairStarted,
+            relaycode_schema::ContextTier,
+            relaycode_schema::PackFormat,
+            relaycode_schema::ContextFiles,
+            relaycode_schema::PackContextRequest,
+            relaycode_schema::PackedFile,
+            relaycode_schema::SkipReason,
+            relaycode_schema::SkippedFile,
+            relaycode_schema::ContextPack,
+            relaycode_schema::SystemPrompt,
+            relaycode_schema::SystemPromptVersion,
+            relaycode_schema::UpdateSystemPromptRequest,
+            relaycode_schema::PreviewSystemPromptRequest,
+            relaycode_schema::SystemPromptPreview,
+            relaycode_schema::GenerateCommitMessageRequest,
+            relaycode_schema::CommitMessageSource,
+            relaycode_schema::CommitMessageSuggestion,
+            relaycode_schema::AcceptCommitMessageRequest,
+            relaycode_schema::AnalyticsBucket,
+            relaycode_schema::AnalyticsGroupBy,
+            relaycode_schema::UsageStats,
+            relaycode_schema::AnalyticsPoint,
+            relaycode_schema::AnalyticsSeries,
+            relaycode_schema::AnalyticsReport,
         )
     ),
     info(
@@ -54,9 +170,21 @@ async fn main() {
 
     let openapi = ApiDoc::openapi();
     let openapi_path = std::path::Path::new("/root/code/relay/openapi.json");
-    fs::write(&openapi_path, openapi.to_pretty_json().unwrap()).unwrap();
+    fs::write(openapi_path, openapi.to_pretty_json().unwrap()).unwrap();
 
-    relaycode_core::STORE.load_data();
+    if let Err(err) = relaycode_core::STORE.load_data() {
+        tracing::warn!("Mock data not loaded: {}", err);
+    }
+    if let Err(err) = relaycode_core::STORE.load_config() {
+        tracing::warn!("Using default config: {}", err);
+    }
+    relaycode_core::STORE.restore_watcher_state();
+    let watcher_config = relaycode_core::STORE.get_config().watcher;
+    match relaycode_core::watcher::restart(&watcher_config) {
+        Ok(()) if watcher_config.enabled => tracing::info!("Watching for patches"),
+        Ok(()) => tracing::info!("Patch watcher disabled; set watcher.enabled to start it"),
+        Err(err) => tracing::warn!("Patch watcher not started: {}", err),
+    }
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -73,6 +201,16 @@ async fn main() {
         .nest("/api", routes::prompts::router())
         .nest("/api", routes::events::router())
         .nest("/api", routes::dev::router())
+        .nest("/api", routes::config::router())
+        .nest("/api", routes::batches::router())
+        .nest("/api", routes::watcher::router())
+        .nest("/api", routes::patches::router())
+        .nest("/api", routes::review::router())
+        .nest("/api", routes::repair::router())
+        .nest("/api", routes::context::router())
+        .nest("/api", routes::system_prompt::router())
+        .nest("/api", routes::commit_message::router())
+        .nest("/api", routes::analytics::router())
         .layer(cors);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
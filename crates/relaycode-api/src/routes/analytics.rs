@@ -0,0 +1,46 @@
+use relaycode_schema::{AnalyticsBucket, AnalyticsGroupBy, AnalyticsReport, ValidationErrors};
+use relaycode_core::analytics;
+use axum::{extract::Query, http::StatusCode, routing::get, Json, Router};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsQuery {
+    /// Bucket width; `day` by default.
+    #[serde(default)]
+    pub bucket: AnalyticsBucket,
+    /// One series per provider, model, author or prompt; a single `all` series when omitted.
+    #[serde(default)]
+    pub group_by: Option<AnalyticsGroupBy>,
+    /// Earliest `createdAt` included: RFC 3339, or `YYYY-MM-DD` for midnight UTC.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Latest `createdAt`, exclusive; a bare date includes that whole day.
+    #[serde(default)]
+    pub to: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/analytics",
+    tag = "Analytics",
+    params(AnalyticsQuery),
+    responses(
+        (status = 200, body = AnalyticsReport, description = "Counts, success rate, tokens and cost per time bucket"),
+        (status = 422, body = ValidationErrors, description = "Malformed date bound")
+    )
+)]
+pub async fn get_analytics(
+    Query(params): Query<AnalyticsQuery>,
+) -> Result<Json<AnalyticsReport>, (StatusCode, Json<ValidationErrors>)> {
+    analytics::report(params.bucket, params.group_by, params.from.as_deref(), params.to.as_deref())
+        .map(Json)
+        .map_err(|errors| (StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrors { errors })))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/analytics", get(get_analytics))
+}
@@ -0,0 +1,66 @@
+use relaycode_schema::{Batch, BulkActionResponse};
+use relaycode_core::{batch::approve_batch, STORE};
+use axum::{
+    routing::{get, post},
+    Json, Router,
+};
+
+#[utoipa::path(
+    get,
+    path = "/api/batches",
+    tag = "Batches",
+    responses(
+        (status = 200, body = [Batch])
+    )
+)]
+pub async fn list_batches() -> Json<Vec<Batch>> {
+    Json(STORE.get_batches())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/batches/{id}",
+    tag = "Batches",
+    params(
+        ("id" = String, Path, description = "Batch ID")
+    ),
+    responses(
+        (status = 200, body = Batch),
+        (status = 404, description = "Batch not found")
+    )
+)]
+pub async fn get_batch(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<Batch>, axum::http::StatusCode> {
+    STORE.get_batch(&id).map(Json).ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/batches/{id}/approve",
+    tag = "Batches",
+    params(
+        ("id" = String, Path, description = "Batch ID")
+    ),
+    responses(
+        (status = 200, body = BulkActionResponse, description = "Members queued for applying; members sharing a file apply in batch order"),
+        (status = 404, description = "Batch not found")
+    )
+)]
+pub async fn approve_batch_members(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<BulkActionResponse>, axum::http::StatusCode> {
+    let updated_ids = approve_batch(&id).ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    Ok(Json(BulkActionResponse {
+        success: true,
+        updated_ids,
+        rejected_ids: Vec::new(),
+    }))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/batches", get(list_batches))
+        .route("/batches/{id}", get(get_batch))
+        .route("/batches/{id}/approve", post(approve_batch_members))
+}
@@ -0,0 +1,70 @@
+use relaycode_schema::{
+    AcceptCommitMessageRequest, CommitMessageSuggestion, GenerateCommitMessageRequest, Transaction,
+    ValidationErrors,
+};
+use relaycode_core::{commit_message, STORE};
+use axum::{http::StatusCode, routing::{post, put}, Json, Router};
+
+type CommitMessageError = (StatusCode, Json<ValidationErrors>);
+
+fn error(status: StatusCode, errors: Vec<String>) -> CommitMessageError {
+    (status, Json(ValidationErrors { errors }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/commit-message/generate",
+    tag = "Commit Messages",
+    request_body = GenerateCommitMessageRequest,
+    responses(
+        (status = 200, body = CommitMessageSuggestion, description = "Suggested message; nothing is stored until it is accepted"),
+        (status = 404, body = ValidationErrors, description = "Transaction not found"),
+        (status = 422, body = ValidationErrors, description = "No transactions given")
+    )
+)]
+pub async fn generate_commit_message(
+    Json(payload): Json<GenerateCommitMessageRequest>,
+) -> Result<Json<CommitMessageSuggestion>, CommitMessageError> {
+    if payload.transaction_ids.is_empty() {
+        return Err(error(StatusCode::UNPROCESSABLE_ENTITY, vec!["transactionIds: must not be empty".to_string()]));
+    }
+    let transactions = commit_message::transactions(&payload.transaction_ids)
+        .map_err(|errors| error(StatusCode::NOT_FOUND, errors))?;
+    Ok(Json(commit_message::suggest(&transactions, payload.deterministic).await))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/commit-message",
+    tag = "Commit Messages",
+    request_body = AcceptCommitMessageRequest,
+    responses(
+        (status = 200, body = Vec<Transaction>, description = "The message is stored on every listed transaction"),
+        (status = 404, body = ValidationErrors, description = "Transaction not found"),
+        (status = 422, body = ValidationErrors, description = "No transactions given or an empty message")
+    )
+)]
+pub async fn accept_commit_message(
+    Json(payload): Json<AcceptCommitMessageRequest>,
+) -> Result<Json<Vec<Transaction>>, CommitMessageError> {
+    let mut errors = Vec::new();
+    if payload.transaction_ids.is_empty() {
+        errors.push("transactionIds: must not be empty".to_string());
+    }
+    if payload.message.trim().is_empty() {
+        errors.push("message: must not be empty".to_string());
+    }
+    if !errors.is_empty() {
+        return Err(error(StatusCode::UNPROCESSABLE_ENTITY, errors));
+    }
+    STORE
+        .set_commit_message(&payload.transaction_ids, payload.message.trim())
+        .map(Json)
+        .map_err(|errors| error(StatusCode::NOT_FOUND, errors))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/commit-message/generate", post(generate_commit_message))
+        .route("/commit-message", put(accept_commit_message))
+}
@@ -0,0 +1,66 @@
+use relaycode_schema::{RelayConfig, ValidationErrors};
+use relaycode_core::{config::{self, ConfigError}, STORE};
+use axum::{
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+
+#[utoipa::path(
+    get,
+    path = "/api/config",
+    tag = "Config",
+    responses(
+        (status = 200, body = RelayConfig, description = "The config, with a stored API key redacted")
+    )
+)]
+pub async fn get_config() -> Json<RelayConfig> {
+    Json(config::redacted(STORE.get_config()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/config",
+    tag = "Config",
+    request_body = RelayConfig,
+    responses(
+        (status = 200, body = RelayConfig),
+        (status = 422, body = ValidationErrors, description = "Config failed schema validation"),
+        (status = 500, description = "Config could not be written")
+    )
+)]
+pub async fn update_config(
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<RelayConfig>, (StatusCode, Json<ValidationErrors>)> {
+    let watcher_before = STORE.get_config().watcher;
+    match STORE.update_config(body) {
+        Ok(updated) => {
+            if updated.watcher != watcher_before {
+                if let Err(err) = relaycode_core::watcher::restart(&updated.watcher) {
+                    tracing::warn!("Patch watcher not restarted: {}", err);
+                }
+            }
+            Ok(Json(config::redacted(updated)))
+        }
+        Err(ConfigError::Invalid(errors)) => Err((StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrors { errors }))),
+        Err(err) => Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ValidationErrors { errors: vec![err.to_string()] }))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/config/schema",
+    tag = "Config",
+    responses(
+        (status = 200, description = "JSON Schema for relay.config.json")
+    )
+)]
+pub async fn get_config_schema() -> Json<serde_json::Value> {
+    Json(relaycode_core::config::config_json_schema())
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/config", get(get_config).put(update_config))
+        .route("/config/schema", get(get_config_schema))
+}
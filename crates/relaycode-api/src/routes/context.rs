@@ -0,0 +1,26 @@
+use relaycode_schema::{ContextPack, PackContextRequest, ValidationErrors};
+use relaycode_core::context;
+use axum::{http::StatusCode, routing::post, Json, Router};
+
+#[utoipa::path(
+    post,
+    path = "/api/context/pack",
+    tag = "Context",
+    request_body = PackContextRequest,
+    responses(
+        (status = 200, body = ContextPack, description = "Packed document plus what was left out and why"),
+        (status = 422, body = ValidationErrors, description = "No files requested or a malformed glob")
+    )
+)]
+pub async fn pack_context(
+    Json(payload): Json<PackContextRequest>,
+) -> Result<Json<ContextPack>, (StatusCode, Json<ValidationErrors>)> {
+    context::pack(&payload)
+        .map(Json)
+        .map_err(|errors| (StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrors { errors })))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/context/pack", post(pack_context))
+}
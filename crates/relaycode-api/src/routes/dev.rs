@@ -1,4 +1,5 @@
 use axum::{
+    http::StatusCode,
     routing::post,
     Json, Router,
 };
@@ -8,12 +9,19 @@ use axum::{
     path = "/api/dev/reset",
     tag = "Dev",
     responses(
-        (status = 200, description = "Mock data reset successfully")
+        (status = 200, description = "Mock data reset successfully"),
+        (status = 500, description = "Mock data could not be read; the store is unchanged")
     )
 )]
-pub async fn reset_mock_data() -> Json<serde_json::Value> {
-    relaycode_core::STORE.load_data();
-    Json(serde_json::json!({ "success": true, "message": "Mock data reset" }))
+pub async fn reset_mock_data() -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    relaycode_core::STORE.load_data().map_err(|err| {
+        tracing::error!("Failed to reset mock data: {}", err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "success": false, "message": err.to_string() })),
+        )
+    })?;
+    Ok(Json(serde_json::json!({ "success": true, "message": "Mock data reset" })))
 }
 
 pub fn router() -> Router {
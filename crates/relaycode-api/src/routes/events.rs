@@ -1,4 +1,4 @@
-use relaycode_schema::{FileStatusEvent, Transaction};
+use relaycode_schema::{CommandOutputEvent, FileStatusEvent, RepairEvent, Transaction, WatcherStatus};
 use relaycode_core::STORE;
 use axum::{
     response::sse::{Event, Sse},
@@ -8,6 +8,7 @@ use axum::{
 use futures::stream::Stream;
 use std::convert::Infallible;
 use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 
 fn tx_to_event(tx: &Transaction) -> String {
     let event = serde_json::json!({
@@ -30,6 +31,44 @@ fn file_event_to_sse(event: &FileStatusEvent) -> String {
     json.to_string()
 }
 
+fn command_output_to_sse(event: &CommandOutputEvent) -> String {
+    let json = serde_json::json!({
+        "type": "output",
+        "transactionId": event.transaction_id,
+        "kind": event.kind,
+        "stream": event.stream,
+        "line": event.line,
+    });
+    json.to_string()
+}
+
+fn watcher_status_to_sse(status: &WatcherStatus) -> String {
+    let json = serde_json::json!({
+        "type": "watcher",
+        "state": status.state,
+        "paused": status.paused,
+        "source": status.source,
+        "lastDetectionAt": status.last_detection_at,
+        "lastError": status.last_error,
+        "detectedCount": status.detected_count,
+        "errorCount": status.error_count,
+    });
+    json.to_string()
+}
+
+fn repair_event_to_sse(event: &RepairEvent) -> String {
+    let json = serde_json::json!({
+        "type": "repair",
+        "transactionId": event.transaction_id,
+        "filePath": event.file_path,
+        "stage": event.stage,
+        "attempt": event.attempt,
+        "message": event.message,
+        "elapsedMs": event.elapsed_ms,
+    });
+    json.to_string()
+}
+
 #[utoipa::path(
     get,
     path = "/api/events",
@@ -41,6 +80,9 @@ fn file_event_to_sse(event: &FileStatusEvent) -> String {
 pub async fn events_stream() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     let mut tx_rx = STORE.subscribe();
     let mut file_rx = STORE.subscribe_to_file_events();
+    let mut output_rx = STORE.subscribe_to_command_output();
+    let mut watcher_rx = STORE.subscribe_to_watcher();
+    let mut repair_rx = STORE.subscribe_to_repairs();
 
     let stream = async_stream::stream! {
         yield Ok(Event::default().data("{\"type\": \"connected\"}"));
@@ -65,6 +107,37 @@ pub async fn events_stream() -> Sse<impl Stream<Item = Result<Event, Infallible>
                         Err(_) => break,
                     }
                 }
+                result = output_rx.recv() => {
+                    match result {
+                        Ok(output_event) => {
+                            let json = command_output_to_sse(&output_event);
+                            yield Ok(Event::default().data(json));
+                        }
+                        // Chatty commands can outrun a slow client; dropping lines beats dropping the stream.
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(_) => break,
+                    }
+                }
+                result = watcher_rx.recv() => {
+                    match result {
+                        Ok(status) => {
+                            let json = watcher_status_to_sse(&status);
+                            yield Ok(Event::default().data(json));
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(_) => break,
+                    }
+                }
+                result = repair_rx.recv() => {
+                    match result {
+                        Ok(repair_event) => {
+                            let json = repair_event_to_sse(&repair_event);
+                            yield Ok(Event::default().data(json));
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(_) => break,
+                    }
+                }
             }
         }
     };
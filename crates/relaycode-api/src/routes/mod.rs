@@ -2,3 +2,13 @@ pub mod transactions;
 pub mod prompts;
 pub mod events;
 pub mod dev;
+pub mod config;
+pub mod batches;
+pub mod watcher;
+pub mod patches;
+pub mod review;
+pub mod repair;
+pub mod context;
+pub mod system_prompt;
+pub mod commit_message;
+pub mod analytics;
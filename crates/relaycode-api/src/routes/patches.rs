@@ -0,0 +1,21 @@
+use relaycode_schema::{DetectPatchRequest, PatchDetection};
+use relaycode_core::detect;
+use axum::{routing::post, Json, Router};
+
+#[utoipa::path(
+    post,
+    path = "/api/patches/detect",
+    tag = "Patches",
+    request_body = DetectPatchRequest,
+    responses(
+        (status = 200, body = PatchDetection, description = "Detected format, confidence and segments")
+    )
+)]
+pub async fn detect_patch(Json(payload): Json<DetectPatchRequest>) -> Json<PatchDetection> {
+    Json(detect::classify(&payload.text))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/patches/detect", post(detect_patch))
+}
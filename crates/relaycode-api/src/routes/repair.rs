@@ -0,0 +1,81 @@
+use relaycode_schema::{RepairFileRequest, RepairStarted, ValidationErrors};
+use relaycode_core::{repair, STORE};
+use axum::{http::StatusCode, routing::post, Json, Router};
+
+type RepairError = (StatusCode, Json<ValidationErrors>);
+
+fn error(status: StatusCode, message: String) -> RepairError {
+    (status, Json(ValidationErrors { errors: vec![message] }))
+}
+
+fn start(id: String, file_paths: Vec<String>) -> Result<(StatusCode, Json<RepairStarted>), RepairError> {
+    let provider = repair::start(&id, file_paths.clone())
+        .map_err(|err| error(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(RepairStarted {
+            transaction_id: id,
+            file_paths,
+            provider,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/transactions/{id}/files/repair",
+    tag = "Repair",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    request_body = RepairFileRequest,
+    responses(
+        (status = 202, body = RepairStarted, description = "Repair started; progress arrives as `repair` events"),
+        (status = 404, description = "Transaction not found"),
+        (status = 409, body = ValidationErrors, description = "The file has not failed")
+    )
+)]
+pub async fn repair_file(
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(body): Json<RepairFileRequest>,
+) -> Result<(StatusCode, Json<RepairStarted>), RepairError> {
+    if STORE.get_transaction(&id).is_none() {
+        return Err(error(StatusCode::NOT_FOUND, format!("No transaction {}", id)));
+    }
+    if !STORE.get_failed_files(&id).contains(&body.file_path) {
+        return Err(error(StatusCode::CONFLICT, format!("{}: has not failed", body.file_path)));
+    }
+    start(id, vec![body.file_path])
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/transactions/{id}/repair",
+    tag = "Repair",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    responses(
+        (status = 202, body = RepairStarted, description = "Every failed file is being repaired in parallel"),
+        (status = 404, description = "Transaction not found"),
+        (status = 409, body = ValidationErrors, description = "No file has failed")
+    )
+)]
+pub async fn repair_failed_files(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<(StatusCode, Json<RepairStarted>), RepairError> {
+    if STORE.get_transaction(&id).is_none() {
+        return Err(error(StatusCode::NOT_FOUND, format!("No transaction {}", id)));
+    }
+    let failed = STORE.get_failed_files(&id);
+    if failed.is_empty() {
+        return Err(error(StatusCode::CONFLICT, "No failed files to repair".to_string()));
+    }
+    start(id, failed)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/transactions/{id}/files/repair", post(repair_file))
+        .route("/transactions/{id}/repair", post(repair_failed_files))
+}
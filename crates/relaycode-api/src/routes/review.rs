@@ -0,0 +1,81 @@
+use relaycode_schema::{FileHunks, ReviewFilesRequest, ReviewHunksRequest, Transaction, ValidationErrors};
+use relaycode_core::STORE;
+use axum::{
+    http::StatusCode,
+    routing::{get, patch},
+    Json, Router,
+};
+
+#[utoipa::path(
+    get,
+    path = "/api/transactions/{id}/hunks",
+    tag = "Review",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    responses(
+        (status = 200, body = [FileHunks]),
+        (status = 404, description = "Transaction not found")
+    )
+)]
+pub async fn list_hunks(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<Vec<FileHunks>>, StatusCode> {
+    STORE.get_hunks(&id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/transactions/{id}/hunks",
+    tag = "Review",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    request_body = ReviewHunksRequest,
+    responses(
+        (status = 200, body = [FileHunks], description = "Hunks with their updated decisions"),
+        (status = 404, description = "Transaction not found"),
+        (status = 422, body = ValidationErrors, description = "Unknown path or hunk id; nothing was changed")
+    )
+)]
+pub async fn review_hunks(
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(body): Json<ReviewHunksRequest>,
+) -> Result<Json<Vec<FileHunks>>, (StatusCode, Json<ValidationErrors>)> {
+    match STORE.review_hunks(&id, &body.decisions) {
+        Some(Ok(hunks)) => Ok(Json(hunks)),
+        Some(Err(errors)) => Err((StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrors { errors }))),
+        None => Err((StatusCode::NOT_FOUND, Json(ValidationErrors { errors: vec![format!("No transaction {}", id)] }))),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/transactions/{id}/files/review",
+    tag = "Review",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    request_body = ReviewFilesRequest,
+    responses(
+        (status = 200, body = Transaction, description = "Once any file is decided, applying touches only approved files"),
+        (status = 404, description = "Transaction not found"),
+        (status = 422, body = ValidationErrors, description = "Unknown path; nothing was changed")
+    )
+)]
+pub async fn review_files(
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(body): Json<ReviewFilesRequest>,
+) -> Result<Json<Transaction>, (StatusCode, Json<ValidationErrors>)> {
+    match STORE.review_files(&id, &body) {
+        Some(Ok(tx)) => Ok(Json(tx)),
+        Some(Err(errors)) => Err((StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrors { errors }))),
+        None => Err((StatusCode::NOT_FOUND, Json(ValidationErrors { errors: vec![format!("No transaction {}", id)] }))),
+    }
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/transactions/{id}/hunks", get(list_hunks).patch(review_hunks))
+        .route("/transactions/{id}/files/review", patch(review_files))
+}
@@ -0,0 +1,111 @@
+use relaycode_schema::{
+    PreviewSystemPromptRequest, SystemPrompt, SystemPromptPreview, SystemPromptVersion,
+    UpdateSystemPromptRequest, ValidationErrors,
+};
+use relaycode_core::system_prompt;
+use axum::{http::StatusCode, routing::{get, post}, Json, Router};
+
+type PromptError = (StatusCode, Json<ValidationErrors>);
+
+fn saved(result: std::io::Result<SystemPrompt>) -> Result<Json<SystemPrompt>, PromptError> {
+    result.map(Json).map_err(|err| {
+        tracing::error!("Failed to save system prompt: {}", err);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ValidationErrors { errors: vec![err.to_string()] }),
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/system-prompt",
+    tag = "System Prompt",
+    responses(
+        (status = 200, body = SystemPrompt, description = "The saved template, or the built-in default")
+    )
+)]
+pub async fn get_system_prompt() -> Json<SystemPrompt> {
+    Json(system_prompt::current())
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/system-prompt",
+    tag = "System Prompt",
+    request_body = UpdateSystemPromptRequest,
+    responses(
+        (status = 200, body = SystemPrompt, description = "Template saved as a new version"),
+        (status = 422, body = ValidationErrors, description = "Empty template")
+    )
+)]
+pub async fn update_system_prompt(
+    Json(payload): Json<UpdateSystemPromptRequest>,
+) -> Result<Json<SystemPrompt>, PromptError> {
+    if payload.content.trim().is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(ValidationErrors { errors: vec!["content: must not be empty".to_string()] }),
+        ));
+    }
+    saved(system_prompt::save(&payload.content))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/system-prompt/versions",
+    tag = "System Prompt",
+    responses(
+        (status = 200, body = Vec<SystemPromptVersion>, description = "Saved versions, oldest first")
+    )
+)]
+pub async fn list_system_prompt_versions() -> Json<Vec<SystemPromptVersion>> {
+    Json(system_prompt::versions())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/system-prompt/versions/{version}/restore",
+    tag = "System Prompt",
+    params(
+        ("version" = u32, Path, description = "Version to restore")
+    ),
+    responses(
+        (status = 200, body = SystemPrompt, description = "The version's content saved as the newest version"),
+        (status = 404, body = ValidationErrors, description = "Version not found")
+    )
+)]
+pub async fn restore_system_prompt_version(
+    axum::extract::Path(version): axum::extract::Path<u32>,
+) -> Result<Json<SystemPrompt>, PromptError> {
+    match system_prompt::restore(version) {
+        Some(result) => saved(result),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(ValidationErrors { errors: vec![format!("No system prompt version {}", version)] }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/system-prompt/preview",
+    tag = "System Prompt",
+    request_body = PreviewSystemPromptRequest,
+    responses(
+        (status = 200, body = SystemPromptPreview, description = "Template rendered with the current project config")
+    )
+)]
+pub async fn preview_system_prompt(
+    Json(payload): Json<PreviewSystemPromptRequest>,
+) -> Json<SystemPromptPreview> {
+    Json(system_prompt::preview(payload.content.as_deref()))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/system-prompt", get(get_system_prompt).put(update_system_prompt))
+        .route("/system-prompt/versions", get(list_system_prompt_versions))
+        .route("/system-prompt/versions/{version}/restore", post(restore_system_prompt_version))
+        .route("/system-prompt/preview", post(preview_system_prompt))
+}
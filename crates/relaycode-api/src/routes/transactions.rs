@@ -1,8 +1,8 @@
-use relaycode_schema::{BulkActionRequest, BulkActionResponse, Transaction, UpdateStatusRequest, ReapplyFileRequest};
-use relaycode_core::{reapply_file, reapply_all_failed, start_simulation, STORE};
+use relaycode_schema::{BulkActionRequest, BulkActionResponse, ChainDiff, ConflictReport, DiagnosticsReport, ImportPatchRequest, NewTransaction, Transaction, TransactionChain, UpdateStatusRequest, ReapplyFileRequest, UpdateFileDiffRequest, ValidationErrors};
+use relaycode_core::{chain, conflicts, handoff, migrate, ingest_text, ingest_transaction, reapply_file, reapply_all_failed, revert_transaction, revert_transactions, start_simulation, ApplyOptions, DiffEditError, STORE};
 use axum::{
     extract::Query,
-    routing::{get, patch, post},
+    routing::{get, patch, post, put},
     Json, Router,
 };
 use serde::Deserialize;
@@ -41,15 +41,58 @@ fn default_limit() -> usize {
 pub async fn list_transactions(
     Query(params): Query<ListQuery>,
 ) -> Json<Vec<Transaction>> {
-    let transactions = STORE.get_transactions(
+    let mut transactions = STORE.get_transactions(
         params.limit,
         params.page,
         params.search.as_deref(),
         params.status.as_deref(),
     );
+    conflicts::flag(&mut transactions);
     Json(transactions)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/transactions",
+    tag = "Transactions",
+    request_body = NewTransaction,
+    responses(
+        (status = 201, body = Transaction, description = "Transaction ingested; applied immediately when approvalMode is auto"),
+        (status = 422, body = ValidationErrors, description = "Not a valid transaction")
+    )
+)]
+pub async fn create_transaction(
+    Json(mut body): Json<serde_json::Value>,
+) -> Result<(axum::http::StatusCode, Json<Transaction>), (axum::http::StatusCode, Json<ValidationErrors>)> {
+    // Older clients embed each file in its block.
+    migrate::legacy_transaction(&mut body);
+    let new_tx: NewTransaction = serde_json::from_value(body).map_err(|err| {
+        (axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrors { errors: vec![err.to_string()] }))
+    })?;
+    Ok((axum::http::StatusCode::CREATED, Json(ingest_transaction(new_tx))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/transactions/import",
+    tag = "Transactions",
+    request_body = ImportPatchRequest,
+    responses(
+        (status = 201, body = [Transaction], description = "One transaction per patch; a format-patch series is chained via parentId"),
+        (status = 422, description = "The text contains no file changes")
+    )
+)]
+pub async fn import_transactions(
+    Json(body): Json<ImportPatchRequest>,
+) -> Result<(axum::http::StatusCode, Json<Vec<Transaction>>), axum::http::StatusCode> {
+    let source = body.source.as_deref().unwrap_or("import");
+    let transactions = ingest_text(&body.text, source);
+    if transactions.is_empty() {
+        return Err(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    Ok((axum::http::StatusCode::CREATED, Json(transactions)))
+}
+
 #[utoipa::path(
     patch,
     path = "/api/transactions/{id}/status",
@@ -60,21 +103,36 @@ pub async fn list_transactions(
     request_body = UpdateStatusRequest,
     responses(
         (status = 200, body = Transaction),
-        (status = 404, description = "Transaction not found")
+        (status = 404, description = "Transaction not found"),
+        (status = 409, description = "`APPLIED` or `COMMITTED` while a validation command failed and `overrideValidation` was not set, or `REVERTED` when the files could not be restored on disk")
     )
 )]
 pub async fn update_transaction_status(
     axum::extract::Path(id): axum::extract::Path<String>,
     Json(body): Json<UpdateStatusRequest>,
 ) -> Result<Json<Transaction>, axum::http::StatusCode> {
+    let override_validation = body.override_validation.unwrap_or(false);
+
     if body.status == relaycode_schema::TransactionStatus::Applying {
-        start_simulation(id.clone(), body.scenario.clone());
+        start_simulation(id.clone(), ApplyOptions::manual(body.scenario.clone(), override_validation));
         
         if let Some(tx) = STORE.get_transaction(&id) {
             return Ok(Json(tx));
         }
     }
 
+    if body.status == relaycode_schema::TransactionStatus::Reverted {
+        return match revert_transaction(&id).await {
+            Some(Ok(tx)) => Ok(Json(tx)),
+            Some(Err(_)) => Err(axum::http::StatusCode::CONFLICT),
+            None => Err(axum::http::StatusCode::NOT_FOUND),
+        };
+    }
+
+    if !STORE.allow_manual_status(&id, &body.status, override_validation) {
+        return Err(axum::http::StatusCode::CONFLICT);
+    }
+
     match STORE.update_transaction_status(&id, body.status) {
         Some(tx) => Ok(Json(tx)),
         None => Err(axum::http::StatusCode::NOT_FOUND),
@@ -87,16 +145,22 @@ pub async fn update_transaction_status(
     tag = "Transactions",
     request_body = BulkActionRequest,
     responses(
-        (status = 200, body = BulkActionResponse)
+        (status = 200, body = BulkActionResponse, description = "`rejectedIds` lists transactions whose validation failed and was not overridden, or whose files could not be restored when reverting")
     )
 )]
 pub async fn bulk_update_transactions(
     Json(body): Json<BulkActionRequest>,
 ) -> Json<BulkActionResponse> {
-    let updated_ids = STORE.update_transaction_status_bulk(&body.ids, body.action);
+    let override_validation = body.override_validation.unwrap_or(false);
+    let (updated_ids, rejected_ids) = if body.action == relaycode_schema::TransactionStatus::Reverted {
+        revert_transactions(&body.ids).await
+    } else {
+        STORE.update_transaction_status_bulk(&body.ids, body.action, override_validation)
+    };
     Json(BulkActionResponse {
-        success: true,
+        success: rejected_ids.is_empty(),
         updated_ids,
+        rejected_ids,
     })
 }
 
@@ -120,6 +184,41 @@ pub async fn reapply_single_file(
     Ok(Json(serde_json::json!({ "success": true })))
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/transactions/{id}/files/diff",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    request_body = UpdateFileDiffRequest,
+    responses(
+        (status = 200, body = Transaction, description = "Diff replaced; the file is being re-applied"),
+        (status = 404, description = "Transaction not found"),
+        (status = 409, body = ValidationErrors, description = "The file has not failed"),
+        (status = 422, body = ValidationErrors, description = "Unknown path or the diff does not parse")
+    )
+)]
+pub async fn update_file_diff(
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(body): Json<UpdateFileDiffRequest>,
+) -> Result<Json<Transaction>, (axum::http::StatusCode, Json<ValidationErrors>)> {
+    match STORE.replace_file_diff(&id, &body) {
+        Some(Ok(tx)) => {
+            reapply_file(id, body.file_path);
+            Ok(Json(tx))
+        }
+        Some(Err(DiffEditError::Invalid(errors))) => Err((axum::http::StatusCode::UNPROCESSABLE_ENTITY, Json(ValidationErrors { errors }))),
+        Some(Err(DiffEditError::NotFailed(message))) => {
+            Err((axum::http::StatusCode::CONFLICT, Json(ValidationErrors { errors: vec![message] })))
+        }
+        None => Err((
+            axum::http::StatusCode::NOT_FOUND,
+            Json(ValidationErrors { errors: vec![format!("No transaction {}", id)] }),
+        )),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/api/transactions/{id}/reapply-failed",
@@ -138,11 +237,174 @@ pub async fn reapply_all_failed_files(
     Ok(Json(serde_json::json!({ "success": true })))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/transactions/{id}/handoff",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    responses(
+        (status = 200, content_type = "text/markdown", body = String, description = "Prompt for an external AI agent covering every failed file"),
+        (status = 404, description = "Transaction not found"),
+        (status = 409, body = ValidationErrors, description = "No file has failed")
+    )
+)]
+pub async fn get_transaction_handoff(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<([(axum::http::header::HeaderName, &'static str); 1], String), (axum::http::StatusCode, Json<ValidationErrors>)> {
+    match handoff::render(&id) {
+        Some(Ok(markdown)) => Ok(([(axum::http::header::CONTENT_TYPE, "text/markdown; charset=utf-8")], markdown)),
+        Some(Err(message)) => Err((axum::http::StatusCode::CONFLICT, Json(ValidationErrors { errors: vec![message] }))),
+        None => Err((
+            axum::http::StatusCode::NOT_FOUND,
+            Json(ValidationErrors { errors: vec![format!("No transaction {}", id)] }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/transactions/{id}/diagnostics",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    responses(
+        (status = 200, body = DiagnosticsReport),
+        (status = 404, description = "Transaction not found")
+    )
+)]
+pub async fn get_transaction_diagnostics(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<DiagnosticsReport>, axum::http::StatusCode> {
+    STORE
+        .get_diagnostics(&id)
+        .map(Json)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/transactions/{id}/chain",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    responses(
+        (status = 200, body = TransactionChain),
+        (status = 404, description = "Transaction not found")
+    )
+)]
+pub async fn get_transaction_chain(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<TransactionChain>, axum::http::StatusCode> {
+    chain::build_chain(&id).map(Json).ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/transactions/{id}/chain/apply",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "Any transaction in the chain")
+    ),
+    responses(
+        (status = 200, body = BulkActionResponse, description = "Pending members queued, applied one after another in chain order"),
+        (status = 404, description = "Transaction not found")
+    )
+)]
+pub async fn apply_transaction_chain(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<BulkActionResponse>, axum::http::StatusCode> {
+    let updated_ids = chain::apply_chain(&id).ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    Ok(Json(BulkActionResponse {
+        success: true,
+        updated_ids,
+        rejected_ids: Vec::new(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/transactions/{id}/chain/revert",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "First transaction to revert; its descendants are reverted too")
+    ),
+    responses(
+        (status = 200, body = BulkActionResponse, description = "Reverted members, deepest first"),
+        (status = 404, description = "Transaction not found"),
+        (status = 409, body = ValidationErrors, description = "A member's files could not be restored on disk")
+    )
+)]
+pub async fn revert_transaction_chain(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<BulkActionResponse>, (axum::http::StatusCode, Json<ValidationErrors>)> {
+    match chain::revert_from(&id).await {
+        Some(Ok(updated_ids)) => Ok(Json(BulkActionResponse {
+            success: true,
+            updated_ids,
+            rejected_ids: Vec::new(),
+        })),
+        Some(Err(message)) => Err((axum::http::StatusCode::CONFLICT, Json(ValidationErrors { errors: vec![message] }))),
+        None => Err((
+            axum::http::StatusCode::NOT_FOUND,
+            Json(ValidationErrors { errors: vec![format!("No transaction {}", id)] }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/transactions/{id}/chain/diff",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "Any transaction in the chain")
+    ),
+    responses(
+        (status = 200, body = ChainDiff),
+        (status = 404, description = "Transaction not found")
+    )
+)]
+pub async fn get_chain_diff(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<ChainDiff>, axum::http::StatusCode> {
+    chain::cumulative_diff(&id).map(Json).ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/transactions/{id}/conflicts",
+    tag = "Transactions",
+    params(
+        ("id" = String, Path, description = "Transaction ID")
+    ),
+    responses(
+        (status = 200, body = ConflictReport),
+        (status = 404, description = "Transaction not found")
+    )
+)]
+pub async fn get_transaction_conflicts(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<ConflictReport>, axum::http::StatusCode> {
+    conflicts::report(&id).map(Json).ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
 pub fn router() -> Router {
     Router::new()
-        .route("/transactions", get(list_transactions))
+        .route("/transactions", get(list_transactions).post(create_transaction))
         .route("/transactions/{id}/status", patch(update_transaction_status))
+        .route("/transactions/import", post(import_transactions))
         .route("/transactions/bulk", post(bulk_update_transactions))
         .route("/transactions/{id}/files/reapply", post(reapply_single_file))
+        .route("/transactions/{id}/files/diff", put(update_file_diff))
         .route("/transactions/{id}/reapply-failed", post(reapply_all_failed_files))
+        .route("/transactions/{id}/diagnostics", get(get_transaction_diagnostics))
+        .route("/transactions/{id}/handoff", get(get_transaction_handoff))
+        .route("/transactions/{id}/conflicts", get(get_transaction_conflicts))
+        .route("/transactions/{id}/chain", get(get_transaction_chain))
+        .route("/transactions/{id}/chain/apply", post(apply_transaction_chain))
+        .route("/transactions/{id}/chain/revert", post(revert_transaction_chain))
+        .route("/transactions/{id}/chain/diff", get(get_chain_diff))
 }
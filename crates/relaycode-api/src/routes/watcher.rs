@@ -0,0 +1,58 @@
+use relaycode_schema::WatcherStatus;
+use relaycode_core::STORE;
+use axum::{
+    routing::{get, post},
+    Json, Router,
+};
+
+#[utoipa::path(
+    get,
+    path = "/api/watcher",
+    tag = "Watcher",
+    responses(
+        (status = 200, body = WatcherStatus)
+    )
+)]
+pub async fn get_watcher_status() -> Json<WatcherStatus> {
+    Json(STORE.get_watcher_status())
+}
+
+fn set_paused(paused: bool) -> Result<Json<WatcherStatus>, axum::http::StatusCode> {
+    STORE.set_watcher_paused(paused).map(Json).map_err(|err| {
+        tracing::error!("Failed to persist watcher state: {}", err);
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/watcher/pause",
+    tag = "Watcher",
+    responses(
+        (status = 200, body = WatcherStatus),
+        (status = 500, description = "Could not write .relay/state.json")
+    )
+)]
+pub async fn pause_watcher() -> Result<Json<WatcherStatus>, axum::http::StatusCode> {
+    set_paused(true)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/watcher/resume",
+    tag = "Watcher",
+    responses(
+        (status = 200, body = WatcherStatus),
+        (status = 500, description = "Could not write .relay/state.json")
+    )
+)]
+pub async fn resume_watcher() -> Result<Json<WatcherStatus>, axum::http::StatusCode> {
+    set_paused(false)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/watcher", get(get_watcher_status))
+        .route("/watcher/pause", post(pause_watcher))
+        .route("/watcher/resume", post(resume_watcher))
+}
@@ -0,0 +1,242 @@
+use crate::{apply, diff};
+use relaycode_schema::AiProviderConfig;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub const MOCK_PROVIDER: &str = "mock";
+
+/// Overrides `aiProvider.apiKey`, so keys can stay out of the config file.
+pub const API_KEY_ENV: &str = "RELAYCODE_API_KEY";
+
+#[derive(Debug)]
+pub enum AiError {
+    /// The request never produced a response: connection, timeout, or a non-success status.
+    Request(String),
+    /// The provider answered, but not with anything usable.
+    Response(String),
+}
+
+impl std::fmt::Display for AiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AiError::Request(message) => write!(f, "provider request failed: {}", message),
+            AiError::Response(message) => write!(f, "unusable provider response: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AiError {}
+
+/// What a patch repair is asked to fix.
+#[derive(Debug, Clone)]
+pub struct RepairContext {
+    pub path: String,
+    pub language: String,
+    /// The file as it is on disk now.
+    pub content: String,
+    /// The file's whole diff.
+    pub diff: String,
+    /// The hunks of `diff` that do not apply, header line included.
+    pub failed_hunks: Vec<String>,
+    pub error: String,
+}
+
+/// The job behind a prompt. HTTP providers only read the prompt text; the mock provider answers
+/// from the structured context instead.
+#[derive(Debug, Clone)]
+pub enum Task {
+    RepairPatch(RepairContext),
+    /// Carries the deterministic summary of the transactions, which the mock provider returns.
+    CommitMessage(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CompletionRequest {
+    pub system: String,
+    pub prompt: String,
+    /// `None` uses the provider's default model.
+    pub model: Option<String>,
+    pub task: Task,
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub model: String,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+pub type CompletionFuture<'a> = Pin<Box<dyn Future<Output = Result<Completion, AiError>> + Send + 'a>>;
+
+pub trait AiProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> CompletionFuture<'a>;
+}
+
+/// API base URLs of providers that can be configured by name alone.
+pub fn default_base_url(name: &str) -> Option<&'static str> {
+    match name {
+        "openai" => Some("https://api.openai.com/v1"),
+        "openrouter" => Some("https://openrouter.ai/api/v1"),
+        "ollama" => Some("http://localhost:11434/v1"),
+        _ => None,
+    }
+}
+
+/// Builds the provider `aiProvider` describes.
+pub fn provider_from_config(config: &AiProviderConfig) -> Result<Arc<dyn AiProvider>, AiError> {
+    if config.name == MOCK_PROVIDER {
+        return Ok(Arc::new(MockProvider));
+    }
+    let base_url = if config.base_url.is_empty() {
+        default_base_url(&config.name)
+            .ok_or_else(|| AiError::Request(format!("no baseUrl configured for provider {}", config.name)))?
+            .to_string()
+    } else {
+        config.base_url.clone()
+    };
+    let api_key = std::env::var(API_KEY_ENV).unwrap_or_else(|_| config.api_key.clone());
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_millis(config.timeout))
+        .build()
+        .map_err(|err| AiError::Request(err.to_string()))?;
+    Ok(Arc::new(OpenAiCompatible {
+        name: config.name.clone(),
+        base_url: base_url.trim_end_matches('/').to_string(),
+        api_key,
+        default_model: config.default_model.clone(),
+        client,
+    }))
+}
+
+/// Any API that speaks the OpenAI `chat/completions` protocol.
+pub struct OpenAiCompatible {
+    name: String,
+    base_url: String,
+    api_key: String,
+    default_model: String,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatible {
+    async fn send(&self, request: &CompletionRequest) -> Result<Completion, AiError> {
+        let model = request.model.clone().unwrap_or_else(|| self.default_model.clone());
+        let body = serde_json::json!({
+            "model": model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": request.system },
+                { "role": "user", "content": request.prompt },
+            ],
+        });
+        let mut http = self.client.post(format!("{}/chat/completions", self.base_url)).json(&body);
+        if !self.api_key.is_empty() {
+            http = http.bearer_auth(&self.api_key);
+        }
+        let response = http.send().await.map_err(|err| AiError::Request(err.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(AiError::Request(format!("{}: {}", status, text.trim())));
+        }
+        let json: serde_json::Value = response.json().await.map_err(|err| AiError::Response(err.to_string()))?;
+        let text = json["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or_else(|| AiError::Response("no choices[0].message.content".to_string()))?;
+        Ok(Completion {
+            text: text.to_string(),
+            model: json["model"].as_str().map(str::to_string).unwrap_or(model),
+            input_tokens: json["usage"]["prompt_tokens"].as_u64(),
+            output_tokens: json["usage"]["completion_tokens"].as_u64(),
+        })
+    }
+}
+
+impl AiProvider for OpenAiCompatible {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> CompletionFuture<'a> {
+        Box::pin(self.send(request))
+    }
+}
+
+/// Offline provider with fixed answers, so repairs and commit messages can be exercised without a model.
+pub struct MockProvider;
+
+impl AiProvider for MockProvider {
+    fn name(&self) -> &str {
+        MOCK_PROVIDER
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> CompletionFuture<'a> {
+        let result = match &request.task {
+            Task::RepairPatch(context) => relocate_hunks(context).map(|diff| format!("```diff\n{}\n```", diff)),
+            Task::CommitMessage(summary) => Ok(summary.clone()),
+        };
+        Box::pin(async move {
+            result.map(|text| Completion {
+                text,
+                model: MOCK_PROVIDER.to_string(),
+                input_tokens: None,
+                output_tokens: None,
+            })
+        })
+    }
+}
+
+/// The mock's repair: finds where each hunk's removed lines sit in the current file, ignoring
+/// indentation and trailing space, and rewrites the hunk's old side from the file as it is now.
+fn relocate_hunks(context: &RepairContext) -> Result<String, AiError> {
+    let lines: Vec<&str> = context.content.lines().collect();
+    let mut hunks = Vec::new();
+    let mut cursor = 0;
+    let mut offset: isize = 0;
+
+    for (n, hunk) in diff::parse_hunks(&context.diff).iter().enumerate() {
+        let old: Vec<&diff::DiffLine> = hunk.lines.iter().filter(|l| l.kind != diff::LineKind::Added).collect();
+        let fits = |at: usize, strict: bool| {
+            at + old.len() <= lines.len()
+                && old.iter().zip(&lines[at..]).all(|(line, actual)| {
+                    line.text.trim() == actual.trim() || (!strict && line.kind == diff::LineKind::Context)
+                })
+        };
+        // Context lines may have drifted as long as the removed lines still anchor the hunk.
+        let anchored = old.iter().any(|l| l.kind == diff::LineKind::Removed);
+        let found = (cursor..=lines.len())
+            .find(|&at| fits(at, true))
+            .or_else(|| (cursor..=lines.len()).find(|&at| anchored && fits(at, false)))
+            .ok_or_else(|| AiError::Response(format!("could not place hunk {} of {}", n + 1, context.path)))?;
+
+        let mut body = Vec::new();
+        let mut at = found;
+        for line in &hunk.lines {
+            match line.kind {
+                diff::LineKind::Added => body.push(format!("+{}", line.text)),
+                diff::LineKind::Removed => {
+                    body.push(format!("-{}", lines[at]));
+                    at += 1;
+                }
+                diff::LineKind::Context => {
+                    body.push(format!(" {}", lines[at]));
+                    at += 1;
+                }
+            }
+        }
+        let old_count = old.len();
+        let new_count = hunk.lines.len() - hunk.lines.iter().filter(|l| l.kind == diff::LineKind::Removed).count();
+        // Empty sides name the line they follow; others their first line.
+        let old_start = found + usize::from(old_count > 0);
+        let new_start = (found as isize + offset).max(0) as usize + usize::from(new_count > 0);
+        hunks.push(format!("@@ -{},{} +{},{} @@\n{}", old_start, old_count, new_start, new_count, body.join("\n")));
+        offset += new_count as isize - old_count as isize;
+        cursor = found + old_count;
+    }
+
+    let repaired = hunks.join("\n");
+    apply::apply_hunks(&context.content, &repaired).map_err(AiError::Response)?;
+    Ok(repaired)
+}
@@ -0,0 +1,268 @@
+use crate::STORE;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use relaycode_schema::{
+    AnalyticsBucket, AnalyticsGroupBy, AnalyticsPoint, AnalyticsReport, AnalyticsSeries, Cost,
+    TokenUsage, Transaction, TransactionStatus, UsageStats,
+};
+use std::collections::BTreeMap;
+
+/// An RFC 3339 instant, or a `YYYY-MM-DD` date read as midnight UTC. `end_of_day` moves a bare
+/// date to the following midnight, so `to=2026-02-12` still covers that day.
+fn parse_bound(name: &str, value: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(instant) = DateTime::parse_from_rfc3339(value) {
+        return Ok(instant.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("{}: expected an RFC 3339 timestamp or YYYY-MM-DD, got {:?}", name, value))?;
+    let midnight = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is valid"));
+    Ok(if end_of_day { midnight + Duration::days(1) } else { midnight })
+}
+
+fn bucket_start(at: DateTime<Utc>, bucket: AnalyticsBucket) -> DateTime<Utc> {
+    let hour = at
+        .with_minute(0)
+        .and_then(|t| t.with_second(0))
+        .and_then(|t| t.with_nanosecond(0))
+        .unwrap_or(at);
+    let day = hour.with_hour(0).unwrap_or(hour);
+    match bucket {
+        AnalyticsBucket::Hour => hour,
+        AnalyticsBucket::Day => day,
+        AnalyticsBucket::Week => day - Duration::days(i64::from(day.weekday().num_days_from_monday())),
+    }
+}
+
+fn status_key(status: &TransactionStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn stats(transactions: &[&Transaction]) -> UsageStats {
+    let mut by_status: BTreeMap<String, u64> = BTreeMap::new();
+    let mut tokens = TokenUsage::default();
+    let mut costs: Vec<Cost> = Vec::new();
+    let (mut succeeded, mut finished, mut files) = (0u64, 0u64, 0usize);
+
+    for tx in transactions {
+        *by_status.entry(status_key(&tx.status)).or_default() += 1;
+        match tx.status {
+            TransactionStatus::Applied | TransactionStatus::Committed => {
+                succeeded += 1;
+                finished += 1;
+            }
+            TransactionStatus::PartiallyApplied | TransactionStatus::Failed => finished += 1,
+            _ => {}
+        }
+        files += tx.files.len();
+
+        tokens.input_tokens += tx.tokens.input_tokens;
+        tokens.output_tokens += tx.tokens.output_tokens;
+        tokens.cached_tokens += tx.tokens.cached_tokens;
+        tokens.total_tokens += tx.tokens.total_tokens;
+
+        match costs.iter_mut().find(|c| c.currency == tx.cost.currency) {
+            Some(total) => {
+                total.amount += tx.cost.amount;
+                total.estimated |= tx.cost.estimated;
+            }
+            None => costs.push(tx.cost.clone()),
+        }
+    }
+    costs.sort_by(|a, b| a.currency.cmp(&b.currency));
+
+    let count = transactions.len() as u64;
+    UsageStats {
+        transactions: count,
+        by_status,
+        apply_success_rate: (finished > 0).then(|| succeeded as f64 / finished as f64),
+        avg_files_per_transaction: if count == 0 { 0.0 } else { files as f64 / count as f64 },
+        tokens,
+        costs,
+    }
+}
+
+fn series(key: String, label: String, transactions: &[(DateTime<Utc>, &Transaction)], bucket: AnalyticsBucket) -> AnalyticsSeries {
+    let mut buckets: BTreeMap<DateTime<Utc>, Vec<&Transaction>> = BTreeMap::new();
+    for (at, tx) in transactions {
+        buckets.entry(bucket_start(*at, bucket)).or_default().push(tx);
+    }
+    let all: Vec<&Transaction> = transactions.iter().map(|(_, tx)| *tx).collect();
+    AnalyticsSeries {
+        key,
+        label,
+        totals: stats(&all),
+        points: buckets
+            .into_iter()
+            .map(|(start, txs)| AnalyticsPoint {
+                start,
+                stats: stats(&txs),
+            })
+            .collect(),
+    }
+}
+
+/// Usage aggregates over the stored transactions created in `[from, to)`, bucketed by
+/// `bucket` and split into one series per `group_by` value. Errors are malformed bounds.
+pub fn report(
+    bucket: AnalyticsBucket,
+    group_by: Option<AnalyticsGroupBy>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<AnalyticsReport, Vec<String>> {
+    let mut errors = Vec::new();
+    let mut bound = |name, value: Option<&str>, end_of_day| {
+        value.and_then(|value| parse_bound(name, value, end_of_day).map_err(|err| errors.push(err)).ok())
+    };
+    let from_at = bound("from", from, false);
+    let to_at = bound("to", to, true);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let all = STORE.get_all_transactions();
+    let mut dated: Vec<(DateTime<Utc>, &Transaction)> = Vec::new();
+    for tx in &all {
+        let at = tx.created_at;
+        if from_at.is_some_and(|from| at < from) || to_at.is_some_and(|to| at >= to) {
+            continue;
+        }
+        dated.push((at, tx));
+    }
+
+    let titles: BTreeMap<String, String> = STORE.get_prompts().into_iter().map(|p| (p.id, p.title)).collect();
+    let mut groups: BTreeMap<String, Vec<(DateTime<Utc>, &Transaction)>> = BTreeMap::new();
+    for (at, tx) in &dated {
+        let key = match group_by {
+            None => "all",
+            Some(AnalyticsGroupBy::Provider) => tx.provider.as_str(),
+            Some(AnalyticsGroupBy::Model) => tx.model.as_str(),
+            Some(AnalyticsGroupBy::Author) => tx.author.as_str(),
+            Some(AnalyticsGroupBy::Prompt) => tx.prompt_id.as_str(),
+        };
+        groups.entry(key.to_string()).or_default().push((*at, tx));
+    }
+
+    let mut series: Vec<AnalyticsSeries> = groups
+        .into_iter()
+        .map(|(key, txs)| {
+            let label = match group_by {
+                _ if key.is_empty() => "unknown".to_string(),
+                Some(AnalyticsGroupBy::Prompt) => titles.get(&key).cloned().unwrap_or_else(|| key.clone()),
+                _ => key.clone(),
+            };
+            series(key, label, &txs, bucket)
+        })
+        .collect();
+    series.sort_by(|a, b| {
+        b.totals
+            .tokens
+            .total_tokens
+            .cmp(&a.totals.tokens.total_tokens)
+            .then(b.totals.transactions.cmp(&a.totals.transactions))
+    });
+
+    let totals = stats(&dated.iter().map(|(_, tx)| *tx).collect::<Vec<_>>());
+    Ok(AnalyticsReport {
+        bucket,
+        group_by,
+        from: from_at,
+        to: to_at,
+        totals,
+        series,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use relaycode_schema::NewTransaction;
+    use rust_decimal::Decimal;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn tx(created_at: &str, status: TransactionStatus, provider: &str, cost: (&str, &str)) -> Transaction {
+        let mut tx = crate::build_transaction(NewTransaction {
+            created_at: Some(at(created_at)),
+            provider: provider.to_string(),
+            ..NewTransaction::default()
+        });
+        tx.status = status;
+        tx.cost = Cost {
+            amount: cost.0.parse().unwrap(),
+            currency: cost.1.to_string(),
+            estimated: false,
+        };
+        tx
+    }
+
+    #[test]
+    fn weeks_start_on_monday_at_midnight() {
+        let cases = [
+            ("2026-02-15T23:59:59Z", AnalyticsBucket::Week, "2026-02-09T00:00:00Z"),
+            ("2026-02-16T00:00:00Z", AnalyticsBucket::Week, "2026-02-16T00:00:00Z"),
+            ("2026-03-01T12:00:00Z", AnalyticsBucket::Week, "2026-02-23T00:00:00Z"),
+            ("2026-02-15T23:59:59Z", AnalyticsBucket::Day, "2026-02-15T00:00:00Z"),
+            ("2026-02-15T23:59:59.5Z", AnalyticsBucket::Hour, "2026-02-15T23:00:00Z"),
+        ];
+        for (instant, bucket, start) in cases {
+            assert_eq!(bucket_start(at(instant), bucket), at(start), "{} {:?}", instant, bucket);
+        }
+    }
+
+    #[test]
+    fn a_bare_to_date_covers_that_whole_day() {
+        assert_eq!(parse_bound("to", "2026-02-12", true).unwrap(), at("2026-02-13T00:00:00Z"));
+        assert_eq!(parse_bound("from", "2026-02-12", false).unwrap(), at("2026-02-12T00:00:00Z"));
+        assert_eq!(parse_bound("to", "2026-02-12T08:00:00+02:00", true).unwrap(), at("2026-02-12T06:00:00Z"));
+        assert!(parse_bound("to", "12/02/2026", true).unwrap_err().starts_with("to: expected"));
+    }
+
+    #[test]
+    fn apply_success_rate_counts_only_finished_transactions() {
+        let statuses = [
+            TransactionStatus::Applied,
+            TransactionStatus::Committed,
+            TransactionStatus::PartiallyApplied,
+            TransactionStatus::Failed,
+            TransactionStatus::Pending,
+            TransactionStatus::Reverted,
+        ];
+        let txs: Vec<Transaction> = statuses.iter().map(|status| tx("2001-01-01T00:00:00Z", status.clone(), "", ("0", "USD"))).collect();
+        let stats = stats(&txs.iter().collect::<Vec<_>>());
+        assert_eq!(stats.apply_success_rate, Some(0.5));
+        assert_eq!(stats.by_status.get("PENDING"), Some(&1));
+        assert_eq!(super::stats(&[&txs[4]]).apply_success_rate, None);
+    }
+
+    #[tokio::test]
+    async fn report_filters_to_the_range_and_sums_costs_per_currency() {
+        let _store = crate::testing::lock().await;
+        let mut estimated = tx("2001-03-06T23:30:00Z", TransactionStatus::Applied, "openai", ("0.25", "USD"));
+        estimated.cost.estimated = true;
+        for tx in [
+            tx("2001-03-04T23:59:59Z", TransactionStatus::Applied, "openai", ("9", "USD")),
+            tx("2001-03-05T00:00:00Z", TransactionStatus::Applied, "openai", ("0.5", "USD")),
+            tx("2001-03-06T10:00:00Z", TransactionStatus::Failed, "", ("1.10", "EUR")),
+            estimated,
+            tx("2001-03-07T00:00:00Z", TransactionStatus::Applied, "openai", ("9", "USD")),
+        ] {
+            STORE.insert_transaction(tx);
+        }
+
+        let by_provider = report(AnalyticsBucket::Day, Some(AnalyticsGroupBy::Provider), Some("2001-03-05"), Some("2001-03-06")).unwrap();
+        assert_eq!(by_provider.totals.transactions, 3);
+        let costs: Vec<(String, Decimal, bool)> = by_provider.totals.costs.iter().map(|c| (c.currency.clone(), c.amount, c.estimated)).collect();
+        assert_eq!(costs, vec![("EUR".to_string(), "1.10".parse().unwrap(), false), ("USD".to_string(), "0.75".parse().unwrap(), true)]);
+        let labels: Vec<(&str, &str, u64)> = by_provider.series.iter().map(|s| (s.key.as_str(), s.label.as_str(), s.totals.transactions)).collect();
+        assert_eq!(labels, vec![("openai", "openai", 2), ("", "unknown", 1)]);
+        assert_eq!(by_provider.series[0].points.len(), 2, "one point per day");
+
+        let by_prompt = report(AnalyticsBucket::Week, Some(AnalyticsGroupBy::Prompt), Some("2001-03-05"), Some("2001-03-06")).unwrap();
+        assert_eq!(by_prompt.series.len(), 1);
+        assert_eq!((by_prompt.series[0].key.as_str(), by_prompt.series[0].label.as_str()), ("", "unknown"));
+    }
+}
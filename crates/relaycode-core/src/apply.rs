@@ -0,0 +1,493 @@
+use crate::diff::{self, LineKind};
+use relaycode_schema::{FileStatus, LineRange, TransactionFile};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug)]
+pub enum ApplyError {
+    /// The patch could not be placed and no usable base was available to merge from.
+    Failed(String),
+    /// A three-way merge collided; the file now holds conflict markers in these regions.
+    Conflict(Vec<LineRange>),
+}
+
+impl std::fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApplyError::Failed(message) => write!(f, "{}", message),
+            ApplyError::Conflict(regions) => write!(f, "Merge conflict in {} region(s)", regions.len()),
+        }
+    }
+}
+
+impl std::error::Error for ApplyError {}
+
+/// How a file patch ended up on disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Applied {
+    /// The hunks matched the current file, possibly at an offset.
+    Direct,
+    /// The file had drifted; the patch was merged in from its base.
+    Merged,
+}
+
+/// Lines without their terminators, plus whether the text ended with a newline.
+fn split_lines(text: &str) -> (Vec<&str>, bool) {
+    let trailing_newline = text.ends_with('\n');
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    let lines = if body.is_empty() && !text.starts_with('\n') {
+        Vec::new()
+    } else {
+        body.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect()
+    };
+    (lines, trailing_newline)
+}
+
+fn join_lines<S: AsRef<str>>(lines: &[S], trailing_newline: bool) -> String {
+    let mut text = lines.iter().map(AsRef::as_ref).collect::<Vec<_>>().join("\n");
+    if trailing_newline && !lines.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+/// Applies `diff` to `original`. Each hunk is looked for at its stated line first, then at the
+/// nearest position below the previous hunk where its old side matches exactly.
+pub fn apply_hunks(original: &str, diff: &str) -> Result<String, String> {
+    let hunks = diff::parse_hunks(diff);
+    if hunks.is_empty() {
+        return Err("Patch has no hunks".to_string());
+    }
+    let (lines, trailing_newline) = split_lines(original);
+    let mut result: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut cursor = 0;
+    let mut offset: isize = 0;
+
+    for (n, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk.lines.iter().filter(|l| l.kind != LineKind::Added).map(|l| l.text.as_str()).collect();
+        let new: Vec<&str> = hunk.lines.iter().filter(|l| l.kind != LineKind::Removed).map(|l| l.text.as_str()).collect();
+        let matches_at = |at: usize| at + old.len() <= lines.len() && lines[at..at + old.len()] == old[..];
+
+        // `@@ -0,0` (new file) and `@@ -5,0` (insert after line 5) both start right after old_start.
+        let stated = if hunk.old_count == 0 { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+        let expected = (stated as isize + offset).clamp(cursor as isize, lines.len() as isize) as usize;
+        let found = (0..=lines.len())
+            .flat_map(|distance| [expected.checked_add(distance), expected.checked_sub(distance)])
+            .flatten()
+            .filter(|&at| at >= cursor && at <= lines.len())
+            .find(|&at| matches_at(at))
+            .ok_or_else(|| format!("Hunk {} (line {}) does not match the file", n + 1, hunk.old_start))?;
+
+        result.extend_from_slice(&lines[cursor..found]);
+        result.extend_from_slice(&new);
+        offset = found as isize - stated as isize;
+        cursor = found + old.len();
+    }
+    result.extend_from_slice(&lines[cursor..]);
+    Ok(join_lines(&result, trailing_newline || original.is_empty()))
+}
+
+/// The hunks of `diff` that cannot be placed in `content` on their own, each as standalone diff text.
+pub fn failed_hunks(content: &str, diff: &str) -> Vec<String> {
+    crate::review::hunk_texts(diff)
+        .into_iter()
+        .filter(|hunk| apply_hunks(content, hunk).is_err())
+        .collect()
+}
+
+/// For each line of `base`, the index of the line it is matched with in `other`.
+fn matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal { old_index, new_index, len } = op {
+            for i in 0..len {
+                matched[old_index + i] = Some(new_index + i);
+            }
+        }
+    }
+    matched
+}
+
+pub struct Merge {
+    pub text: String,
+    /// Regions wrapped in conflict markers, counted in the merged text.
+    pub conflicts: Vec<LineRange>,
+}
+
+/// Line-based diff3 merge of two edits of `base`. Where both sides changed the same lines
+/// differently, both versions are kept between `<<<<<<<`/`|||||||`/`=======`/`>>>>>>>` markers.
+pub fn merge3(base: &str, current: &str, patched: &str) -> Merge {
+    let (base_lines, _) = split_lines(base);
+    let (ours, ours_newline) = split_lines(current);
+    let (theirs, theirs_newline) = split_lines(patched);
+    let (ours_match, theirs_match) = (matches(&base_lines, &ours), matches(&base_lines, &theirs));
+
+    let mut output: Vec<&str> = Vec::new();
+    let mut conflicts = Vec::new();
+    let (mut b, mut o, mut t) = (0, 0, 0);
+    loop {
+        // Copy the stretch all three agree on.
+        while b < base_lines.len() && ours_match[b] == Some(o) && theirs_match[b] == Some(t) {
+            output.push(base_lines[b]);
+            b += 1;
+            o += 1;
+            t += 1;
+        }
+        // The next base line both sides still have ends the unstable chunk.
+        let next = (b..base_lines.len()).find(|&i| {
+            ours_match[i].is_some_and(|at| at >= o) && theirs_match[i].is_some_and(|at| at >= t)
+        });
+        let (end_b, end_o, end_t) = match next {
+            Some(i) => (i, ours_match[i].unwrap(), theirs_match[i].unwrap()),
+            None => (base_lines.len(), ours.len(), theirs.len()),
+        };
+        let (base_chunk, ours_chunk, theirs_chunk) = (&base_lines[b..end_b], &ours[o..end_o], &theirs[t..end_t]);
+
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            output.extend_from_slice(theirs_chunk);
+        } else if theirs_chunk == base_chunk {
+            output.extend_from_slice(ours_chunk);
+        } else {
+            let start = output.len() + 1;
+            output.push("<<<<<<< current");
+            output.extend_from_slice(ours_chunk);
+            output.push("||||||| base");
+            output.extend_from_slice(base_chunk);
+            output.push("=======");
+            output.extend_from_slice(theirs_chunk);
+            output.push(">>>>>>> patch");
+            conflicts.push(LineRange {
+                start,
+                end: output.len(),
+            });
+        }
+
+        (b, o, t) = (end_b, end_o, end_t);
+        if next.is_none() {
+            break;
+        }
+    }
+
+    Merge {
+        text: join_lines(&output, ours_newline || theirs_newline),
+        conflicts,
+    }
+}
+
+/// Undoes a file patch written by [`apply_file`]: the approved diff is reversed, a creation
+/// becomes a deletion and the other way round, and a rename moves the file back.
+pub fn revert_file(root: &Path, file: &TransactionFile) -> Result<Applied, ApplyError> {
+    let status = match file.status {
+        FileStatus::Created => FileStatus::Deleted,
+        FileStatus::Deleted => FileStatus::Created,
+        FileStatus::Modified => FileStatus::Modified,
+        FileStatus::Renamed => FileStatus::Renamed,
+    };
+    let (path, old_path) = match (&file.status, &file.old_path) {
+        (FileStatus::Renamed, Some(old_path)) => (old_path.clone(), Some(file.path.clone())),
+        _ => (file.path.clone(), None),
+    };
+    let reversed = TransactionFile {
+        path,
+        old_path,
+        status,
+        diff: diff::reverse(&crate::review::approved_diff(file).diff),
+        base_content: None,
+        ..file.clone()
+    };
+    apply_file(root, &reversed)
+}
+
+/// What a file patch is about to overwrite, so [`restore`] can put it back byte for byte.
+pub struct Snapshot(Vec<(PathBuf, Option<Vec<u8>>)>);
+
+/// Records the current content of every path [`apply_file`] would write or remove for `file`.
+pub fn snapshot(root: &Path, file: &TransactionFile) -> Snapshot {
+    let paths = [Some(file.path.as_str()), file.old_path.as_deref()];
+    Snapshot(
+        paths
+            .into_iter()
+            .flatten()
+            .filter_map(|relative| resolve_in_root(root, relative).ok())
+            .map(|path| {
+                let content = std::fs::read(&path).ok();
+                (path, content)
+            })
+            .collect(),
+    )
+}
+
+/// Puts every path of `snapshot` back as it was, removing the ones that did not exist.
+pub fn restore(snapshot: &Snapshot) -> std::io::Result<()> {
+    for (path, content) in &snapshot.0 {
+        match content {
+            Some(bytes) => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(path, bytes)?;
+            }
+            None => match std::fs::remove_file(path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            },
+        }
+    }
+    Ok(())
+}
+
+/// The committed version of `path`, used as the merge base when the transaction carries none.
+fn git_head_content(root: &Path, path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["show", &format!("HEAD:./{}", path)])
+        .current_dir(root)
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `relative` joined onto `root`, or an error when it could point outside it: absolute paths,
+/// `..` components, and paths whose nearest existing ancestor resolves (through symlinks) elsewhere.
+pub fn resolve_in_root(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let path = Path::new(relative);
+    if relative.trim().is_empty() {
+        return Err("Empty file path".to_string());
+    }
+    if path.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(format!("{}: path must stay inside the project root", relative));
+    }
+    let joined = root.join(path);
+    let root = root.canonicalize().map_err(|err| format!("{}: {}", root.display(), err))?;
+    let existing = joined.ancestors().find(|p| p.exists()).unwrap_or(&root);
+    let resolved = existing.canonicalize().map_err(|err| format!("{}: {}", relative, err))?;
+    if !resolved.starts_with(&root) {
+        return Err(format!("{}: path must stay inside the project root", relative));
+    }
+    Ok(joined)
+}
+
+/// Writes `text`, which uses `\n`, with CRLF endings when the file had them.
+fn with_line_endings(text: &str, crlf: bool) -> String {
+    if crlf {
+        text.replace("\r\n", "\n").replace('\n', "\r\n")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Writes one file's patch under `root`, falling back to a three-way merge from the file's base
+/// (its `baseContent`, else the `HEAD` version) when the hunks no longer match the file. Renames
+/// read `oldPath`, write `path` and then remove `oldPath`. Blocks on disk and `git`, so async
+/// callers run it on the blocking pool.
+pub fn apply_file(root: &Path, file: &TransactionFile) -> Result<Applied, ApplyError> {
+    let path = resolve_in_root(root, &file.path).map_err(ApplyError::Failed)?;
+    let source = match file.old_path.as_deref().filter(|old| *old != file.path) {
+        Some(old) if matches!(file.status, FileStatus::Renamed) => {
+            Some(resolve_in_root(root, old).map_err(ApplyError::Failed)?)
+        }
+        _ => None,
+    };
+    let io_error = |err: std::io::Error| ApplyError::Failed(format!("{}: {}", file.path, err));
+
+    if matches!(file.status, FileStatus::Deleted) {
+        return match std::fs::remove_file(&path) {
+            Ok(()) => Ok(Applied::Direct),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Applied::Direct),
+            Err(err) => Err(io_error(err)),
+        };
+    }
+
+    // Creating a file that is already there only succeeds when it already holds the patch.
+    if matches!(file.status, FileStatus::Created) && path.exists() {
+        let existing = std::fs::read_to_string(&path).map_err(io_error)?;
+        let created = apply_hunks("", &file.diff).map_err(ApplyError::Failed)?;
+        return if existing.replace("\r\n", "\n") == created {
+            Ok(Applied::Direct)
+        } else {
+            Err(ApplyError::Failed(format!("{}: file already exists with different content", file.path)))
+        };
+    }
+
+    // A rename whose old path is already gone was moved by an earlier apply; patch the new path.
+    let read_from = source.as_ref().filter(|source| source.exists()).unwrap_or(&path);
+    let current = match std::fs::read_to_string(read_from) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && matches!(file.status, FileStatus::Created) => String::new(),
+        Err(err) => return Err(io_error(err)),
+    };
+    let crlf = current.contains("\r\n");
+    let write = |content: &str| -> Result<(), ApplyError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        std::fs::write(&path, with_line_endings(content, crlf)).map_err(io_error)?;
+        if read_from != &path {
+            std::fs::remove_file(read_from).map_err(io_error)?;
+        }
+        Ok(())
+    };
+
+    // A pure rename carries no hunks.
+    if matches!(file.status, FileStatus::Renamed) && diff::parse_hunks(&file.diff).is_empty() {
+        write(&current)?;
+        return Ok(Applied::Direct);
+    }
+
+    let direct_error = match apply_hunks(&current, &file.diff) {
+        Ok(updated) => {
+            write(&updated)?;
+            return Ok(Applied::Direct);
+        }
+        Err(err) => err,
+    };
+
+    let base_path = file.old_path.as_deref().filter(|_| matches!(file.status, FileStatus::Renamed)).unwrap_or(&file.path);
+    let Some(base) = file.base_content.clone().or_else(|| git_head_content(root, base_path)) else {
+        return Err(ApplyError::Failed(direct_error));
+    };
+    // A base the patch does not fit either is not the one the patch was written against.
+    let patched = apply_hunks(&base, &file.diff).map_err(|_| ApplyError::Failed(direct_error))?;
+    let merge = merge3(&base, &current, &patched);
+    write(&merge.text)?;
+    if merge.conflicts.is_empty() {
+        Ok(Applied::Merged)
+    } else {
+        Err(ApplyError::Conflict(merge.conflicts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn scratch_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "relaycode-apply-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file(path: &str, status: &str, diff: &str) -> TransactionFile {
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "status": status,
+            "language": "text",
+            "diff": diff,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merge3_takes_both_sides_when_they_touch_different_lines() {
+        let merge = merge3("a\nb\nc\nd\n", "A\nb\nc\nd\n", "a\nb\nc\nD\n");
+        assert_eq!(merge.text, "A\nb\nc\nD\n");
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge3_marks_lines_both_sides_changed() {
+        let merge = merge3("a\nb\nc\n", "a\nX\nc\n", "a\nY\nc\n");
+        assert_eq!(
+            merge.text,
+            "a\n<<<<<<< current\nX\n||||||| base\nb\n=======\nY\n>>>>>>> patch\nc\n"
+        );
+        assert_eq!(merge.conflicts, vec![LineRange { start: 2, end: 8 }]);
+    }
+
+    #[test]
+    fn apply_hunks_finds_a_hunk_that_moved() {
+        let patched = apply_hunks("x\ny\na\nb\n", "@@ -1,2 +1,2 @@\n a\n-b\n+c").unwrap();
+        assert_eq!(patched, "x\ny\na\nc\n");
+    }
+
+    #[test]
+    fn apply_file_merges_a_drifted_file_from_its_base() {
+        let root = scratch_dir();
+        std::fs::write(root.join("a.txt"), "A\nb\nc\nd\ne\n").unwrap();
+        let mut patch = file("a.txt", "modified", "@@ -1,5 +1,5 @@\n a\n b\n c\n d\n-e\n+E");
+        patch.base_content = Some("a\nb\nc\nd\ne\n".to_string());
+
+        assert_eq!(apply_file(&root, &patch).unwrap(), Applied::Merged);
+        assert_eq!(std::fs::read_to_string(root.join("a.txt")).unwrap(), "A\nb\nc\nd\nE\n");
+    }
+
+    #[test]
+    fn apply_file_rejects_paths_outside_the_root() {
+        let root = scratch_dir().join("project");
+        std::fs::create_dir_all(&root).unwrap();
+        for path in ["../escape.txt", "/tmp/relaycode-escape.txt", "src/../../escape.txt"] {
+            let result = apply_file(&root, &file(path, "created", "@@ -0,0 +1,1 @@\n+pwned"));
+            assert!(matches!(result, Err(ApplyError::Failed(_))), "{} was accepted", path);
+        }
+        assert!(!root.parent().unwrap().join("escape.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_file_rejects_symlinks_out_of_the_root() {
+        let dir = scratch_dir();
+        let (root, outside) = (dir.join("project"), dir.join("outside"));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+
+        let result = apply_file(&root, &file("link/a.txt", "created", "@@ -0,0 +1,1 @@\n+pwned"));
+        assert!(matches!(result, Err(ApplyError::Failed(_))));
+        assert!(!outside.join("a.txt").exists());
+    }
+
+    #[test]
+    fn apply_file_moves_a_renamed_file() {
+        let root = scratch_dir();
+        std::fs::write(root.join("old.txt"), "a\nb\n").unwrap();
+        let mut patch = file("new.txt", "renamed", "@@ -1,2 +1,2 @@\n a\n-b\n+c");
+        patch.old_path = Some("old.txt".to_string());
+
+        apply_file(&root, &patch).unwrap();
+        assert!(!root.join("old.txt").exists());
+        assert_eq!(std::fs::read_to_string(root.join("new.txt")).unwrap(), "a\nc\n");
+    }
+
+    #[test]
+    fn apply_file_keeps_crlf_line_endings() {
+        let root = scratch_dir();
+        std::fs::write(root.join("a.txt"), "a\r\nb\r\n").unwrap();
+        apply_file(&root, &file("a.txt", "modified", "@@ -1,2 +1,2 @@\n a\n-b\n+c")).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("a.txt")).unwrap(), "a\r\nc\r\n");
+    }
+
+    #[test]
+    fn revert_file_undoes_apply_file() {
+        let root = scratch_dir();
+        std::fs::write(root.join("a.txt"), "a\nb\n").unwrap();
+        let edit = file("a.txt", "modified", "@@ -1,2 +1,3 @@\n a\n-b\n+c\n+d");
+        let create = file("new.txt", "created", "@@ -0,0 +1,1 @@\n+hello");
+        apply_file(&root, &edit).unwrap();
+        apply_file(&root, &create).unwrap();
+
+        revert_file(&root, &create).unwrap();
+        revert_file(&root, &edit).unwrap();
+        assert!(!root.join("new.txt").exists());
+        assert_eq!(std::fs::read_to_string(root.join("a.txt")).unwrap(), "a\nb\n");
+    }
+
+    #[test]
+    fn apply_file_creates_a_file_only_once() {
+        let root = scratch_dir();
+        let create = file("new.txt", "created", "@@ -0,0 +1,2 @@\n+hello\n+world");
+        apply_file(&root, &create).unwrap();
+        assert_eq!(apply_file(&root, &create).unwrap(), Applied::Direct, "the same content is already applied");
+        assert_eq!(std::fs::read_to_string(root.join("new.txt")).unwrap(), "hello\nworld\n");
+
+        std::fs::write(root.join("new.txt"), "someone else's file\n").unwrap();
+        assert!(matches!(apply_file(&root, &create), Err(ApplyError::Failed(_))));
+        assert_eq!(std::fs::read_to_string(root.join("new.txt")).unwrap(), "someone else's file\n");
+    }
+}
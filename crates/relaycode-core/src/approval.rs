@@ -0,0 +1,78 @@
+use crate::diagnostics;
+use relaycode_schema::{
+    ApprovalMethod, ApprovalMode, ApprovalRecord, ApprovalRejection, ApprovalRule, DiagnosticSeverity, PatchConfig, Transaction,
+    ValidationKind, ValidationRun,
+};
+
+pub fn record(method: ApprovalMethod, rule: ApprovalRule, detail: Option<String>) -> ApprovalRecord {
+    ApprovalRecord {
+        method,
+        rule,
+        decided_at: chrono::Utc::now(),
+        detail,
+        rejection: None,
+    }
+}
+
+/// Decides whether a freshly ingested transaction may be applied without a reviewer.
+///
+/// Returns the reason when the transaction has to wait for manual approval.
+pub fn check_auto_approval(tx: &Transaction, patch: &PatchConfig) -> Result<ApprovalRecord, String> {
+    if patch.approval_mode != ApprovalMode::Auto {
+        return Err("approvalMode is manual".to_string());
+    }
+    let file_count = crate::file_paths(tx).len();
+    if file_count < patch.min_file_changes as usize {
+        return Err(format!(
+            "{} file change(s) is below minFileChanges ({})",
+            file_count, patch.min_file_changes
+        ));
+    }
+    Ok(record(ApprovalMethod::Auto, ApprovalRule::AutoMode, None))
+}
+
+/// Number of errors a linter run reported. A failing run whose output we can't parse counts as one.
+pub fn linter_error_count(run: &ValidationRun) -> usize {
+    let output = format!("{}{}", run.stdout, run.stderr);
+    let found = diagnostics::parse_output(&output, &crate::config::project_root());
+    let errors = diagnostics::count(&found, DiagnosticSeverity::Error);
+    if errors == 0 && !run.success {
+        1
+    } else {
+        errors
+    }
+}
+
+/// Re-judges an auto approval once the linter has run. Within `approvalOnErrorCount` the approval
+/// is re-issued under the linter rule. Above it the original record is kept as the audit trail
+/// and a rejection is added to it; the apply then rolls back the files it wrote.
+pub fn confirm_with_linter(approval: ApprovalRecord, validations: &[ValidationRun], patch: &PatchConfig) -> ApprovalRecord {
+    let Some(linter) = validations.iter().find(|run| run.kind == ValidationKind::Linter) else {
+        return approval;
+    };
+    let errors = linter_error_count(linter);
+    let detail = format!("{} linter error(s), approvalOnErrorCount is {}", errors, patch.approval_on_error_count);
+    if errors > patch.approval_on_error_count as usize {
+        return ApprovalRecord {
+            rejection: Some(ApprovalRejection {
+                decided_at: chrono::Utc::now(),
+                reason: detail,
+            }),
+            ..approval
+        };
+    }
+    record(ApprovalMethod::Auto, ApprovalRule::LinterErrorThreshold, Some(detail))
+}
+
+/// Whether a validation run of the latest apply keeps the transaction from `APPLIED`: any failed
+/// run, except a linter run an auto approval accepted under `approvalOnErrorCount`.
+pub fn validation_blocks(tx: &Transaction) -> bool {
+    let linter_accepted = tx
+        .approval
+        .as_ref()
+        .is_some_and(|a| a.rule == ApprovalRule::LinterErrorThreshold && a.rejection.is_none());
+    tx.validations
+        .iter()
+        .filter(|run| !(linter_accepted && run.kind == ValidationKind::Linter))
+        .any(|run| !run.success)
+}
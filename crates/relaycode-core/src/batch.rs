@@ -0,0 +1,167 @@
+use crate::{approval, build_transaction, chain, start_simulation, ApplyOptions, STORE};
+use relaycode_schema::{Batch, FileOverlap, NewTransaction, Transaction, TransactionStatus};
+
+fn generate_batch_id() -> String {
+    use rand::Rng;
+    format!("batch-{:08x}", rand::thread_rng().gen::<u32>())
+}
+
+/// Paths touched by more than one of `transactions`, in first-seen order.
+pub fn find_overlaps(transactions: &[Transaction]) -> Vec<FileOverlap> {
+    let mut overlaps: Vec<FileOverlap> = Vec::new();
+    for tx in transactions {
+        for path in crate::file_paths(tx) {
+            match overlaps.iter_mut().find(|o| o.path == path) {
+                Some(overlap) => overlap.transaction_ids.push(tx.id.clone()),
+                None => overlaps.push(FileOverlap {
+                    path,
+                    transaction_ids: vec![tx.id.clone()],
+                }),
+            }
+        }
+    }
+    overlaps.retain(|o| o.transaction_ids.len() > 1);
+    overlaps
+}
+
+/// Stores `items` as one chained batch: the first becomes the chain root and the rest point at it.
+///
+/// In auto approval mode a batch without overlapping files is applied right away; otherwise it waits
+/// for a single reviewer decision via [`approve_batch`].
+pub fn ingest_batch(items: Vec<NewTransaction>) -> Option<Batch> {
+    let batch_id = generate_batch_id();
+    let mut members: Vec<Transaction> = Vec::new();
+    for item in items {
+        let mut tx = build_transaction(item);
+        match members.first() {
+            None => tx.is_chain_root = Some(true),
+            Some(root) => tx.parent_id = Some(root.id.clone()),
+        }
+        tx.batch_id = Some(batch_id.clone());
+        members.push(tx);
+    }
+    let root_id = members.first()?.id.clone();
+
+    let batch = Batch {
+        id: batch_id,
+        root_id,
+        transaction_ids: members.iter().map(|tx| tx.id.clone()).collect(),
+        created_at: chrono::Utc::now(),
+        overlaps: find_overlaps(&members),
+    };
+    for tx in &members {
+        STORE.insert_transaction(tx.clone());
+    }
+    STORE.insert_batch(batch.clone());
+
+    let config = STORE.get_config();
+    let auto_approvals: Option<Vec<_>> = members
+        .iter()
+        .map(|tx| approval::check_auto_approval(tx, &config.patch).ok())
+        .collect();
+    if let (Some(approvals), true) = (auto_approvals, batch.overlaps.is_empty()) {
+        for (tx, approval) in members.iter().zip(approvals) {
+            start_simulation(tx.id.clone(), ApplyOptions {
+                scenario: None,
+                override_validation: false,
+                approval,
+            });
+        }
+    }
+
+    Some(batch)
+}
+
+/// Starts applying every still-pending member of a batch. Members that share a file apply one
+/// after another in batch order, stopping at the first that does not apply; members touching
+/// disjoint files run concurrently. Returns the ids queued for applying.
+pub fn approve_batch(id: &str) -> Option<Vec<String>> {
+    let batch = STORE.get_batch(id)?;
+    let pending: Vec<Transaction> = batch
+        .transaction_ids
+        .iter()
+        .filter_map(|tx_id| STORE.get_transaction(tx_id))
+        .filter(|tx| tx.status == TransactionStatus::Pending)
+        .collect();
+    let queued: Vec<String> = pending.iter().map(|tx| tx.id.clone()).collect();
+
+    for group in overlap_groups(&queued, &find_overlaps(&pending)) {
+        let mut queue: Vec<(String, ApplyOptions)> =
+            group.into_iter().map(|tx_id| (tx_id, ApplyOptions::manual(None, false))).collect();
+        match queue.len() {
+            1 => {
+                let (tx_id, options) = queue.remove(0);
+                start_simulation(tx_id, options);
+            }
+            _ => chain::apply_in_order(queue),
+        }
+    }
+    Some(queued)
+}
+
+/// Splits `ids` into groups linked by shared files, each kept in the order of `ids`.
+fn overlap_groups(ids: &[String], overlaps: &[FileOverlap]) -> Vec<Vec<String>> {
+    let shares_a_file = |a: &String, b: &String| {
+        overlaps
+            .iter()
+            .any(|overlap| overlap.transaction_ids.contains(a) && overlap.transaction_ids.contains(b))
+    };
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for id in ids {
+        let (mut linked, rest): (Vec<_>, Vec<_>) = groups
+            .into_iter()
+            .partition(|group| group.iter().any(|member| shares_a_file(member, id)));
+        groups = rest;
+        let mut group: Vec<String> = linked.drain(..).flatten().collect();
+        group.push(id.clone());
+        group.sort_by_key(|member| ids.iter().position(|other| other == member));
+        groups.push(group);
+    }
+    groups.sort_by_key(|group| ids.iter().position(|id| *id == group[0]));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn overlap_groups_link_members_through_shared_files() {
+        let ids: Vec<String> = ["a", "b", "c", "d"].iter().map(|id| id.to_string()).collect();
+        let overlap = |path: &str, members: &[&str]| FileOverlap {
+            path: path.to_string(),
+            transaction_ids: members.iter().map(|id| id.to_string()).collect(),
+        };
+        let groups = overlap_groups(&ids, &[overlap("x", &["b", "d"]), overlap("y", &["a", "d"])]);
+        assert_eq!(groups, vec![vec!["a".to_string(), "b".to_string(), "d".to_string()], vec!["c".to_string()]]);
+        assert_eq!(overlap_groups(&ids, &[]).len(), 4);
+    }
+
+    #[tokio::test]
+    async fn approving_a_batch_applies_overlapping_members_in_order() {
+        let _store = testing::lock().await;
+        let root = testing::configure(relaycode_schema::PatchConfig {
+            apply_to_disk: true,
+            ..relaycode_schema::PatchConfig::default()
+        });
+        std::fs::write(root.join("shared.txt"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(root.join("alone.txt"), "x\n").unwrap();
+
+        let batch = ingest_batch(vec![
+            testing::new_transaction(&[("shared.txt", "modified", "@@ -1,2 +1,2 @@\n-one\n+ONE\n two")]),
+            testing::new_transaction(&[("shared.txt", "modified", "@@ -2,2 +2,2 @@\n two\n-three\n+THREE")]),
+            testing::new_transaction(&[("alone.txt", "modified", "@@ -1,1 +1,1 @@\n-x\n+y")]),
+        ])
+        .unwrap();
+        assert_eq!(batch.overlaps.len(), 1);
+
+        let queued = approve_batch(&batch.id).unwrap();
+        assert_eq!(queued, batch.transaction_ids);
+        for id in &queued {
+            assert_eq!(testing::finished(id).await, TransactionStatus::Applied, "{}", id);
+        }
+        assert_eq!(std::fs::read_to_string(root.join("shared.txt")).unwrap(), "ONE\ntwo\nTHREE\n");
+        assert_eq!(std::fs::read_to_string(root.join("alone.txt")).unwrap(), "y\n");
+    }
+}
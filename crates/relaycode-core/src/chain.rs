@@ -0,0 +1,317 @@
+use crate::{start_simulation, ApplyOptions, STORE};
+use relaycode_schema::{
+    ChainDiff, ChainFileDiff, ChainNode, FileStatus, Transaction, TransactionChain, TransactionStatus,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Builds the tree around `id`: walks `parentId` up to the root, then every descendant down from it.
+pub fn build_chain(id: &str) -> Option<TransactionChain> {
+    let transactions = STORE.get_all_transactions();
+    let by_id: HashMap<&str, &Transaction> = transactions.iter().map(|tx| (tx.id.as_str(), tx)).collect();
+    by_id.get(id)?;
+
+    // Walk up, stopping at the root, a missing parent, or a node seen before.
+    let mut path = vec![id.to_string()];
+    let mut cycle = None;
+    let mut orphan_ids = Vec::new();
+    loop {
+        let current = by_id[path.last().unwrap().as_str()];
+        let Some(parent_id) = current.parent_id.as_deref() else {
+            break;
+        };
+        if !by_id.contains_key(parent_id) {
+            orphan_ids.push(current.id.clone());
+            break;
+        }
+        if let Some(at) = path.iter().position(|seen| seen == parent_id) {
+            cycle = Some(path[at..].to_vec());
+            break;
+        }
+        path.push(parent_id.to_string());
+    }
+    let root_id = path.last().unwrap().clone();
+    let ancestor_ids: Vec<String> = path[1..].iter().rev().cloned().collect();
+
+    // Store order is newest first; children are listed oldest first.
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for tx in transactions.iter().rev() {
+        if let Some(parent_id) = tx.parent_id.as_deref() {
+            children.entry(parent_id).or_default().push(tx.id.as_str());
+        }
+    }
+
+    let mut nodes = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec![(root_id.as_str(), 0)];
+    while let Some((node_id, depth)) = stack.pop() {
+        if !visited.insert(node_id) {
+            continue;
+        }
+        let tx = by_id[node_id];
+        let node_children: Vec<&str> = children.get(node_id).cloned().unwrap_or_default();
+        stack.extend(node_children.iter().rev().map(|child| (*child, depth + 1)));
+        nodes.push(ChainNode {
+            id: tx.id.clone(),
+            parent_id: tx.parent_id.clone(),
+            status: tx.status.clone(),
+            description: tx.description.clone(),
+            depth,
+            children: node_children.iter().map(|child| child.to_string()).collect(),
+        });
+    }
+
+    Some(TransactionChain {
+        transaction_id: id.to_string(),
+        root_id,
+        nodes,
+        ancestor_ids,
+        orphan_ids,
+        cycle,
+    })
+}
+
+/// Ids of `id` and everything below it, parents before children.
+fn subtree(chain: &TransactionChain, id: &str) -> Vec<String> {
+    let mut ids = vec![id.to_string()];
+    let mut i = 0;
+    while i < ids.len() {
+        if let Some(node) = chain.nodes.iter().find(|node| node.id == ids[i]) {
+            ids.extend(node.children.iter().filter(|child| !ids.contains(child)).cloned().collect::<Vec<_>>());
+        }
+        i += 1;
+    }
+    chain.nodes.iter().map(|node| node.id.clone()).filter(|node_id| ids.contains(node_id)).collect()
+}
+
+pub(crate) async fn wait_until_settled(id: &str) -> Option<TransactionStatus> {
+    let mut updates = STORE.subscribe();
+    loop {
+        let status = STORE.get_transaction(id)?.status;
+        if status != TransactionStatus::Applying {
+            return Some(status);
+        }
+        // Any update wakes us; a lagged receiver just re-reads the store.
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(1), updates.recv()).await;
+    }
+}
+
+/// Applies every pending member of the chain in order, one at a time, stopping at the first member
+/// that does not end up `APPLIED`. Returns the ids queued for applying.
+pub fn apply_chain(id: &str) -> Option<Vec<String>> {
+    let chain = build_chain(id)?;
+    let queued: Vec<String> = chain
+        .nodes
+        .iter()
+        .filter(|node| node.status == TransactionStatus::Pending)
+        .map(|node| node.id.clone())
+        .collect();
+
+    apply_in_order(queued.iter().map(|tx_id| (tx_id.clone(), ApplyOptions::manual(None, false))).collect());
+    Some(queued)
+}
+
+/// Applies each transaction once the one before it has settled as `APPLIED`, so later patches
+/// of a series never race earlier ones over the same files. Stops at the first that does not.
+pub(crate) fn apply_in_order(queue: Vec<(String, ApplyOptions)>) {
+    tokio::spawn(async move {
+        for (tx_id, options) in queue {
+            start_simulation(tx_id.clone(), options);
+            let status = wait_until_settled(&tx_id).await;
+            if status != Some(TransactionStatus::Applied) {
+                tracing::warn!("Chain apply stopped at {}: {:?}", tx_id, status);
+                break;
+            }
+        }
+    });
+}
+
+/// Reverts `id` and every applied or committed descendant, deepest first. With
+/// `patch.applyToDisk` on, each member's applied files are restored on disk before its status
+/// changes; the first member that cannot be restored stops the revert, leaving it and its
+/// ancestors as they were. The error then also names the members already reverted.
+pub async fn revert_from(id: &str) -> Option<Result<Vec<String>, String>> {
+    let chain = build_chain(id)?;
+    let on_disk = STORE.get_config().patch.apply_to_disk;
+    let mut reverted = Vec::new();
+    for tx_id in subtree(&chain, id).iter().rev() {
+        let Some(tx) = STORE.get_transaction(tx_id).filter(|tx| crate::is_revertible(&tx.status)) else {
+            continue;
+        };
+        if on_disk {
+            if let Err(err) = crate::revert_on_disk(tx).await {
+                let mut message = format!("Could not revert {}: {}", tx_id, err);
+                if !reverted.is_empty() {
+                    message.push_str(&format!("; already reverted {}", reverted.join(", ")));
+                }
+                tracing::warn!("{}", message);
+                return Some(Err(message));
+            }
+        }
+        if STORE.update_transaction_status(tx_id, TransactionStatus::Reverted).is_some() {
+            reverted.push(tx_id.clone());
+        }
+    }
+    Some(Ok(reverted))
+}
+
+/// Concatenates the diffs of every non-reverted member in apply order. Renames get a git
+/// `rename from`/`rename to` header, and later members editing the new path list under it.
+pub fn cumulative_diff(id: &str) -> Option<ChainDiff> {
+    let chain = build_chain(id)?;
+    let mut transaction_ids = Vec::new();
+    let mut files: Vec<ChainFileDiff> = Vec::new();
+    let mut sections = Vec::new();
+
+    for node in &chain.nodes {
+        let Some(tx) = STORE.get_transaction(&node.id) else {
+            continue;
+        };
+        if tx.status == TransactionStatus::Reverted {
+            continue;
+        }
+        transaction_ids.push(tx.id.clone());
+        for file in &tx.files {
+            let renamed_from = file.old_path.as_deref().filter(|old| matches!(file.status, FileStatus::Renamed) && *old != file.path);
+            if file.diff.trim().is_empty() && renamed_from.is_none() {
+                continue;
+            }
+            let mut section = String::new();
+            if let Some(old) = renamed_from {
+                section.push_str(&format!("diff --git a/{} b/{}\nrename from {}\nrename to {}\n", old, file.path, old, file.path));
+            }
+            if !file.diff.trim().is_empty() {
+                let (old, new) = match file.status {
+                    FileStatus::Created => ("/dev/null".to_string(), format!("b/{}", file.path)),
+                    FileStatus::Deleted => (format!("a/{}", file.path), "/dev/null".to_string()),
+                    _ => (format!("a/{}", renamed_from.unwrap_or(&file.path)), format!("b/{}", file.path)),
+                };
+                section.push_str(&format!("--- {}\n+++ {}\n{}", old, new, file.diff.trim_end()));
+            }
+            sections.push(section.trim_end().to_string());
+
+            // Entries are keyed by the latest path, so a rename moves the file's history along with it.
+            let previous = renamed_from.unwrap_or(&file.path);
+            match files.iter_mut().find(|f| f.path == previous || f.path == file.path) {
+                Some(entry) => {
+                    if entry.path != file.path {
+                        entry.original_path.get_or_insert_with(|| entry.path.clone());
+                        entry.path = file.path.clone();
+                    }
+                    entry.transaction_ids.push(tx.id.clone());
+                }
+                None => files.push(ChainFileDiff {
+                    path: file.path.clone(),
+                    original_path: renamed_from.map(str::to_string),
+                    transaction_ids: vec![tx.id.clone()],
+                }),
+            }
+        }
+    }
+
+    let mut diff = sections.join("\n");
+    if !diff.is_empty() {
+        diff.push('\n');
+    }
+    Some(ChainDiff {
+        root_id: chain.root_id,
+        transaction_ids,
+        files,
+        diff,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ingest_transaction, testing};
+    use relaycode_schema::{NewTransaction, PatchConfig};
+
+    fn on_disk() -> std::path::PathBuf {
+        testing::configure(PatchConfig { apply_to_disk: true, ..PatchConfig::default() })
+    }
+
+    fn child_of(parent: &Transaction, files: &[(&str, &str, &str)]) -> Transaction {
+        ingest_transaction(NewTransaction { parent_id: Some(parent.id.clone()), ..testing::new_transaction(files) })
+    }
+
+    #[tokio::test]
+    async fn build_chain_walks_up_to_the_root_and_down_to_every_descendant() {
+        let _store = testing::lock().await;
+        testing::configure(PatchConfig::default());
+        let root = ingest_transaction(testing::new_transaction(&[]));
+        let first = child_of(&root, &[]);
+        let second = child_of(&root, &[]);
+        let grandchild = child_of(&first, &[]);
+        let orphan = ingest_transaction(NewTransaction { parent_id: Some("tx-missing".to_string()), ..testing::new_transaction(&[]) });
+
+        let chain = build_chain(&grandchild.id).unwrap();
+        assert_eq!(chain.root_id, root.id);
+        assert_eq!(chain.ancestor_ids, vec![root.id.clone(), first.id.clone()]);
+        let order: Vec<(&str, usize)> = chain.nodes.iter().map(|node| (node.id.as_str(), node.depth)).collect();
+        assert_eq!(order, vec![(root.id.as_str(), 0), (first.id.as_str(), 1), (grandchild.id.as_str(), 2), (second.id.as_str(), 1)]);
+        assert_eq!(subtree(&chain, &first.id), vec![first.id.clone(), grandchild.id.clone()]);
+        assert!(chain.orphan_ids.is_empty() && chain.cycle.is_none());
+
+        assert_eq!(build_chain(&orphan.id).unwrap().orphan_ids, vec![orphan.id.clone()]);
+        assert!(build_chain("tx-missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn apply_chain_stops_at_the_first_member_that_does_not_apply() {
+        let _store = testing::lock().await;
+        let dir = on_disk();
+        std::fs::write(dir.join("chain-stop.txt"), "a\nb\n").unwrap();
+        let root = ingest_transaction(testing::new_transaction(&[("chain-stop.txt", "modified", "@@ -1,2 +1,2 @@\n-a\n+A\n b")]));
+        let broken = child_of(&root, &[("chain-stop.txt", "modified", "@@ -1,2 +1,2 @@\n x\n-y\n+z")]);
+        let last = child_of(&broken, &[("chain-stop.txt", "modified", "@@ -1,2 +1,2 @@\n A\n-b\n+B")]);
+
+        let queued = apply_chain(&root.id).unwrap();
+        assert_eq!(queued, vec![root.id.clone(), broken.id.clone(), last.id.clone()]);
+        assert_eq!(testing::finished(&root.id).await, TransactionStatus::Applied);
+        assert_eq!(testing::finished(&broken.id).await, TransactionStatus::Failed);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(STORE.get_transaction(&last.id).unwrap().status, TransactionStatus::Pending);
+        assert_eq!(std::fs::read_to_string(dir.join("chain-stop.txt")).unwrap(), "A\nb\n");
+    }
+
+    #[tokio::test]
+    async fn revert_from_restores_descendants_first() {
+        let _store = testing::lock().await;
+        let dir = on_disk();
+        std::fs::write(dir.join("chain-revert.txt"), "a\nb\n").unwrap();
+        let root = ingest_transaction(testing::new_transaction(&[("chain-revert.txt", "modified", "@@ -1,2 +1,2 @@\n-a\n+A\n b")]));
+        let child = child_of(&root, &[("chain-revert.txt", "modified", "@@ -1,2 +1,2 @@\n A\n-b\n+B")]);
+        apply_chain(&root.id).unwrap();
+        assert_eq!(testing::finished(&child.id).await, TransactionStatus::Applied);
+        assert_eq!(std::fs::read_to_string(dir.join("chain-revert.txt")).unwrap(), "A\nB\n");
+
+        assert_eq!(revert_from(&root.id).await.unwrap().unwrap(), vec![child.id.clone(), root.id.clone()]);
+        assert_eq!(std::fs::read_to_string(dir.join("chain-revert.txt")).unwrap(), "a\nb\n");
+        assert_eq!(STORE.get_transaction(&root.id).unwrap().status, TransactionStatus::Reverted);
+        assert_eq!(revert_from(&root.id).await.unwrap().unwrap(), Vec::<String>::new(), "nothing left to revert");
+    }
+
+    #[tokio::test]
+    async fn cumulative_diff_follows_renames() {
+        let _store = testing::lock().await;
+        testing::configure(PatchConfig::default());
+        let root = ingest_transaction(testing::new_transaction(&[("old.rs", "modified", "@@ -1 +1 @@\n-a\n+b")]));
+        let mut rename = testing::new_transaction(&[("new.rs", "renamed", "")]);
+        rename.files[0].old_path = Some("old.rs".to_string());
+        let rename = ingest_transaction(NewTransaction { parent_id: Some(root.id.clone()), ..rename });
+        let edit = child_of(&rename, &[("new.rs", "modified", "@@ -1 +1 @@\n-b\n+c")]);
+
+        let chain_diff = cumulative_diff(&root.id).unwrap();
+        assert_eq!(chain_diff.transaction_ids, vec![root.id.clone(), rename.id.clone(), edit.id.clone()]);
+        assert_eq!(chain_diff.files.len(), 1);
+        let file = &chain_diff.files[0];
+        assert_eq!((file.path.as_str(), file.original_path.as_deref()), ("new.rs", Some("old.rs")));
+        assert_eq!(file.transaction_ids, chain_diff.transaction_ids);
+        assert_eq!(
+            chain_diff.diff,
+            "--- a/old.rs\n+++ b/old.rs\n@@ -1 +1 @@\n-a\n+b\n\
+             diff --git a/old.rs b/new.rs\nrename from old.rs\nrename to new.rs\n\
+             --- a/new.rs\n+++ b/new.rs\n@@ -1 +1 @@\n-b\n+c\n"
+        );
+    }
+}
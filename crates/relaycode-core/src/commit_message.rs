@@ -0,0 +1,375 @@
+use crate::ai::{self, CompletionRequest, Task};
+use crate::{system_prompt, STORE};
+use relaycode_schema::{
+    CommitMessageSource, CommitMessageSuggestion, FileApplyStatus, FileStatus, Transaction,
+};
+
+/// Conventional-commit headers longer than this get their subject shortened.
+const MAX_HEADER_LEN: usize = 72;
+
+/// Diff characters per file sent to the provider; enough to see what changed.
+const MAX_DIFF_CHARS: usize = 4000;
+
+const COMMIT_INSTRUCTIONS: &str = "You write git commit messages in the Conventional Commits format. \
+Reply with the message only: a `type(scope): subject` header of at most 72 characters in the imperative \
+mood, a blank line, then a short body of `- ` bullets. No code fences and no commentary.";
+
+const TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+/// `type(scope)!: subject` with a known type and a non-empty subject.
+fn is_conventional(header: &str) -> bool {
+    let Some((prefix, subject)) = header.split_once(": ") else {
+        return false;
+    };
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let kind = match prefix.split_once('(') {
+        Some((kind, scope)) => match scope.strip_suffix(')') {
+            Some(scope) if !scope.is_empty() => kind,
+            _ => return false,
+        },
+        None => prefix,
+    };
+    TYPES.contains(&kind) && !subject.trim().is_empty()
+}
+
+fn commit_type(tx: &Transaction) -> &'static str {
+    let description = tx.description.to_lowercase();
+    let words: Vec<&str> = description.split(|c: char| !c.is_alphanumeric()).collect();
+    let mentions = |keys: &[&str]| words.iter().any(|w| keys.contains(w));
+    let files = &tx.files;
+    if mentions(&["fix", "fixes", "fixed", "bug", "repair", "resolve", "resolves"]) {
+        "fix"
+    } else if (!files.is_empty() && files.iter().all(|f| f.path.ends_with(".md"))) || mentions(&["docs", "documentation", "readme"]) {
+        "docs"
+    } else if mentions(&["refactor", "restructure", "rename", "cleanup", "simplify"]) {
+        "refactor"
+    } else if mentions(&["test", "tests", "spec"]) {
+        "test"
+    } else if mentions(&["perf", "performance", "faster", "speed"]) {
+        "perf"
+    } else if mentions(&["format", "formatting", "lint"]) {
+        "style"
+    } else {
+        "feat"
+    }
+}
+
+/// The last component of the directory every changed file lives in, unless that is the root or `src`.
+fn scope(transactions: &[Transaction]) -> Option<String> {
+    let dirs: Vec<Vec<&str>> = transactions
+        .iter()
+        .flat_map(|tx| &tx.files)
+        .map(|file| {
+            let parts: Vec<&str> = file.path.split('/').collect();
+            parts[..parts.len() - 1].to_vec()
+        })
+        .collect();
+    let first = dirs.first()?;
+    let shared = (0..first.len())
+        .take_while(|&i| dirs.iter().all(|dir| dir.get(i) == first.get(i)))
+        .count();
+    let name = *first[..shared].last()?;
+    (name != "src").then(|| name.to_string())
+}
+
+/// First line without any `type(scope):` prefix or trailing period, lower-case start unless it
+/// opens with an acronym.
+fn subject(description: &str) -> String {
+    let line = description.lines().next().unwrap_or("").trim().trim_end_matches('.');
+    let line = match line.split_once(": ") {
+        Some((prefix, rest)) if !prefix.is_empty() && !prefix.contains(' ') => rest.trim(),
+        _ => line,
+    };
+    let mut chars = line.chars();
+    match (chars.next(), chars.next()) {
+        (Some(first), Some(second)) if !second.is_uppercase() => {
+            first.to_lowercase().chain(line[first.len_utf8()..].chars()).collect()
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// Drops whole words from the end of `subject` until the header fits.
+fn fit_header(prefix: &str, subject: &str) -> String {
+    let mut header = format!("{}: {}", prefix, subject);
+    while header.len() > MAX_HEADER_LEN {
+        match header.rfind(' ') {
+            Some(at) if at > prefix.len() + 1 => header.truncate(at),
+            _ => break,
+        }
+    }
+    header
+}
+
+fn file_verb(status: &FileStatus) -> &'static str {
+    match status {
+        FileStatus::Modified => "edit",
+        FileStatus::Created => "new",
+        FileStatus::Deleted => "delete",
+        FileStatus::Renamed => "rename",
+    }
+}
+
+fn file_lines(transactions: &[Transaction]) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for file in transactions.iter().flat_map(|tx| &tx.files) {
+        let note = match file.apply_status {
+            FileApplyStatus::Failed => " (not applied)",
+            _ => "",
+        };
+        let line = format!("- {}: {}{}", file_verb(&file.status), file.path, note);
+        if !lines.contains(&line) {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// A conventional commit built from the transactions' descriptions and files, without a model.
+pub fn fallback(transactions: &[Transaction]) -> String {
+    let Some(first) = transactions.first() else {
+        return String::new();
+    };
+    let header_line = first.description.lines().next().unwrap_or("").trim();
+    let header = if transactions.len() == 1 && is_conventional(header_line) {
+        header_line.to_string()
+    } else {
+        let prefix = match scope(transactions) {
+            Some(scope) => format!("{}({})", commit_type(first), scope),
+            None => commit_type(first).to_string(),
+        };
+        fit_header(&prefix, &subject(&first.description))
+    };
+
+    let mut body: Vec<String> = Vec::new();
+    if transactions.len() > 1 {
+        body.extend(transactions.iter().map(|tx| format!("- {}", subject(&tx.description))));
+        body.push(String::new());
+    }
+    body.extend(file_lines(transactions));
+    format!("{}\n\n{}", header, body.join("\n")).trim_end().to_string()
+}
+
+fn build_prompt(transactions: &[Transaction]) -> String {
+    let mut prompt = String::from("Write the commit message for these changes.\n");
+    for tx in transactions {
+        prompt.push_str(&format!("\n## {}\n", tx.description.trim()));
+        if !tx.reasoning.trim().is_empty() {
+            prompt.push_str(&format!("\nReasoning:\n{}\n", tx.reasoning.trim()));
+        }
+        for file in &tx.files {
+            let diff: String = file.diff.chars().take(MAX_DIFF_CHARS).collect();
+            let cut = if diff.len() < file.diff.len() { "\n[diff truncated]" } else { "" };
+            prompt.push_str(&format!(
+                "\n`{}` ({}):\n```diff\n{}{}\n```\n",
+                file.path,
+                file_verb(&file.status),
+                diff.trim_end(),
+                cut
+            ));
+        }
+    }
+    prompt
+}
+
+/// The reply without code fences or quotes; `Err` when its first line is not a conventional header.
+fn clean_reply(reply: &str) -> Result<String, String> {
+    let text = reply.trim();
+    let text = match text.strip_prefix("```") {
+        Some(rest) => {
+            let body = &rest[rest.find('\n').map(|i| i + 1).unwrap_or(rest.len())..];
+            body[..body.find("```").unwrap_or(body.len())].trim()
+        }
+        None => text,
+    };
+    let text = text.trim_matches('"').trim();
+    let header = text.lines().next().unwrap_or("");
+    if is_conventional(header) {
+        Ok(text.to_string())
+    } else {
+        Err(format!("reply does not start with a conventional commit header: {:?}", header))
+    }
+}
+
+/// The transactions for `ids`, in the order given, or one error per unknown id.
+pub fn transactions(ids: &[String]) -> Result<Vec<Transaction>, Vec<String>> {
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for id in ids {
+        match STORE.get_transaction(id) {
+            Some(tx) => found.push(tx),
+            None => missing.push(format!("No transaction {}", id)),
+        }
+    }
+    if missing.is_empty() { Ok(found) } else { Err(missing) }
+}
+
+/// Asks the configured provider for a message, falling back to [`fallback`] when it is skipped,
+/// unreachable, or answers with something that is not a conventional commit.
+pub async fn suggest(transactions: &[Transaction], deterministic: bool) -> CommitMessageSuggestion {
+    let transaction_ids: Vec<String> = transactions.iter().map(|tx| tx.id.clone()).collect();
+    let summary = fallback(transactions);
+    let fallback_with = |provider: Option<String>, warning: Option<String>| CommitMessageSuggestion {
+        transaction_ids: transaction_ids.clone(),
+        message: summary.clone(),
+        source: CommitMessageSource::Fallback,
+        provider,
+        warning,
+    };
+    if deterministic {
+        return fallback_with(None, None);
+    }
+
+    let provider = match ai::provider_from_config(&STORE.get_config().ai_provider) {
+        Ok(provider) => provider,
+        Err(err) => return fallback_with(None, Some(err.to_string())),
+    };
+    let request = CompletionRequest {
+        system: system_prompt::compose(COMMIT_INSTRUCTIONS),
+        prompt: build_prompt(transactions),
+        model: None,
+        task: Task::CommitMessage(summary.clone()),
+    };
+    let name = provider.name().to_string();
+    match provider.complete(&request).await.map_err(|err| err.to_string()).and_then(|c| clean_reply(&c.text)) {
+        Ok(message) => CommitMessageSuggestion {
+            transaction_ids: transaction_ids.clone(),
+            message,
+            source: CommitMessageSource::Ai,
+            provider: Some(name),
+            warning: None,
+        },
+        Err(err) => {
+            tracing::warn!("Commit message from {} not used: {}", name, err);
+            fallback_with(Some(name), Some(err))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_transaction, testing};
+
+    fn tx(description: &str, files: &[(&str, &str)]) -> Transaction {
+        let files: Vec<(&str, &str, &str)> = files.iter().map(|(path, status)| (*path, *status, "")).collect();
+        let mut tx = build_transaction(testing::new_transaction(&files));
+        tx.description = description.to_string();
+        tx
+    }
+
+    #[test]
+    fn is_conventional_table() {
+        let cases = [
+            ("feat: add x", true),
+            ("fix(api): handle y", true),
+            ("feat(api)!: drop z", true),
+            ("refactor!: split module", true),
+            ("feature: add x", false),
+            ("fix(): empty scope", false),
+            ("fix(api: unclosed scope", false),
+            ("fix:  ", false),
+            ("fix:no space", false),
+            ("Add stuff", false),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(is_conventional(header), expected, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn subject_table() {
+        let cases = [
+            ("Add login form.", "add login form"),
+            ("feat(ui): Add button", "add button"),
+            ("API: expose keys", "expose keys"),
+            ("HTTP client retries", "HTTP client retries"),
+            ("Fix it: now with colons", "fix it: now with colons"),
+            ("Title line\nMore detail below", "title line"),
+            ("A", "A"),
+            ("", ""),
+        ];
+        for (description, expected) in cases {
+            assert_eq!(subject(description), expected, "{:?}", description);
+        }
+    }
+
+    #[test]
+    fn fit_header_table() {
+        let long = "add a very long subject line that keeps going well past the seventy two character limit";
+        let unbreakable = "x".repeat(80);
+        let cases = [
+            ("feat", "add x", "feat: add x".to_string()),
+            ("feat(api)", long, "feat(api): add a very long subject line that keeps going well past the".to_string()),
+            ("fix", unbreakable.as_str(), format!("fix: {}", unbreakable)),
+        ];
+        for (prefix, subject, expected) in cases {
+            assert_eq!(fit_header(prefix, subject), expected, "{:?}", subject);
+        }
+    }
+
+    #[test]
+    fn scope_table() {
+        let cases = [
+            (vec![vec![("crates/core/src/a.rs", "modified"), ("crates/core/src/b.rs", "modified")]], None),
+            (vec![vec![("crates/core/lib.rs", "modified"), ("crates/core/x/y.rs", "created")]], Some("core")),
+            (vec![vec![("web/a.ts", "modified")], vec![("web/b.ts", "modified")]], Some("web")),
+            (vec![vec![("a/x.rs", "modified"), ("b/y.rs", "modified")]], None),
+            (vec![vec![("README.md", "modified")]], None),
+        ];
+        for (members, expected) in cases {
+            let transactions: Vec<Transaction> = members.iter().map(|files| tx("change", files)).collect();
+            assert_eq!(scope(&transactions).as_deref(), expected, "{:?}", members);
+        }
+    }
+
+    #[test]
+    fn clean_reply_table() {
+        let cases = [
+            ("```\nfeat: add x\n\n- body\n```", Ok("feat: add x\n\n- body")),
+            ("```text\nfix(api): y\n```\nHope this helps!", Ok("fix(api): y")),
+            ("\"chore: bump deps\"", Ok("chore: bump deps")),
+            ("  docs: tidy readme  \n", Ok("docs: tidy readme")),
+            ("Here is your message:\nfeat: x", Err(())),
+            ("```\nUpdated the parser\n```", Err(())),
+        ];
+        for (reply, expected) in cases {
+            assert_eq!(clean_reply(reply).map_err(|_| ()), expected.map(str::to_string), "{:?}", reply);
+        }
+    }
+
+    #[test]
+    fn fallback_table() {
+        let mut failed = tx("Fix typo in login", &[("web/login.ts", "modified")]);
+        failed.files[0].apply_status = FileApplyStatus::Failed;
+        let cases = [
+            (vec![], String::new()),
+            (
+                vec![tx("fix(api): handle timeouts", &[("api/client.rs", "modified")])],
+                "fix(api): handle timeouts\n\n- edit: api/client.rs".to_string(),
+            ),
+            (
+                vec![tx("Update the guide", &[("docs/guide.md", "modified"), ("README.md", "created")])],
+                "docs: update the guide\n\n- edit: docs/guide.md\n- new: README.md".to_string(),
+            ),
+            (
+                vec![tx("Add login form", &[("web/login.ts", "created")]), failed],
+                "feat(web): add login form\n\n- add login form\n- fix typo in login\n\n- new: web/login.ts\n- edit: web/login.ts (not applied)".to_string(),
+            ),
+            (
+                vec![tx(
+                    "Add a very long subject line that keeps going well past the seventy two character limit",
+                    &[("lib/a.rs", "deleted")],
+                )],
+                "feat(lib): add a very long subject line that keeps going well past the\n\n- delete: lib/a.rs".to_string(),
+            ),
+        ];
+        for (transactions, expected) in cases {
+            let message = fallback(&transactions);
+            assert_eq!(message, expected);
+            assert!(message.lines().next().unwrap_or("").len() <= MAX_HEADER_LEN);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use relaycode_schema::RelayConfig;
+use std::path::{Path, PathBuf};
+use utoipa::{PartialSchema, ToSchema};
+
+pub const CONFIG_FILE_NAME: &str = "relay.config.json";
+pub const CONFIG_SCHEMA_URL: &str = "https://relay.noca.pro/schema.json";
+/// Sent in place of a stored API key.
+pub const REDACTED_API_KEY: &str = "********";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "failed to access config: {}", err),
+            ConfigError::Invalid(errors) => write!(f, "invalid config: {}", errors.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+/// `config` as it may be sent to a client, with a stored API key replaced by [`REDACTED_API_KEY`].
+pub fn redacted(mut config: RelayConfig) -> RelayConfig {
+    if !config.ai_provider.api_key.is_empty() {
+        config.ai_provider.api_key = REDACTED_API_KEY.to_string();
+    }
+    config
+}
+
+/// Location of `relay.config.json`, overridable with `RELAYCODE_CONFIG_PATH`.
+pub fn config_path() -> PathBuf {
+    match std::env::var_os("RELAYCODE_CONFIG_PATH") {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(CONFIG_FILE_NAME),
+    }
+}
+
+/// The project root is the directory holding the config file.
+pub fn project_root() -> PathBuf {
+    config_path()
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Relaycode's own files (`state.json`, prompts) live in `.relay/` under the project root.
+pub fn relay_dir() -> PathBuf {
+    project_root().join(".relay")
+}
+
+pub fn default_config() -> RelayConfig {
+    let project_id = project_root()
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| RelayConfig::default().project_id);
+    RelayConfig {
+        schema: Some(CONFIG_SCHEMA_URL.to_string()),
+        project_id,
+        ..RelayConfig::default()
+    }
+}
+
+/// Reads the config from disk, falling back to defaults when the file does not exist.
+pub fn load_config() -> Result<RelayConfig, ConfigError> {
+    let path = config_path();
+    if !path.exists() {
+        return Ok(default_config());
+    }
+    let raw = std::fs::read_to_string(&path)?;
+    let value: serde_json::Value = serde_json::from_str(&raw)
+        .map_err(|err| ConfigError::Invalid(vec![format!("{}: {}", CONFIG_FILE_NAME, err)]))?;
+    parse_config(value).map_err(ConfigError::Invalid)
+}
+
+pub fn save_config(config: &RelayConfig) -> Result<(), ConfigError> {
+    let mut json = serde_json::to_string_pretty(config).expect("config serializes");
+    json.push('\n');
+    std::fs::write(config_path(), json)?;
+    Ok(())
+}
+
+/// Parses and validates a config document, collecting every violation instead of stopping at the first.
+pub fn parse_config(value: serde_json::Value) -> Result<RelayConfig, Vec<String>> {
+    let config: RelayConfig = serde_json::from_value(value).map_err(|err| vec![err.to_string()])?;
+    let errors = validate_config(&config);
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Checks `config` against [`config_json_schema`], so the limits are only written down once, in
+/// the `#[schema]` attributes of the config types, plus the rules a schema cannot express.
+pub fn validate_config(config: &RelayConfig) -> Vec<String> {
+    let value = serde_json::to_value(config).expect("config serializes");
+    let schema = config_json_schema();
+    let mut errors = Vec::new();
+    check_schema(&value, &schema, &schema["$defs"], "", &mut errors);
+
+    let ai = &config.ai_provider;
+    if ai.name != crate::ai::MOCK_PROVIDER && ai.base_url.is_empty() && crate::ai::default_base_url(&ai.name).is_none() {
+        errors.push(format!("aiProvider.baseUrl: required for provider {}", ai.name));
+    }
+    let prefix = &config.git.git_branch_prefix;
+    if prefix.chars().any(|c| c.is_whitespace() || "~^:?*[\\".contains(c)) || prefix.contains("..") {
+        errors.push("git.gitBranchPrefix: contains characters not allowed in a git ref".to_string());
+    }
+
+    errors
+}
+
+/// Validates `value` against the subset of JSON Schema that utoipa generates for the config
+/// types: `$ref`, `oneOf`, `type`, `enum`, `minimum`, `maximum`, `minLength`, `required`,
+/// `properties` and `additionalProperties`.
+fn check_schema(value: &serde_json::Value, schema: &serde_json::Value, defs: &serde_json::Value, path: &str, errors: &mut Vec<String>) {
+    use serde_json::Value;
+    let at = if path.is_empty() { "config" } else { path };
+
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/$defs/");
+        return check_schema(value, &defs[name], defs, path, errors);
+    }
+    if let Some(options) = schema["oneOf"].as_array() {
+        if let [only] = options.as_slice() {
+            check_schema(value, only, defs, path, errors);
+        } else {
+            let matching = options
+                .iter()
+                .filter(|option| {
+                    let mut option_errors = Vec::new();
+                    check_schema(value, option, defs, path, &mut option_errors);
+                    option_errors.is_empty()
+                })
+                .count();
+            if matching != 1 {
+                errors.push(format!("{}: does not match exactly one of the allowed forms", at));
+            }
+        }
+    }
+
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let type_matches = |name: &&str| match *name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    };
+    if !types.is_empty() && !types.iter().any(type_matches) {
+        errors.push(format!("{}: must be of type {}", at, types.join(" or ")));
+        return;
+    }
+
+    if let Some(allowed) = schema["enum"].as_array() {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            errors.push(format!("{}: must be one of {}", at, allowed.join(", ")));
+        }
+    }
+    if let (Some(number), Some(minimum)) = (value.as_f64(), schema["minimum"].as_f64()) {
+        if number < minimum {
+            errors.push(format!("{}: must be at least {}", at, schema["minimum"]));
+        }
+    }
+    if let (Some(number), Some(maximum)) = (value.as_f64(), schema["maximum"].as_f64()) {
+        if number > maximum {
+            errors.push(format!("{}: must be at most {}", at, schema["maximum"]));
+        }
+    }
+    if let (Some(text), Some(min_length)) = (value.as_str(), schema["minLength"].as_u64()) {
+        if (text.chars().count() as u64) < min_length {
+            errors.push(match min_length {
+                1 => format!("{}: must not be empty", at),
+                _ => format!("{}: must be at least {} characters", at, min_length),
+            });
+        }
+    }
+
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    let child = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    for key in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+        if !object.contains_key(key) {
+            errors.push(format!("{}: is required", child(key)));
+        }
+    }
+    let properties = schema["properties"].as_object();
+    for (key, item) in object {
+        match properties.and_then(|properties| properties.get(key)) {
+            Some(property) => check_schema(item, property, defs, &child(key), errors),
+            None if schema["additionalProperties"] == Value::Bool(false) => {
+                errors.push(format!("{}: unknown field", child(key)));
+            }
+            None => {}
+        }
+    }
+}
+
+/// JSON Schema (draft 2020-12) for `relay.config.json`, derived from the `RelayConfig` types.
+pub fn config_json_schema() -> serde_json::Value {
+    let mut components = Vec::new();
+    RelayConfig::schemas(&mut components);
+
+    let defs: serde_json::Map<String, serde_json::Value> = components
+        .into_iter()
+        .map(|(name, schema)| (name, serde_json::to_value(schema).expect("schema serializes")))
+        .collect();
+
+    let mut schema = serde_json::to_value(RelayConfig::schema()).expect("schema serializes");
+    if let serde_json::Value::Object(ref mut root) = schema {
+        root.insert("$schema".into(), "https://json-schema.org/draft/2020-12/schema".into());
+        root.insert("$id".into(), CONFIG_SCHEMA_URL.into());
+        root.insert("title".into(), "Relaycode configuration".into());
+        root.insert("$defs".into(), serde_json::Value::Object(defs));
+    }
+    rewrite_refs(&mut schema);
+    schema
+}
+
+fn rewrite_refs(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if key == "$ref" {
+                    if let serde_json::Value::String(reference) = child {
+                        *reference = reference.replace("#/components/schemas/", "#/$defs/");
+                    }
+                } else {
+                    rewrite_refs(child);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(rewrite_refs),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn defaults_pass() {
+        assert!(validate_config(&RelayConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn limits_come_from_the_schema() {
+        let errors = parse_config(json!({
+            "projectId": "",
+            "watcher": { "clipboardPollInterval": 50, "bulkSize": 0 },
+            "aiProvider": { "timeout": 0 },
+        }))
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "aiProvider.timeout: must be at least 1",
+                "projectId: must not be empty",
+                "watcher.bulkSize: must be at least 1",
+                "watcher.clipboardPollInterval: must be at least 100",
+            ]
+        );
+    }
+
+    #[test]
+    fn rules_outside_the_schema_still_apply() {
+        let errors = parse_config(json!({
+            "projectId": "demo",
+            "git": { "gitBranchPrefix": "relay..x" },
+            "aiProvider": { "name": "acme" },
+        }))
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "aiProvider.baseUrl: required for provider acme",
+                "git.gitBranchPrefix: contains characters not allowed in a git ref",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_schema_reports_types_enums_and_unknown_fields() {
+        let schema = json!({
+            "type": "object",
+            "required": ["mode"],
+            "properties": {
+                "mode": { "type": "string", "enum": ["auto", "manual"] },
+                "count": { "type": "integer", "maximum": 3 },
+            },
+            "additionalProperties": false,
+        });
+        let mut errors = Vec::new();
+        check_schema(&json!({ "count": "x", "extra": 1 }), &schema, &json!({}), "", &mut errors);
+        assert_eq!(errors, ["mode: is required", "count: must be of type integer", "extra: unknown field"]);
+
+        errors.clear();
+        check_schema(&json!({ "mode": "fast", "count": 4 }), &schema, &json!({}), "", &mut errors);
+        assert_eq!(errors, ["count: must be at most 3", "mode: must be one of \"auto\", \"manual\""]);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{diff, STORE};
+use relaycode_schema::{
+    ConflictKind, ConflictReport, FileConflict, FileStatus, LineRange, Transaction, TransactionConflict,
+    TransactionStatus,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Statuses whose changes are either about to land or already on disk.
+fn is_active(status: &TransactionStatus) -> bool {
+    matches!(
+        status,
+        TransactionStatus::Pending
+            | TransactionStatus::Applying
+            | TransactionStatus::Applied
+            | TransactionStatus::PartiallyApplied
+    )
+}
+
+struct FileRanges {
+    path: String,
+    whole_file: bool,
+    ranges: Vec<LineRange>,
+}
+
+/// Old-side line ranges of every hunk, per file. A pure insertion is the single line it follows.
+/// A rename also claims the whole of its old path, since nothing else can edit it afterwards.
+fn file_ranges(tx: &Transaction) -> Vec<FileRanges> {
+    tx.files
+        .iter()
+        .flat_map(|file| {
+            let ranges: Vec<LineRange> = diff::parse_hunks(&file.diff)
+                .iter()
+                .map(|hunk| LineRange {
+                    start: hunk.old_start,
+                    end: hunk.old_start + hunk.old_count.saturating_sub(1),
+                })
+                .collect();
+            let renamed_from = file
+                .old_path
+                .clone()
+                .filter(|old| matches!(file.status, FileStatus::Renamed) && *old != file.path)
+                .map(|old| FileRanges {
+                    path: old,
+                    whole_file: true,
+                    ranges: ranges.clone(),
+                });
+            std::iter::once(FileRanges {
+                path: file.path.clone(),
+                whole_file: matches!(file.status, FileStatus::Created | FileStatus::Deleted),
+                ranges,
+            })
+            .chain(renamed_from)
+        })
+        .collect()
+}
+
+fn overlaps(a: &LineRange, b: &LineRange) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+fn compare(ours: &[FileRanges], theirs: &[FileRanges]) -> Vec<FileConflict> {
+    ours.iter()
+        .filter_map(|mine| {
+            let other = theirs.iter().find(|other| other.path == mine.path)?;
+            if mine.whole_file || other.whole_file {
+                return Some(FileConflict {
+                    path: mine.path.clone(),
+                    kind: ConflictKind::Lines,
+                    ranges: mine.ranges.clone(),
+                    other_ranges: other.ranges.clone(),
+                });
+            }
+            let ranges: Vec<LineRange> = mine
+                .ranges
+                .iter()
+                .filter(|a| other.ranges.iter().any(|b| overlaps(a, b)))
+                .copied()
+                .collect();
+            let other_ranges: Vec<LineRange> = other
+                .ranges
+                .iter()
+                .filter(|b| mine.ranges.iter().any(|a| overlaps(a, b)))
+                .copied()
+                .collect();
+            Some(if ranges.is_empty() {
+                FileConflict {
+                    path: mine.path.clone(),
+                    kind: ConflictKind::File,
+                    ranges: mine.ranges.clone(),
+                    other_ranges: other.ranges.clone(),
+                }
+            } else {
+                FileConflict {
+                    path: mine.path.clone(),
+                    kind: ConflictKind::Lines,
+                    ranges,
+                    other_ranges,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Ids of `tx`'s ancestors. A child is written against its parent, so their overlap is not a conflict.
+fn lineage<'a>(tx: &'a Transaction, by_id: &HashMap<&str, &'a Transaction>) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut current = tx;
+    while let Some(parent) = current.parent_id.as_deref().and_then(|id| by_id.get(id)) {
+        if !seen.insert(parent.id.as_str()) {
+            break;
+        }
+        current = parent;
+    }
+    seen
+}
+
+struct Analysis {
+    active: Vec<Transaction>,
+    ranges: HashMap<String, Vec<FileRanges>>,
+    ancestors: HashMap<String, HashSet<String>>,
+    /// Line-level conflicts of each active transaction, in store order, computed once per pair.
+    line_edges: HashMap<String, Vec<String>>,
+}
+
+impl Analysis {
+    fn new(transactions: Vec<Transaction>) -> Self {
+        let by_id: HashMap<&str, &Transaction> = transactions.iter().map(|tx| (tx.id.as_str(), tx)).collect();
+        let ancestors = transactions
+            .iter()
+            .map(|tx| {
+                let ids = lineage(tx, &by_id).into_iter().map(str::to_string).collect();
+                (tx.id.clone(), ids)
+            })
+            .collect();
+        let active: Vec<Transaction> = transactions.iter().filter(|tx| is_active(&tx.status)).cloned().collect();
+        let ranges = active.iter().map(|tx| (tx.id.clone(), file_ranges(tx))).collect();
+        let mut analysis = Self {
+            active,
+            ranges,
+            ancestors,
+            line_edges: HashMap::new(),
+        };
+        analysis.line_edges = analysis.find_line_edges();
+        analysis
+    }
+
+    fn find_line_edges(&self) -> HashMap<String, Vec<String>> {
+        let mut edges: HashMap<String, Vec<String>> =
+            self.active.iter().map(|tx| (tx.id.clone(), Vec::new())).collect();
+        for (i, a) in self.active.iter().enumerate() {
+            for b in &self.active[i + 1..] {
+                if self.related(&a.id, &b.id) {
+                    continue;
+                }
+                let files = compare(&self.ranges[&a.id], &self.ranges[&b.id]);
+                if files.iter().any(|f| f.kind == ConflictKind::Lines) {
+                    edges.get_mut(&a.id).unwrap().push(b.id.clone());
+                    edges.get_mut(&b.id).unwrap().push(a.id.clone());
+                }
+            }
+        }
+        edges
+    }
+
+    fn related(&self, a: &str, b: &str) -> bool {
+        self.ancestors.get(a).is_some_and(|set| set.contains(b)) || self.ancestors.get(b).is_some_and(|set| set.contains(a))
+    }
+
+    fn conflicts_of(&self, tx: &Transaction) -> Vec<TransactionConflict> {
+        let ours = self.ranges.get(&tx.id).map(Vec::as_slice).unwrap_or_default();
+        self.active
+            .iter()
+            .filter(|other| other.id != tx.id && !self.related(&tx.id, &other.id))
+            .filter_map(|other| {
+                let files = compare(ours, &self.ranges[&other.id]);
+                (!files.is_empty()).then(|| TransactionConflict {
+                    transaction_id: other.id.clone(),
+                    status: other.status.clone(),
+                    files,
+                })
+            })
+            .collect()
+    }
+
+    fn line_conflicts(&self, tx: &Transaction) -> Vec<String> {
+        self.line_edges.get(&tx.id).cloned().unwrap_or_default()
+    }
+}
+
+/// Orders pending transactions so as many as possible apply cleanly: oldest first, skipping any
+/// that collide with one already picked, then the colliding rest.
+fn suggest_order(analysis: &Analysis, start: &Transaction) -> Vec<String> {
+    let pending: HashMap<&str, &Transaction> = analysis
+        .active
+        .iter()
+        .filter(|tx| tx.status == TransactionStatus::Pending)
+        .map(|tx| (tx.id.as_str(), tx))
+        .collect();
+
+    // Everything reachable from `start` through line conflicts between pending transactions.
+    let mut component = vec![start.id.clone()];
+    let mut edges: HashMap<String, Vec<String>> = HashMap::new();
+    let mut i = 0;
+    while i < component.len() {
+        let Some(tx) = analysis.active.iter().find(|tx| tx.id == component[i]) else {
+            i += 1;
+            continue;
+        };
+        let neighbours: Vec<String> = analysis
+            .line_conflicts(tx)
+            .into_iter()
+            .filter(|id| pending.contains_key(id.as_str()))
+            .collect();
+        for id in &neighbours {
+            if !component.contains(id) {
+                component.push(id.clone());
+            }
+        }
+        edges.insert(tx.id.clone(), neighbours);
+        i += 1;
+    }
+    component.retain(|id| pending.contains_key(id.as_str()));
+    // Store order is newest first, and a parent is always older than its children.
+    component.sort_by_key(|id| std::cmp::Reverse(analysis.active.iter().position(|tx| &tx.id == id)));
+
+    let (mut clean, mut colliding): (Vec<String>, Vec<String>) = (Vec::new(), Vec::new());
+    for id in component {
+        let collides = clean.iter().any(|picked| edges.get(&id).is_some_and(|n| n.contains(picked)));
+        if collides {
+            colliding.push(id);
+        } else {
+            clean.push(id);
+        }
+    }
+    clean.extend(colliding);
+    clean
+}
+
+pub fn report(id: &str) -> Option<ConflictReport> {
+    let analysis = Analysis::new(STORE.get_all_transactions());
+    let tx = STORE.get_transaction(id)?;
+    let conflicts = analysis.conflicts_of(&tx);
+    let suggested_order = suggest_order(&analysis, &tx);
+    Some(ConflictReport {
+        transaction_id: tx.id,
+        conflicts,
+        suggested_order,
+    })
+}
+
+/// Fills `conflictsWith` on each active transaction in `page` with its line-level conflicts.
+/// The pairwise analysis runs once for the whole page.
+pub fn flag(page: &mut [Transaction]) {
+    let analysis = Analysis::new(STORE.get_all_transactions());
+    for tx in page.iter_mut().filter(|tx| is_active(&tx.status)) {
+        tx.conflicts_with = analysis.line_conflicts(tx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_transaction, testing};
+
+    fn tx(files: &[(&str, &str, &str)]) -> Transaction {
+        build_transaction(testing::new_transaction(files))
+    }
+
+    fn kinds(analysis: &Analysis, tx: &Transaction) -> Vec<(String, ConflictKind)> {
+        analysis
+            .conflicts_of(tx)
+            .into_iter()
+            .flat_map(|c| c.files.into_iter().map(move |f| (format!("{}:{}", c.transaction_id, f.path), f.kind)))
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_hunks_conflict_on_lines_and_adjacent_ones_only_share_the_file() {
+        let lines_1_to_3 = tx(&[("a.rs", "modified", "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c")]);
+        let lines_3_to_4 = tx(&[("a.rs", "modified", "@@ -3,2 +3,2 @@\n-c\n+C\n d")]);
+        let lines_4_to_5 = tx(&[("a.rs", "modified", "@@ -4,2 +4,2 @@\n-d\n+D\n e")]);
+        let elsewhere = tx(&[("b.rs", "modified", "@@ -1,1 +1,1 @@\n-a\n+A")]);
+        let analysis = Analysis::new(vec![lines_1_to_3.clone(), lines_3_to_4.clone(), lines_4_to_5.clone(), elsewhere.clone()]);
+
+        assert_eq!(
+            kinds(&analysis, &lines_1_to_3),
+            vec![(format!("{}:a.rs", lines_3_to_4.id), ConflictKind::Lines), (format!("{}:a.rs", lines_4_to_5.id), ConflictKind::File)]
+        );
+        let overlap = &analysis.conflicts_of(&lines_1_to_3)[0].files[0];
+        assert_eq!((overlap.ranges.clone(), overlap.other_ranges.clone()), (vec![LineRange { start: 1, end: 3 }], vec![LineRange { start: 3, end: 4 }]));
+        assert_eq!(analysis.line_conflicts(&lines_3_to_4), vec![lines_1_to_3.id.clone(), lines_4_to_5.id.clone()]);
+        assert!(analysis.conflicts_of(&elsewhere).is_empty());
+    }
+
+    #[test]
+    fn a_rename_conflicts_with_edits_to_its_old_path() {
+        let mut rename = tx(&[("new.rs", "renamed", "")]);
+        rename.files[0].old_path = Some("old.rs".to_string());
+        let edit_old = tx(&[("old.rs", "modified", "@@ -9,1 +9,1 @@\n-x\n+y")]);
+        let create_new = tx(&[("new.rs", "created", "@@ -0,0 +1,1 @@\n+z")]);
+        let analysis = Analysis::new(vec![rename.clone(), edit_old.clone(), create_new.clone()]);
+
+        assert_eq!(
+            kinds(&analysis, &rename),
+            vec![(format!("{}:old.rs", edit_old.id), ConflictKind::Lines), (format!("{}:new.rs", create_new.id), ConflictKind::Lines)]
+        );
+    }
+
+    #[test]
+    fn children_do_not_conflict_with_their_ancestors() {
+        let parent = tx(&[("a.rs", "modified", "@@ -1,2 +1,2 @@\n-a\n+A\n b")]);
+        let mut child = tx(&[("a.rs", "modified", "@@ -1,2 +1,2 @@\n A\n-b\n+B")]);
+        child.parent_id = Some(parent.id.clone());
+        let analysis = Analysis::new(vec![child.clone(), parent.clone()]);
+        assert!(analysis.conflicts_of(&child).is_empty());
+        assert!(analysis.line_conflicts(&parent).is_empty());
+    }
+
+    #[test]
+    fn suggest_order_puts_non_colliding_transactions_first() {
+        // Store order is newest first: `oldest` was ingested before `middle`, then `newest`.
+        let oldest = tx(&[("a.rs", "modified", "@@ -1,2 +1,2 @@\n-a\n+A\n b")]);
+        let middle = tx(&[("a.rs", "modified", "@@ -2,2 +2,2 @@\n-b\n+B\n c")]);
+        let newest = tx(&[("a.rs", "modified", "@@ -3,2 +3,2 @@\n-c\n+C\n d")]);
+        let unrelated = tx(&[("b.rs", "modified", "@@ -1,1 +1,1 @@\n-a\n+A")]);
+        let mut applied = tx(&[("a.rs", "modified", "@@ -1,1 +1,1 @@\n-a\n+X")]);
+        applied.status = TransactionStatus::Applied;
+        let analysis = Analysis::new(vec![applied, unrelated, newest.clone(), middle.clone(), oldest.clone()]);
+
+        let expected = vec![oldest.id.clone(), newest.id.clone(), middle.id.clone()];
+        assert_eq!(suggest_order(&analysis, &middle), expected);
+        assert_eq!(suggest_order(&analysis, &newest), expected, "the whole component is ordered from any member");
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{config, patch};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use relaycode_schema::{
+    ContextPack, ContextTier, PackContextRequest, PackFormat, PackedFile, SkipReason, SkippedFile,
+};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Bytes inspected for a NUL when deciding whether a file is binary.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+fn default_budget(tier: ContextTier) -> u64 {
+    match tier {
+        ContextTier::Compact => 8_000,
+        ContextTier::Medium => 32_000,
+        ContextTier::Extended => 128_000,
+    }
+}
+
+/// Rough token count for prompt budgeting: about four characters per token for code and prose.
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// `./src/` and `src` both mean everything under `src` when `src` is a directory. Patterns that
+/// could reach outside `root` are rejected, so nothing reveals which files exist there.
+fn normalize_pattern(root: &Path, pattern: &str) -> Result<String, String> {
+    let trimmed = pattern.trim();
+    let absolute = trimmed.starts_with(['/', '\\']) || trimmed.as_bytes().get(1) == Some(&b':');
+    if absolute || trimmed.split(['/', '\\']).any(|part| part == "..") {
+        return Err(format!("{}: pattern must stay inside the project root", trimmed));
+    }
+    let pattern = trimmed.trim_start_matches("./");
+    Ok(match pattern.strip_suffix('/') {
+        Some(dir) => format!("{}/**", dir),
+        None if !pattern.is_empty() && !is_glob(pattern) && root.join(pattern).is_dir() => format!("{}/**", pattern),
+        None => pattern.to_string(),
+    })
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+fn build_globs(patterns: &[String]) -> Result<GlobSet, Vec<String>> {
+    let mut builder = GlobSetBuilder::new();
+    let mut errors = Vec::new();
+    for pattern in patterns {
+        match GlobBuilder::new(pattern).literal_separator(true).build() {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(err) => errors.push(format!("{}: {}", pattern, err)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    builder.build().map_err(|err| vec![err.to_string()])
+}
+
+/// Every file under `root` that no ignore file excludes, as `/`-separated relative paths.
+fn visible_files(root: &Path) -> Vec<String> {
+    let mut paths: Vec<String> = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[derive(Default)]
+struct Tree {
+    dirs: BTreeMap<String, Tree>,
+    files: Vec<String>,
+}
+
+impl Tree {
+    fn insert(&mut self, path: &str) {
+        match path.split_once('/') {
+            Some((dir, rest)) => self.dirs.entry(dir.to_string()).or_default().insert(rest),
+            None => self.files.push(path.to_string()),
+        }
+    }
+
+    /// Directories first, then files, indented two spaces per level.
+    fn render(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        for (name, dir) in &self.dirs {
+            out.push_str(&format!("{}{}/\n", indent, name));
+            dir.render(depth + 1, out);
+        }
+        for name in &self.files {
+            out.push_str(&format!("{}{}\n", indent, name));
+        }
+    }
+}
+
+/// A backtick fence longer than any run of backticks inside `content`.
+fn fence_for(content: &str) -> String {
+    let longest = content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn render(format: PackFormat, tree: &str, files: &[(PackedFile, String)]) -> String {
+    let mut out = String::new();
+    match format {
+        PackFormat::Markdown => {
+            out.push_str(&format!("# Directory Structure\n```\n{}```\n\n# Files\n", tree));
+            for (file, content) in files {
+                let fence = fence_for(content);
+                out.push_str(&format!(
+                    "\n## File: {}\n{}{}\n{}\n{}\n",
+                    file.path,
+                    fence,
+                    file.language,
+                    content.trim_end_matches('\n'),
+                    fence
+                ));
+            }
+        }
+        PackFormat::Xml => {
+            out.push_str(&format!("<directory_structure>\n{}</directory_structure>\n\n<files>\n", escape_xml(tree)));
+            for (file, content) in files {
+                out.push_str(&format!(
+                    "<file path=\"{}\">\n{}\n</file>\n",
+                    escape_xml(&file.path).replace('"', "&quot;"),
+                    escape_xml(content.trim_end_matches('\n'))
+                ));
+            }
+            out.push_str("</files>\n");
+        }
+    }
+    out
+}
+
+/// Packs the requested files under the project root into one document. Errors are request
+/// problems (nothing asked for, a malformed glob); unreadable or excluded files are reported in
+/// `skipped` instead.
+pub fn pack(request: &PackContextRequest) -> Result<ContextPack, Vec<String>> {
+    let tiered = request.context_files.as_ref().map(|tiers| match request.tier {
+        ContextTier::Compact => &tiers.compact,
+        ContextTier::Medium => &tiers.medium,
+        ContextTier::Extended => &tiers.extended,
+    });
+    let root = config::project_root();
+    let mut patterns: Vec<String> = Vec::new();
+    let mut errors = Vec::new();
+    for pattern in request.files.iter().chain(tiered.into_iter().flatten()).map(|p| normalize_pattern(&root, p)) {
+        match pattern {
+            Ok(pattern) if !pattern.is_empty() && !patterns.contains(&pattern) => patterns.push(pattern),
+            Ok(_) => {}
+            Err(err) => errors.push(err),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    if patterns.is_empty() {
+        return Err(vec!["No files requested".to_string()]);
+    }
+
+    let globs = build_globs(&patterns)?;
+    let mut matched_patterns = vec![false; patterns.len()];
+    // Earlier patterns get the token budget first.
+    let mut candidates: Vec<(usize, String)> = Vec::new();
+    for path in visible_files(&root) {
+        let hits = globs.matches(&path);
+        if let Some(&first) = hits.iter().min() {
+            hits.into_iter().for_each(|i| matched_patterns[i] = true);
+            candidates.push((first, path));
+        }
+    }
+    candidates.sort();
+
+    let mut skipped: Vec<SkippedFile> = patterns
+        .iter()
+        .zip(&matched_patterns)
+        .filter(|(_, matched)| !**matched)
+        .map(|(pattern, _)| SkippedFile {
+            path: pattern.clone(),
+            reason: if !is_glob(pattern) && root.join(pattern).is_file() {
+                SkipReason::Ignored
+            } else {
+                SkipReason::NotFound
+            },
+        })
+        .collect();
+
+    let max_file_size = request.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE);
+    let budget = request.max_tokens.unwrap_or_else(|| default_budget(request.tier));
+    let mut used = 0;
+    let mut packed: Vec<(PackedFile, String)> = Vec::new();
+    for (_, path) in candidates {
+        let full = root.join(&path);
+        let skip = |reason| SkippedFile {
+            path: path.clone(),
+            reason,
+        };
+        let bytes = match std::fs::metadata(&full) {
+            Ok(meta) => meta.len(),
+            Err(_) => {
+                skipped.push(skip(SkipReason::NotFound));
+                continue;
+            }
+        };
+        if bytes > max_file_size {
+            skipped.push(skip(SkipReason::TooLarge));
+            continue;
+        }
+        let raw = match std::fs::read(&full) {
+            Ok(raw) => raw,
+            Err(_) => {
+                skipped.push(skip(SkipReason::NotFound));
+                continue;
+            }
+        };
+        let content = match String::from_utf8(raw) {
+            Ok(text) if !text.as_bytes()[..text.len().min(BINARY_SNIFF_LEN)].contains(&0) => text,
+            _ => {
+                skipped.push(skip(SkipReason::Binary));
+                continue;
+            }
+        };
+        let tokens = estimate_tokens(&content);
+        if used + tokens > budget {
+            skipped.push(skip(SkipReason::OverBudget));
+            continue;
+        }
+        used += tokens;
+        packed.push((
+            PackedFile {
+                language: patch::language_for_path(&path).to_string(),
+                path,
+                bytes,
+                tokens,
+            },
+            content,
+        ));
+    }
+
+    packed.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+    let mut tree = Tree::default();
+    packed.iter().for_each(|(file, _)| tree.insert(&file.path));
+    let mut tree_text = String::new();
+    tree.render(0, &mut tree_text);
+
+    let document = render(request.format, &tree_text, &packed);
+    Ok(ContextPack {
+        tier: request.tier,
+        format: request.format,
+        total_tokens: estimate_tokens(&document),
+        files: packed.into_iter().map(|(file, _)| file).collect(),
+        skipped,
+        document,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the test project root, and its path relative to that root.
+    fn scratch(name: &str) -> (std::path::PathBuf, String) {
+        let relative = format!("context-{}", name);
+        let dir = crate::testing::project_root().join(&relative);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        (dir, relative)
+    }
+
+    fn request(files: &[String]) -> PackContextRequest {
+        PackContextRequest {
+            files: files.to_vec(),
+            ..PackContextRequest::default()
+        }
+    }
+
+    fn skipped(pack: &ContextPack) -> Vec<(String, SkipReason)> {
+        pack.skipped.iter().map(|s| (s.path.clone(), s.reason)).collect()
+    }
+
+    #[test]
+    fn a_bare_directory_means_everything_under_it() {
+        let (dir, rel) = scratch("dir");
+        std::fs::create_dir_all(dir.join("src/nested")).unwrap();
+        std::fs::write(dir.join("src/a.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(dir.join("src/nested/b.rs"), "fn b() {}\n").unwrap();
+
+        for pattern in [format!("{}/src", rel), format!("./{}/src/", rel)] {
+            let pack = pack(&request(&[pattern])).unwrap();
+            let paths: Vec<&str> = pack.files.iter().map(|f| f.path.as_str()).collect();
+            assert_eq!(paths, vec![format!("{}/src/a.rs", rel), format!("{}/src/nested/b.rs", rel)]);
+        }
+    }
+
+    #[test]
+    fn patterns_outside_the_root_are_rejected() {
+        let (_, rel) = scratch("escape");
+        for pattern in ["../secret.txt", "src/../../x", "/etc/passwd", "C:/Windows/win.ini"] {
+            let errors = pack(&request(&[format!("{}/a.rs", rel), pattern.to_string()])).unwrap_err();
+            assert_eq!(errors, vec![format!("{}: pattern must stay inside the project root", pattern)]);
+        }
+    }
+
+    #[test]
+    fn gitignored_files_are_reported_as_ignored() {
+        let (dir, rel) = scratch("ignore");
+        std::fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(dir.join("kept.txt"), "kept\n").unwrap();
+        std::fs::write(dir.join("debug.log"), "noise\n").unwrap();
+
+        let pack = pack(&request(&[format!("{}/**", rel), format!("{}/debug.log", rel), format!("{}/missing.txt", rel)])).unwrap();
+        let paths: Vec<&str> = pack.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec![format!("{}/.gitignore", rel), format!("{}/kept.txt", rel)]);
+        assert_eq!(
+            skipped(&pack),
+            vec![(format!("{}/debug.log", rel), SkipReason::Ignored), (format!("{}/missing.txt", rel), SkipReason::NotFound)]
+        );
+    }
+
+    #[test]
+    fn binary_and_oversized_files_are_skipped() {
+        let (dir, rel) = scratch("skips");
+        std::fs::write(dir.join("image.bin"), [0x89, b'P', b'N', b'G', 0, 1, 2]).unwrap();
+        std::fs::write(dir.join("big.txt"), "x".repeat(200)).unwrap();
+        std::fs::write(dir.join("small.txt"), "ok\n").unwrap();
+
+        let pack = pack(&PackContextRequest {
+            max_file_size: Some(100),
+            ..request(std::slice::from_ref(&rel))
+        })
+        .unwrap();
+        assert_eq!(pack.files.len(), 1);
+        assert_eq!(pack.files[0].path, format!("{}/small.txt", rel));
+        assert_eq!(
+            skipped(&pack),
+            vec![(format!("{}/big.txt", rel), SkipReason::TooLarge), (format!("{}/image.bin", rel), SkipReason::Binary)]
+        );
+    }
+
+    #[test]
+    fn the_token_budget_goes_to_earlier_patterns_first() {
+        let (dir, rel) = scratch("budget");
+        std::fs::write(dir.join("first.txt"), "a".repeat(40)).unwrap();
+        std::fs::write(dir.join("second.txt"), "b".repeat(40)).unwrap();
+        std::fs::write(dir.join("third.txt"), "c".repeat(8)).unwrap();
+
+        let pack = pack(&PackContextRequest {
+            max_tokens: Some(14),
+            ..request(&[format!("{}/second.txt", rel), format!("{}/*.txt", rel)])
+        })
+        .unwrap();
+        let files: Vec<(&str, u64)> = pack.files.iter().map(|f| (f.path.as_str(), f.tokens)).collect();
+        assert_eq!(files, vec![(format!("{}/second.txt", rel).as_str(), 10), (format!("{}/third.txt", rel).as_str(), 2)]);
+        assert_eq!(skipped(&pack), vec![(format!("{}/first.txt", rel), SkipReason::OverBudget)]);
+    }
+
+    #[test]
+    fn fences_outrun_backticks_in_the_content() {
+        assert_eq!(fence_for("plain"), "```");
+        assert_eq!(fence_for("inline `code` only"), "```");
+        assert_eq!(fence_for("```rust\nfn a() {}\n```"), "````");
+        assert_eq!(fence_for("`````"), "``````");
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+}
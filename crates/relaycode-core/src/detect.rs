@@ -0,0 +1,381 @@
+use crate::{diff, patch};
+use relaycode_schema::{PatchDetection, PatchFormat, PatchSegment};
+
+/// Below this the text is treated as ordinary clipboard content rather than a patch.
+pub const MIN_CONFIDENCE: f64 = 0.6;
+
+struct Scored {
+    segment: PatchSegment,
+    confidence: f64,
+}
+
+fn segment(format: PatchFormat, path: Option<String>, lines: &[&str], start: usize, end: usize) -> PatchSegment {
+    PatchSegment {
+        format,
+        path,
+        content: lines[start..end].join("\n"),
+        start_line: start + 1,
+        end_line: end,
+    }
+}
+
+/// Identifies which patch formats `text` contains, how sure we are, and where each piece sits.
+pub fn classify(text: &str) -> PatchDetection {
+    let lines: Vec<&str> = text.lines().collect();
+    let fences = fenced_blocks(&lines);
+
+    let mut found = Vec::new();
+    found.extend(detect_format_patch(&lines));
+    found.extend(detect_unified_diff(&lines));
+    found.extend(detect_search_replace(&lines, &fences));
+    let footer = detect_yaml_footer(&lines, &fences);
+    let mut whole_files = detect_whole_files(&lines, &fences);
+    if footer.is_some() {
+        // The footer is only ever emitted alongside file blocks, so it vouches for them.
+        for scored in &mut whole_files {
+            scored.confidence = scored.confidence.max(0.95);
+        }
+    }
+    found.extend(whole_files);
+
+    let best = found
+        .iter()
+        .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+        .map(|scored| (scored.segment.format, scored.confidence));
+    found.extend(footer);
+    found.sort_by_key(|scored| scored.segment.start_line);
+    let segments: Vec<PatchSegment> = found.into_iter().map(|scored| scored.segment).collect();
+
+    let (format, confidence) = match best {
+        Some((format, confidence)) => (Some(format), confidence),
+        // A footer on its own describes changes that are not there.
+        None if !segments.is_empty() => (Some(PatchFormat::RelaycodeYaml), 0.4),
+        None => (None, 0.0),
+    };
+    let is_patch = confidence >= MIN_CONFIDENCE;
+    PatchDetection {
+        format,
+        confidence,
+        is_patch,
+        ingestible: is_patch && format.is_some_and(ingestible),
+        segments,
+    }
+}
+
+/// Formats [`crate::parse_patch_text`] turns into transactions.
+pub fn ingestible(format: PatchFormat) -> bool {
+    matches!(format, PatchFormat::UnifiedDiff | PatchFormat::FormatPatch)
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+/// `(open, close)` line indices of every closed ``` block.
+fn fenced_blocks(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut open = None;
+    for (i, line) in lines.iter().enumerate() {
+        if !is_fence(line) {
+            continue;
+        }
+        match open.take() {
+            None => open = Some(i),
+            Some(start) => blocks.push((start, i)),
+        }
+    }
+    blocks
+}
+
+pub(crate) fn is_mbox_separator(line: &str) -> bool {
+    // `From 1a2b…40 hex… Mon Sep 17 00:00:00 2001`
+    line.strip_prefix("From ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .is_some_and(|hash| hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn detect_format_patch(lines: &[&str]) -> Vec<Scored> {
+    let mut starts: Vec<usize> = (0..lines.len()).filter(|&i| is_mbox_separator(lines[i])).collect();
+    if starts.is_empty() {
+        // Mail clients often drop the separator line but keep the headers.
+        starts = (0..lines.len())
+            .filter(|&i| {
+                lines[i].starts_with("From: ")
+                    && lines[i + 1..].iter().take(10).any(|line| line.starts_with("Subject: "))
+            })
+            .collect();
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(lines.len());
+            let body = &lines[start..end];
+            let has_diff = body.iter().any(|line| line.starts_with("diff --git "));
+            let subject = body
+                .iter()
+                .find_map(|line| line.strip_prefix("Subject: "))
+                .is_some_and(|subject| subject.contains("[PATCH"));
+            let confidence = match (has_diff, subject) {
+                (true, _) => 0.97,
+                (false, true) => 0.5,
+                (false, false) => 0.2,
+            };
+            Scored {
+                segment: segment(PatchFormat::FormatPatch, None, lines, start, end),
+                confidence,
+            }
+        })
+        .filter(|scored| scored.confidence >= MIN_CONFIDENCE)
+        .collect()
+}
+
+fn is_plain_header(lines: &[&str], i: usize) -> bool {
+    lines[i].starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "))
+}
+
+/// True when every hunk's line counts agree with its header.
+fn counts_match(hunks: &[diff::Hunk]) -> bool {
+    hunks.iter().all(|hunk| {
+        let old = hunk.lines.iter().filter(|l| l.kind != diff::LineKind::Added).count();
+        let new = hunk.lines.iter().filter(|l| l.kind != diff::LineKind::Removed).count();
+        old == hunk.old_count && new == hunk.new_count
+    })
+}
+
+fn is_file_header(line: &str) -> bool {
+    ["--- ", "+++ ", "new file mode", "deleted file mode", "rename from ", "rename to "]
+        .iter()
+        .chain(patch::EXTENDED_HEADERS)
+        .any(|prefix| line.starts_with(prefix))
+}
+
+fn detect_unified_diff(lines: &[&str]) -> Vec<Scored> {
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let git_header = lines[i].starts_with("diff --git ");
+        if !git_header && !is_plain_header(lines, i) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let (mut in_hunks, mut binary) = (false, false);
+        i += if git_header { 1 } else { 2 };
+        while i < lines.len() {
+            let line = lines[i];
+            // `-- ` opens the signature that `git format-patch` appends after the last diff.
+            if line.starts_with("diff --git ") || is_fence(line) || line == "-- " || (in_hunks && is_plain_header(lines, i)) {
+                break;
+            }
+            let belongs = if line.starts_with("@@") {
+                in_hunks = true;
+                true
+            } else if line.starts_with("GIT binary patch") {
+                binary = true;
+                true
+            } else if in_hunks {
+                matches!(line.chars().next(), None | Some('+' | '-' | ' ' | '\\'))
+            } else {
+                binary || is_file_header(line)
+            };
+            if !belongs {
+                break;
+            }
+            i += 1;
+        }
+        let mut end = i;
+        while end > start + 1 && lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+
+        let section = lines[start..end].join("\n");
+        let (patches, _) = patch::split_unified_diff(&section);
+        let Some(file) = patches.into_iter().next() else {
+            continue;
+        };
+        let hunks = diff::parse_hunks(&file.diff);
+        let changes = hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .any(|line| line.kind != diff::LineKind::Context);
+        let header_only = git_header
+            && lines[start..end].iter().any(|line| {
+                ["rename from ", "new mode ", "Binary files ", "GIT binary patch"]
+                    .iter()
+                    .any(|marker| line.starts_with(marker))
+            });
+        let mut confidence = match (changes, git_header) {
+            (true, true) => 0.95,
+            (true, false) => 0.85,
+            (false, _) if header_only => 0.8,
+            (false, _) => 0.2,
+        };
+        if changes && !counts_match(&hunks) {
+            confidence -= 0.1;
+        }
+        if confidence >= MIN_CONFIDENCE {
+            found.push(Scored {
+                segment: segment(PatchFormat::UnifiedDiff, Some(file.path()), lines, start, end),
+                confidence,
+            });
+        }
+    }
+    found
+}
+
+/// Accepts `src/app.ts`, `./Dockerfile`, `crates/core/src/lib.rs`; rejects prose and URLs.
+fn path_like(token: &str) -> Option<String> {
+    let token = token.trim().trim_matches('`');
+    if token.is_empty() || token.len() > 260 || token.contains(char::is_whitespace) || token.contains("://") {
+        return None;
+    }
+    if token.starts_with('-') || token.ends_with('/') || token.ends_with('.') {
+        return None;
+    }
+    let name = token.rsplit('/').next().unwrap_or(token);
+    let has_extension = name.rsplit_once('.').is_some_and(|(stem, ext)| {
+        // `.env` counts, `v1.2` does not.
+        ext.starts_with(|c: char| c.is_ascii_alphabetic()) && ext.chars().all(|c| c.is_ascii_alphanumeric()) && (!stem.is_empty() || name.len() > 1)
+    });
+    (has_extension || token.contains('/') || name == "Dockerfile" || name == "Makefile")
+        .then(|| token.trim_start_matches("./").to_string())
+}
+
+/// Reads a path from `// src/a.ts`, `# app.py`, `<!-- index.html -->` and similar one-line comments.
+/// Anything after the path (such as a strategy name) is ignored.
+fn path_from_comment(line: &str) -> Option<String> {
+    let line = line.trim();
+    let rest = ["//", "#", "--", "/*", "<!--", ";"]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))?;
+    let rest = rest.trim_end_matches("*/").trim_end_matches("-->");
+    path_like(rest.split_whitespace().next()?)
+}
+
+/// Path from a fence info string: "```ts // src/a.ts" or "```ts src/a.ts".
+fn path_from_fence(line: &str) -> Option<String> {
+    let info = line.trim_start().trim_start_matches('`');
+    let mut words = info.split_whitespace();
+    let first = words.next()?;
+    if let Some(path) = path_like(first) {
+        return Some(path);
+    }
+    let rest: Vec<&str> = words.collect();
+    path_from_comment(&rest.join(" ")).or_else(|| rest.first().and_then(|word| path_like(word)))
+}
+
+fn detect_search_replace(lines: &[&str], fences: &[(usize, usize)]) -> Vec<Scored> {
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !lines[i].trim_start().starts_with("<<<<<<< SEARCH") {
+            i += 1;
+            continue;
+        }
+        let search = i;
+        let divider = (search + 1..lines.len()).find(|&j| lines[j].trim() == "=======");
+        let replace = divider.and_then(|divider| {
+            (divider + 1..lines.len()).find(|&j| lines[j].trim_start().starts_with(">>>>>>> REPLACE"))
+        });
+        let Some(replace) = replace else {
+            i += 1;
+            continue;
+        };
+
+        // The path sits on the fence line, the line before the fence, or the line before the marker.
+        let fence = fences.iter().find(|(open, close)| *open < search && replace < *close);
+        let path = match fence {
+            Some(&(open, _)) => path_from_fence(lines[open])
+                .or_else(|| (open + 1 < search).then(|| path_from_comment(lines[open + 1])).flatten())
+                .or_else(|| open.checked_sub(1).and_then(|prev| path_like(lines[prev]))),
+            None => search.checked_sub(1).and_then(|prev| path_like(lines[prev]).or_else(|| path_from_comment(lines[prev]))),
+        };
+        found.push(Scored {
+            segment: segment(PatchFormat::SearchReplace, path, lines, search, replace + 1),
+            confidence: 0.95,
+        });
+        i = replace + 1;
+    }
+    found
+}
+
+fn is_yaml_footer(body: &[&str]) -> bool {
+    body.iter().any(|line| line.starts_with("projectId:"))
+        && body.iter().any(|line| line.starts_with("uuid:") || line.starts_with("changeSummary:"))
+}
+
+fn detect_yaml_footer(lines: &[&str], fences: &[(usize, usize)]) -> Option<Scored> {
+    fences
+        .iter()
+        .rev()
+        .find(|&&(open, close)| {
+            let lang = lines[open].trim_start().trim_start_matches('`').trim();
+            matches!(lang, "yaml" | "yml") && is_yaml_footer(&lines[open + 1..close])
+        })
+        .map(|&(open, close)| Scored {
+            segment: segment(PatchFormat::RelaycodeYaml, None, lines, open + 1, close),
+            confidence: 0.9,
+        })
+}
+
+fn detect_whole_files(lines: &[&str], fences: &[(usize, usize)]) -> Vec<Scored> {
+    fences
+        .iter()
+        .filter_map(|&(open, close)| {
+            let body = &lines[open + 1..close];
+            let other_format = body.iter().any(|line| {
+                line.starts_with("diff --git ") || line.starts_with("@@ ") || line.trim_start().starts_with("<<<<<<< SEARCH")
+            });
+            if other_format || is_yaml_footer(body) {
+                return None;
+            }
+            let (path, content_start) = match path_from_fence(lines[open]) {
+                Some(path) => (path, open + 1),
+                None => (path_from_comment(body.first()?)?, open + 2),
+            };
+            Some(Scored {
+                segment: segment(PatchFormat::WholeFile, Some(path), lines, content_start, close),
+                confidence: 0.8,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordinary_text_is_not_a_patch() {
+        for text in [
+            "",
+            "Meeting notes: ship the release on Friday.",
+            "error[E0308]: mismatched types\n --> src/main.rs:4:5\n  |\n4 |     1\n  |     ^ expected `()`",
+            "- buy milk\n- call Sam\n+ remember the cake",
+            "```\nlet x = 1;\n```",
+        ] {
+            let detection = classify(text);
+            assert!(!detection.is_patch, "{:?} was taken for a patch: {:?}", text, detection.format);
+            assert!(!detection.ingestible);
+        }
+    }
+
+    #[test]
+    fn unified_diffs_are_ingestible() {
+        let detection = classify("--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,1 +1,1 @@\n-old\n+new\n");
+        assert_eq!(detection.format, Some(PatchFormat::UnifiedDiff));
+        assert!(detection.is_patch && detection.ingestible);
+        assert_eq!(detection.segments[0].path.as_deref(), Some("src/a.rs"));
+    }
+
+    #[test]
+    fn search_replace_blocks_are_recognised_but_not_ingestible() {
+        let detection = classify("src/a.rs\n<<<<<<< SEARCH\nold\n=======\nnew\n>>>>>>> REPLACE\n");
+        assert_eq!(detection.format, Some(PatchFormat::SearchReplace));
+        assert!(detection.is_patch);
+        assert!(!detection.ingestible);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::diff;
+use relaycode_schema::{Diagnostic, DiagnosticSeverity, LintSummary, Transaction, TransactionFile};
+use std::path::Path;
+
+/// Parses linter/compiler output into diagnostics.
+///
+/// Understands cargo's `--message-format=json`, `tsc` (`file(line,col): error TS…`), and the
+/// `file:line[:col]: message` shape used by gcc, eslint's unix formatter, `tsc --pretty` and most others.
+pub fn parse_output(output: &str, project_root: &Path) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_end();
+            parse_cargo_json(line)
+                .or_else(|| parse_tsc(line))
+                .or_else(|| parse_colon_format(line))
+        })
+        .map(|mut diagnostic| {
+            diagnostic.file_path = normalize_path(&diagnostic.file_path, project_root);
+            diagnostic
+        })
+        .collect()
+}
+
+fn parse_cargo_json(line: &str) -> Option<Diagnostic> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    if value["reason"] != "compiler-message" {
+        return None;
+    }
+    let message = &value["message"];
+    let severity = match message["level"].as_str()? {
+        "error" => DiagnosticSeverity::Error,
+        "warning" => DiagnosticSeverity::Warning,
+        _ => return None,
+    };
+    let span = message["spans"]
+        .as_array()?
+        .iter()
+        .find(|span| span["is_primary"].as_bool() == Some(true))?;
+    Some(Diagnostic {
+        file_path: span["file_name"].as_str()?.to_string(),
+        line: span["line_start"].as_u64()? as u32,
+        column: span["column_start"].as_u64().map(|c| c as u32),
+        severity,
+        message: message["message"].as_str()?.to_string(),
+        rule: message["code"]["code"].as_str().map(str::to_string),
+        diff_line: None,
+    })
+}
+
+/// `src/app.ts(12,5): error TS2322: Type 'string' is not assignable to type 'number'.`
+fn parse_tsc(line: &str) -> Option<Diagnostic> {
+    let open = line.find('(')?;
+    // A `(1,2): ` inside the message of another format is not a location.
+    let path = &line[..open];
+    if path.is_empty() || path.contains(char::is_whitespace) || path[drive_prefix_len(path)..].contains(':') {
+        return None;
+    }
+    let close = open + line[open..].find("): ")?;
+    let (row, col) = line[open + 1..close].split_once(',')?;
+    let (line_number, column) = (row.trim().parse().ok()?, col.trim().parse().ok()?);
+    let (severity, rule, message) = split_severity(&line[close + 3..])?;
+    Some(Diagnostic {
+        file_path: line[..open].to_string(),
+        line: line_number,
+        column: Some(column),
+        severity,
+        message,
+        rule,
+        diff_line: None,
+    })
+}
+
+/// `path:line[:col]: message` or `path:line:col - error TS1005: message`.
+fn parse_colon_format(line: &str) -> Option<Diagnostic> {
+    // Skip a Windows drive letter so `C:\src\a.ts:3:1` splits at the right colon.
+    let skip = drive_prefix_len(line);
+
+    for (offset, _) in line[skip..].match_indices(':') {
+        let at = skip + offset;
+        let Some((line_number, column, rest)) = parse_location(&line[at + 1..]) else {
+            continue;
+        };
+        let path = line[..at].trim();
+        if path.is_empty() || path.contains(char::is_whitespace) {
+            return None;
+        }
+        let rest = rest.trim();
+        let (severity, rule, message) = split_severity(rest).unwrap_or_else(|| split_eslint_suffix(rest));
+        return Some(Diagnostic {
+            file_path: path.to_string(),
+            line: line_number,
+            column,
+            severity,
+            message,
+            rule,
+            diff_line: None,
+        });
+    }
+    None
+}
+
+/// 2 for a path starting with a drive such as `C:\` or `c:/`, else 0. A bare `x:` is a file named `x`.
+fn drive_prefix_len(path: &str) -> usize {
+    match path.as_bytes() {
+        [drive, b':', b'\\' | b'/', ..] if drive.is_ascii_alphabetic() => 2,
+        _ => 0,
+    }
+}
+
+/// Parses `12:5: rest`, `12: rest` or `12:5 - rest`.
+fn parse_location(text: &str) -> Option<(u32, Option<u32>, &str)> {
+    let (line_number, rest) = leading_number(text)?;
+    let (column, rest) = match rest.strip_prefix(':').and_then(leading_number) {
+        Some((column, rest)) => (Some(column), rest),
+        None => (None, rest),
+    };
+    let message = rest.strip_prefix(':').or_else(|| rest.strip_prefix(" - "))?;
+    Some((line_number, column, message))
+}
+
+fn leading_number(text: &str) -> Option<(u32, &str)> {
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    Some((text[..digits].parse().ok()?, &text[digits..]))
+}
+
+/// Splits a leading `error TS2322:`, `warning:` or `error[E0308]:` off a message.
+fn split_severity(text: &str) -> Option<(DiagnosticSeverity, Option<String>, String)> {
+    let text = text.trim_start();
+    let lower = text.to_lowercase();
+    let (severity, keyword) = [
+        (DiagnosticSeverity::Error, "error"),
+        (DiagnosticSeverity::Error, "fatal error"),
+        (DiagnosticSeverity::Warning, "warning"),
+        (DiagnosticSeverity::Info, "note"),
+        (DiagnosticSeverity::Info, "info"),
+    ]
+    .into_iter()
+    .find(|(_, keyword)| lower.starts_with(keyword))?;
+
+    let after = &text[keyword.len()..];
+    let (rule, after) = if let Some(bracketed) = after.strip_prefix('[') {
+        let end = bracketed.find(']')?;
+        (Some(bracketed[..end].to_string()), &bracketed[end + 1..])
+    } else if let Some(code) = after.strip_prefix(' ').filter(|a| a.split(':').next().is_some_and(is_rule_code)) {
+        let end = code.find(':').unwrap_or(code.len());
+        (Some(code[..end].to_string()), &code[end..])
+    } else {
+        (None, after)
+    };
+    let message = after.strip_prefix(':')?.trim().to_string();
+    Some((severity, rule, message))
+}
+
+fn is_rule_code(candidate: &str) -> bool {
+    !candidate.is_empty()
+        && !candidate.contains(' ')
+        && candidate.chars().any(|c| c.is_ascii_digit())
+        && candidate.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// eslint's unix formatter ends messages with `[Error/rule-name]`.
+fn split_eslint_suffix(text: &str) -> (DiagnosticSeverity, Option<String>, String) {
+    if let Some(open) = text.rfind(" [") {
+        if let Some(inner) = text[open + 2..].strip_suffix(']') {
+            let (level, rule) = inner.split_once('/').unwrap_or((inner, ""));
+            let severity = match level.to_lowercase().as_str() {
+                "error" => Some(DiagnosticSeverity::Error),
+                "warning" => Some(DiagnosticSeverity::Warning),
+                _ => None,
+            };
+            if let Some(severity) = severity {
+                let rule = (!rule.is_empty()).then(|| rule.to_string());
+                return (severity, rule, text[..open].trim().to_string());
+            }
+        }
+    }
+    (DiagnosticSeverity::Error, None, text.to_string())
+}
+
+/// Makes a reported path comparable with `TransactionFile.path`.
+pub fn normalize_path(path: &str, project_root: &Path) -> String {
+    let path = path.replace('\\', "/");
+    let root = project_root
+        .canonicalize()
+        .unwrap_or_else(|_| project_root.to_path_buf())
+        .to_string_lossy()
+        .replace('\\', "/");
+    let relative = path
+        .strip_prefix(&format!("{}/", root.trim_end_matches('/')))
+        .unwrap_or(&path);
+    relative.trim_start_matches("./").to_string()
+}
+
+pub fn count(diagnostics: &[Diagnostic], severity: DiagnosticSeverity) -> usize {
+    diagnostics.iter().filter(|d| d.severity == severity).count()
+}
+
+pub fn summarize(before: &[Diagnostic], after: &[Diagnostic]) -> LintSummary {
+    let errors_before = count(before, DiagnosticSeverity::Error);
+    let errors_after = count(after, DiagnosticSeverity::Error);
+    LintSummary {
+        errors_before,
+        errors_after,
+        warnings_before: count(before, DiagnosticSeverity::Warning),
+        warnings_after: count(after, DiagnosticSeverity::Warning),
+        error_delta: errors_after as i64 - errors_before as i64,
+    }
+}
+
+fn attach_to_file(file: &mut TransactionFile, diagnostics: &[Diagnostic]) {
+    let hunks = diff::parse_hunks(&file.diff);
+    file.diagnostics = diagnostics
+        .iter()
+        .filter(|d| d.file_path == file.path)
+        .map(|d| Diagnostic {
+            diff_line: diff::diff_index_for_new_line(&hunks, d.line as usize),
+            ..d.clone()
+        })
+        .collect();
+    file.diagnostics.sort_by_key(|d| (d.line, d.column));
+}
+
+/// Replaces each file's diagnostics with the ones reported for its path.
+pub fn attach(tx: &mut Transaction, diagnostics: &[Diagnostic]) {
+    for file in &mut tx.files {
+        attach_to_file(file, diagnostics);
+    }
+}
+
+/// All file diagnostics of a transaction, in file order then line order.
+pub fn collect(tx: &Transaction) -> Vec<Diagnostic> {
+    tx.files.iter().flat_map(|file| file.diagnostics.iter().cloned()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Diagnostic {
+        let mut found = parse_output(line, Path::new("/work/project"));
+        assert_eq!(found.len(), 1, "{:?} gave {:?}", line, found);
+        found.remove(0)
+    }
+
+    fn summary(d: &Diagnostic) -> (&str, u32, Option<u32>, DiagnosticSeverity, Option<&str>, &str) {
+        (&d.file_path, d.line, d.column, d.severity.clone(), d.rule.as_deref(), &d.message)
+    }
+
+    #[test]
+    fn cargo_json_messages() {
+        let line = serde_json::json!({
+            "reason": "compiler-message",
+            "message": {
+                "level": "error",
+                "message": "mismatched types",
+                "code": { "code": "E0308" },
+                "spans": [
+                    { "file_name": "src/other.rs", "line_start": 1, "column_start": 1, "is_primary": false },
+                    { "file_name": "src/main.rs", "line_start": 4, "column_start": 5, "is_primary": true },
+                ],
+            },
+        })
+        .to_string();
+        assert_eq!(
+            summary(&parse(&line)),
+            ("src/main.rs", 4, Some(5), DiagnosticSeverity::Error, Some("E0308"), "mismatched types")
+        );
+        assert!(parse_output(r#"{"reason":"build-finished","success":true}"#, Path::new(".")).is_empty());
+    }
+
+    #[test]
+    fn tsc_messages() {
+        let d = parse("src/app.ts(12,5): error TS2322: Type 'string' is not assignable to type 'number'.");
+        assert_eq!(
+            summary(&d),
+            ("src/app.ts", 12, Some(5), DiagnosticSeverity::Error, Some("TS2322"), "Type 'string' is not assignable to type 'number'.")
+        );
+        let d = parse("src/app.ts:3:7 - error TS1005: ';' expected.");
+        assert_eq!(summary(&d), ("src/app.ts", 3, Some(7), DiagnosticSeverity::Error, Some("TS1005"), "';' expected."));
+    }
+
+    #[test]
+    fn gcc_style_messages() {
+        let d = parse("src/a.c:3:5: warning: unused variable 'x' [-Wunused-variable]");
+        assert_eq!(
+            summary(&d),
+            ("src/a.c", 3, Some(5), DiagnosticSeverity::Warning, None, "unused variable 'x' [-Wunused-variable]")
+        );
+        let d = parse("app.py:12: note: defined here");
+        assert_eq!(summary(&d), ("app.py", 12, None, DiagnosticSeverity::Info, None, "defined here"));
+    }
+
+    #[test]
+    fn eslint_unix_messages() {
+        let d = parse("/work/project/src/a.js:1:10: 'x' is defined but never used. [Warning/no-unused-vars]");
+        assert_eq!(
+            summary(&d),
+            ("src/a.js", 1, Some(10), DiagnosticSeverity::Warning, Some("no-unused-vars"), "'x' is defined but never used.")
+        );
+    }
+
+    #[test]
+    fn windows_paths() {
+        let d = parse(r"C:\work\src\a.ts:3:1: error: cannot find name 'b'");
+        assert_eq!(summary(&d), ("C:/work/src/a.ts", 3, Some(1), DiagnosticSeverity::Error, None, "cannot find name 'b'"));
+        let d = parse(r"c:\work\src\a.ts(4,2): error TS2304: Cannot find name 'c'.");
+        assert_eq!((d.file_path.as_str(), d.line), ("c:/work/src/a.ts", 4));
+        let d = parse("x:7: error: a file named x");
+        assert_eq!((d.file_path.as_str(), d.line, d.column), ("x", 7, None));
+    }
+
+    #[test]
+    fn messages_containing_colons_and_locations() {
+        let d = parse("src/a.rs:3:1: error: expected one of: `a`, `b`; see foo(1,2): here");
+        assert_eq!(
+            summary(&d),
+            ("src/a.rs", 3, Some(1), DiagnosticSeverity::Error, None, "expected one of: `a`, `b`; see foo(1,2): here")
+        );
+        let d = parse("src/a.rs:10:2: error[E0599]: no method named `x` found: see src/b.rs:4:1: here");
+        assert_eq!((d.file_path.as_str(), d.line, d.rule.as_deref()), ("src/a.rs", 10, Some("E0599")));
+        assert_eq!(d.message, "no method named `x` found: see src/b.rs:4:1: here");
+    }
+
+    #[test]
+    fn ordinary_output_is_ignored() {
+        let output = "12:30:01 INFO server: started\nIn file included from src/a.h:3:10,\nDone in 2.1s";
+        assert!(parse_output(output, Path::new(".")).is_empty());
+    }
+
+    #[test]
+    fn summary_counts_the_change_in_errors() {
+        let before = parse_output("a.c:1:1: error: x\na.c:2:1: warning: y", Path::new("."));
+        let after = parse_output("a.c:1:1: error: x\na.c:3:1: error: z\nb.c:1:1: error: w", Path::new("."));
+        let summary = summarize(&before, &after);
+        assert_eq!((summary.errors_before, summary.errors_after, summary.error_delta), (1, 3, 2));
+        assert_eq!((summary.warnings_before, summary.warnings_after), (1, 0));
+    }
+}
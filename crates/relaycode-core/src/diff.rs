@@ -0,0 +1,182 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub text: String,
+    /// Zero-based index of this line within the diff text.
+    pub diff_index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    /// Text after the closing `@@`, usually the enclosing function.
+    pub section: String,
+    /// Zero-based index of the `@@` header within the diff text.
+    pub header_index: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    /// Pairs each context/added line with its one-based line number in the patched file.
+    pub fn new_side_lines(&self) -> impl Iterator<Item = (usize, &DiffLine)> {
+        let mut next = self.new_start.max(1);
+        self.lines.iter().filter(|l| l.kind != LineKind::Removed).map(move |line| {
+            let number = next;
+            next += 1;
+            (number, line)
+        })
+    }
+}
+
+/// Parses `@@ -a,b +c,d @@ section`; omitted counts default to 1.
+pub fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize, usize, String)> {
+    let rest = line.strip_prefix("@@ ")?;
+    let end = rest.find(" @@")?;
+    let mut ranges = rest[..end].split_whitespace();
+    let (old_start, old_count) = parse_range(ranges.next()?.strip_prefix('-')?)?;
+    let (new_start, new_count) = parse_range(ranges.next()?.strip_prefix('+')?)?;
+    let section = rest[end + 3..].trim().to_string();
+    Some((old_start, old_count, new_start, new_count, section))
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn is_file_header(line: &str) -> bool {
+    line.starts_with("diff ") || line.starts_with("--- ") || line.starts_with("+++ ") || line.starts_with("index ")
+}
+
+/// Splits a diff into hunks. Hunk headers with wrong counts are tolerated, since AI output often has them.
+pub fn parse_hunks(diff: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut current: Option<Hunk> = None;
+    let (mut old_seen, mut new_seen) = (0, 0);
+
+    for (index, line) in diff.lines().enumerate() {
+        if let Some((old_start, old_count, new_start, new_count, section)) = parse_hunk_header(line) {
+            hunks.extend(current.take());
+            current = Some(Hunk {
+                old_start,
+                old_count,
+                new_start,
+                new_count,
+                section,
+                header_index: index,
+                lines: Vec::new(),
+            });
+            old_seen = 0;
+            new_seen = 0;
+            continue;
+        }
+        let Some(hunk) = current.as_mut() else {
+            continue;
+        };
+        let exhausted = old_seen >= hunk.old_count && new_seen >= hunk.new_count;
+        if exhausted && is_file_header(line) {
+            hunks.extend(current.take());
+            continue;
+        }
+        let (kind, text) = match line.chars().next() {
+            Some('+') => (LineKind::Added, &line[1..]),
+            Some('-') => (LineKind::Removed, &line[1..]),
+            Some(' ') => (LineKind::Context, &line[1..]),
+            Some('\\') => continue,
+            None => (LineKind::Context, ""),
+            Some(_) => {
+                hunks.extend(current.take());
+                continue;
+            }
+        };
+        match kind {
+            LineKind::Added => new_seen += 1,
+            LineKind::Removed => old_seen += 1,
+            LineKind::Context => {
+                old_seen += 1;
+                new_seen += 1;
+            }
+        }
+        hunk.lines.push(DiffLine {
+            kind,
+            text: text.to_string(),
+            diff_index: index,
+        });
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// Checks that an edited diff is something the applier can use: at least one hunk, and nothing
+/// after the first hunk header that `parse_hunks` would silently drop.
+pub fn validate(diff: &str) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    let mut in_hunk = false;
+    for (index, line) in diff.lines().enumerate() {
+        if let Some(rest) = line.strip_prefix("@@") {
+            if parse_hunk_header(line).is_none() {
+                errors.push(format!("line {}: malformed hunk header `@@{}`", index + 1, rest));
+            }
+            in_hunk = true;
+            continue;
+        }
+        let diff_line = line.is_empty() || line.starts_with([' ', '+', '-', '\\']) || is_file_header(line);
+        if in_hunk && !diff_line {
+            errors.push(format!("line {}: expected ' ', '+' or '-' at the start of the line", index + 1));
+        }
+    }
+    let hunks = parse_hunks(diff);
+    if hunks.is_empty() && errors.is_empty() {
+        errors.push("Diff has no hunks".to_string());
+    }
+    for hunk in hunks.iter().filter(|hunk| hunk.lines.is_empty()) {
+        errors.push(format!("line {}: hunk has no lines", hunk.header_index + 1));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// The same hunks with their sides swapped, so that applying the result undoes `diff`. Lines
+/// before the first hunk header are kept as they are.
+pub fn reverse(diff: &str) -> String {
+    let mut in_hunks = false;
+    diff.lines()
+        .map(|line| {
+            if let Some((old_start, old_count, new_start, new_count, section)) = parse_hunk_header(line) {
+                in_hunks = true;
+                let header = format!("@@ -{},{} +{},{} @@", new_start, new_count, old_start, old_count);
+                return if section.is_empty() { header } else { format!("{} {}", header, section) };
+            }
+            match (in_hunks, line.chars().next()) {
+                (true, Some('+')) => format!("-{}", &line[1..]),
+                (true, Some('-')) => format!("+{}", &line[1..]),
+                _ => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Maps a one-based line in the patched file to the diff line that produced it, if the diff shows it.
+pub fn diff_index_for_new_line(hunks: &[Hunk], new_line: usize) -> Option<usize> {
+    hunks
+        .iter()
+        .flat_map(|hunk| hunk.new_side_lines())
+        .find(|(number, _)| *number == new_line)
+        .map(|(_, line)| line.diff_index)
+}
@@ -0,0 +1,124 @@
+use crate::{apply, config, diff, STORE};
+use relaycode_schema::{FileApplyStatus, Transaction, TransactionFile};
+
+/// Lines of current content shown above and below each failed hunk.
+const CONTEXT_LINES: usize = 10;
+
+const RESPONSE_FORMAT: &str = "Reply with one fenced ```diff block holding a unified diff for every file above, \
+each starting with `--- a/<path>` and `+++ b/<path>` headers. Base the hunks on the current content shown here, \
+not on the failing diff, and keep the intended change. No other code blocks; a short explanation before the \
+diff is fine. The reply can be pasted straight into Relaycode's patch import.";
+
+/// One-based, inclusive line windows of `content` around each failed hunk, merged where they touch.
+fn windows(content: &str, failed_hunks: &[String]) -> Vec<(usize, usize)> {
+    let total = content.lines().count();
+    let mut ranges: Vec<(usize, usize)> = failed_hunks
+        .iter()
+        .flat_map(|text| diff::parse_hunks(text))
+        .map(|hunk| {
+            let start = hunk.old_start.saturating_sub(CONTEXT_LINES).max(1);
+            let last = hunk.old_start + hunk.old_count.saturating_sub(1);
+            (start, (last + CONTEXT_LINES).min(total))
+        })
+        .filter(|(start, end)| start <= end)
+        .collect();
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn render_file(file: &TransactionFile) -> String {
+    let mut section = format!("### `{}`\n\n", file.path);
+    let error = file.error_message.as_deref().unwrap_or("No error message recorded");
+    section.push_str(&format!("**Error:** {}\n\n", error));
+
+    let content = apply::resolve_in_root(&config::project_root(), &file.path)
+        .and_then(|full| std::fs::read_to_string(full).map_err(|err| err.to_string()));
+    match content {
+        Ok(content) => {
+            let failed = apply::failed_hunks(&content, &file.diff);
+            let lines: Vec<&str> = content.lines().collect();
+            let shown = windows(&content, &failed);
+            if failed.is_empty() {
+                section.push_str("**Current content:** every hunk matches the file as it is now.\n\n");
+            } else if shown.is_empty() {
+                section.push_str("**Current content:** the failing hunks point past the end of the file.\n\n");
+            }
+            for (start, end) in shown {
+                section.push_str(&format!("**Current content, lines {}-{} of {}:**\n\n", start, end, lines.len()));
+                section.push_str(&format!("```{}\n", file.language));
+                for (number, line) in lines[start - 1..end].iter().enumerate() {
+                    section.push_str(&format!("{:>5} | {}\n", start + number, line));
+                }
+                section.push_str("```\n\n");
+            }
+        }
+        Err(err) => section.push_str(&format!("**Current content:** unavailable ({}).\n\n", err)),
+    }
+
+    section.push_str(&format!("**Failing diff:**\n\n```diff\n{}\n```\n", file.diff.trim_end()));
+    section
+}
+
+fn failed_files(tx: &Transaction) -> Vec<&TransactionFile> {
+    tx.files
+        .iter()
+        .filter(|file| file.apply_status == FileApplyStatus::Failed)
+        .collect()
+}
+
+/// A self-contained markdown prompt asking an outside model to fix the transaction's failed files.
+/// `None` when the transaction does not exist; `Some(Err)` when none of its files failed.
+pub fn render(id: &str) -> Option<Result<String, String>> {
+    let tx = STORE.get_transaction(id)?;
+    let failed = failed_files(&tx);
+    if failed.is_empty() {
+        return Some(Err("No failed files to hand off".to_string()));
+    }
+
+    let prompt = STORE.get_prompts().into_iter().find(|p| p.id == tx.prompt_id);
+    let mut markdown = format!("# Repair request: {}\n\n", tx.description);
+    let source: Vec<&str> = [tx.provider.as_str(), tx.model.as_str()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+    let source = if source.is_empty() { "an AI assistant".to_string() } else { source.join(" / ") };
+    markdown.push_str(&format!(
+        "These changes were generated by {} for transaction `{}`, but {} of its files could not be applied. \
+         Please produce corrected patches for them.\n\n",
+        source,
+        tx.id,
+        failed.len()
+    ));
+
+    markdown.push_str("## Original prompt\n\n");
+    match prompt {
+        Some(prompt) => markdown.push_str(&format!("{}\n\n", prompt.content.trim())),
+        None => markdown.push_str("_The original prompt is not available._\n\n"),
+    }
+
+    markdown.push_str("## Reasoning behind the changes\n\n");
+    if tx.reasoning.trim().is_empty() {
+        markdown.push_str("_No reasoning was recorded._\n\n");
+    } else {
+        markdown.push_str(&format!("{}\n\n", tx.reasoning.trim()));
+    }
+
+    markdown.push_str("## Failed files\n\n");
+    for file in failed {
+        markdown.push_str(&render_file(file));
+        markdown.push('\n');
+    }
+
+    markdown.push_str("## Expected response\n\n");
+    markdown.push_str(RESPONSE_FORMAT);
+    markdown.push('\n');
+    Some(Ok(markdown))
+}
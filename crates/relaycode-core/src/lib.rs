@@ -1,4 +1,31 @@
-use relaycode_schema::{FileApplyStatus, FileStatusEvent, SimulationScenario, Transaction, TransactionStatus, TransactionFile, Prompt};
+pub mod ai;
+pub mod analytics;
+pub mod apply;
+pub mod approval;
+pub mod batch;
+pub mod chain;
+pub mod commit_message;
+pub mod config;
+pub mod conflicts;
+pub mod context;
+pub mod detect;
+pub mod diagnostics;
+pub mod diff;
+pub mod handoff;
+pub mod mbox;
+pub mod migrate;
+pub mod patch;
+pub mod pricing;
+pub mod repair;
+pub mod review;
+pub mod runner;
+pub mod system_prompt;
+#[cfg(test)]
+mod testing;
+pub mod timestamps;
+pub mod watcher;
+
+use relaycode_schema::{ApprovalMethod, ApprovalRecord, ApprovalRule, Batch, CommandOutputEvent, FileApplyStatus, FileStatusEvent, LineRange, NewTransaction, RepairEvent, SimulationScenario, StatusTransition, Transaction, TransactionBlock, TransactionFile, TransactionStatus, Prompt, RelayConfig, ValidationKind, ValidationRun, WatcherStatus};
 use std::sync::{Arc, RwLock};
 use tokio::sync::broadcast;
 
@@ -8,6 +35,9 @@ pub type Subscriber = Arc<dyn Fn(Transaction) + Send + Sync>;
 pub struct AppState {
     pub transactions: Vec<Transaction>,
     pub prompts: Vec<Prompt>,
+    pub config: RelayConfig,
+    pub batches: Vec<Batch>,
+    pub watcher: WatcherStatus,
     active_simulations: std::collections::HashSet<String>,
 }
 
@@ -15,40 +45,66 @@ pub struct Store {
     pub state: Arc<RwLock<AppState>>,
     pub tx_sender: broadcast::Sender<Transaction>,
     pub file_sender: broadcast::Sender<FileStatusEvent>,
+    pub output_sender: broadcast::Sender<CommandOutputEvent>,
+    pub watcher_sender: broadcast::Sender<WatcherStatus>,
+    pub repair_sender: broadcast::Sender<RepairEvent>,
 }
 
 impl Default for Store {
     fn default() -> Self {
         let (tx_sender, _) = broadcast::channel(100);
         let (file_sender, _) = broadcast::channel(100);
+        let (output_sender, _) = broadcast::channel(1000);
+        let (watcher_sender, _) = broadcast::channel(100);
+        let (repair_sender, _) = broadcast::channel(100);
         Self {
             state: Arc::new(RwLock::new(AppState::default())),
             tx_sender,
             file_sender,
+            output_sender,
+            watcher_sender,
+            repair_sender,
         }
     }
 }
 
+/// Why [`Store::replace_file_diff`] refused an edit.
+#[derive(Debug)]
+pub enum DiffEditError {
+    /// The path is not part of the transaction, or the diff does not parse.
+    Invalid(Vec<String>),
+    /// The file has not failed; edits are only for repairing failed files.
+    NotFailed(String),
+}
+
 impl Store {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn load_data(&self) {
+    /// Replaces transactions and prompts with the bundled mock data. On an error the store is
+    /// left as it was.
+    pub fn load_data(&self) -> serde_json::Result<()> {
         let data = include_str!("data/mock-data.json");
-        let parsed: serde_json::Value = serde_json::from_str(data).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(data)?;
         
-        let transactions: Vec<Transaction> = serde_json::from_value(
-            parsed["transactions"].clone()
-        ).unwrap();
+        let mut raw_transactions = parsed["transactions"].clone();
+        if let Some(items) = raw_transactions.as_array_mut() {
+            items.iter_mut().for_each(migrate::legacy_transaction);
+        }
+        let transactions: Vec<Transaction> = serde_json::from_value(raw_transactions)?;
         
-        let prompts: Vec<Prompt> = serde_json::from_value(
-            parsed["prompts"].clone()
-        ).unwrap();
+        let mut raw_prompts = parsed["prompts"].clone();
+        if let Some(items) = raw_prompts.as_array_mut() {
+            items.iter_mut().for_each(migrate::legacy_prompt);
+        }
+        let prompts: Vec<Prompt> = serde_json::from_value(raw_prompts)?;
 
         let mut state = self.state.write().unwrap();
         state.transactions = transactions;
         state.prompts = prompts;
+        state.batches.clear();
+        Ok(())
     }
 
     pub fn get_transactions(&self, limit: usize, page: usize, search: Option<&str>, status: Option<&str>) -> Vec<Transaction> {
@@ -65,31 +121,68 @@ impl Store {
             result.retain(|t| {
                 t.description.to_lowercase().contains(&search_lower)
                     || t.author.to_lowercase().contains(&search_lower)
+                    || t.files.iter().any(|f| f.path.to_lowercase().contains(&search_lower))
                     || t.blocks.iter().any(|b| {
-                        if let Some(content) = &b.content {
-                            content.to_lowercase().contains(&search_lower)
-                        } else if let Some(file) = &b.file {
-                            file.path.to_lowercase().contains(&search_lower)
-                        } else {
-                            false
-                        }
+                        let text = match b {
+                            TransactionBlock::File { .. } => return false,
+                            TransactionBlock::Command { command, .. } => command,
+                            TransactionBlock::Markdown { content }
+                            | TransactionBlock::PlanStep { content, .. }
+                            | TransactionBlock::Note { content } => content,
+                        };
+                        text.to_lowercase().contains(&search_lower)
                     })
             });
         }
 
+        result.sort_by_key(|tx| std::cmp::Reverse(tx.created_at));
         let start = (page.saturating_sub(1)) * limit;
-        result[start..].iter().take(limit).cloned().collect()
+        result[start..]
+            .iter()
+            .take(limit)
+            .cloned()
+            .map(|mut tx| {
+                timestamps::refresh(&mut tx);
+                tx
+            })
+            .collect()
     }
 
     pub fn get_prompts(&self) -> Vec<Prompt> {
         let state = self.state.read().unwrap();
-        state.prompts.clone()
+        let mut prompts = state.prompts.clone();
+        prompts.iter_mut().for_each(timestamps::refresh_prompt);
+        prompts
+    }
+
+    pub fn load_config(&self) -> Result<(), config::ConfigError> {
+        let loaded = config::load_config()?;
+        self.state.write().unwrap().config = loaded;
+        Ok(())
+    }
+
+    pub fn get_config(&self) -> RelayConfig {
+        let state = self.state.read().unwrap();
+        state.config.clone()
+    }
+
+    /// Validates, persists and then swaps in a new config document. A blank or redacted API key
+    /// keeps the stored one.
+    pub fn update_config(&self, value: serde_json::Value) -> Result<RelayConfig, config::ConfigError> {
+        let mut updated = config::parse_config(value).map_err(config::ConfigError::Invalid)?;
+        let api_key = &updated.ai_provider.api_key;
+        if api_key.trim().is_empty() || api_key == config::REDACTED_API_KEY {
+            updated.ai_provider.api_key = self.get_config().ai_provider.api_key;
+        }
+        config::save_config(&updated)?;
+        self.state.write().unwrap().config = updated.clone();
+        Ok(updated)
     }
 
     pub fn update_transaction_status(&self, id: &str, status: TransactionStatus) -> Option<Transaction> {
         let mut state = self.state.write().unwrap();
         if let Some(tx) = state.transactions.iter_mut().find(|t| t.id == id) {
-            tx.status = status.clone();
+            timestamps::set_status(tx, status);
             let tx_clone = tx.clone();
             drop(state);
             let _ = self.tx_sender.send(tx_clone.clone());
@@ -98,25 +191,36 @@ impl Store {
         None
     }
 
-    pub fn update_transaction_status_bulk(&self, ids: &[String], status: TransactionStatus) -> Vec<String> {
+    /// Sets `status` on every listed transaction that [`Store::allow_manual_status`] lets through.
+    /// Returns the updated ids and the ids refused because a validation run blocks them.
+    pub fn update_transaction_status_bulk(
+        &self,
+        ids: &[String],
+        status: TransactionStatus,
+        override_validation: bool,
+    ) -> (Vec<String>, Vec<String>) {
+        let (allowed, rejected): (Vec<&String>, Vec<&String>) =
+            ids.iter().partition(|id| self.allow_manual_status(id, &status, override_validation));
         let mut state = self.state.write().unwrap();
         let mut updated_ids = Vec::new();
-        
+
         for tx in state.transactions.iter_mut() {
-            if ids.contains(&tx.id) {
-                tx.status = status.clone();
+            if allowed.contains(&&tx.id) {
+                timestamps::set_status(tx, status.clone());
                 updated_ids.push(tx.id.clone());
                 let tx_clone = tx.clone();
                 let _ = self.tx_sender.send(tx_clone);
             }
         }
-        
-        updated_ids
+
+        (updated_ids, rejected.into_iter().cloned().collect())
     }
 
     pub fn get_transaction(&self, id: &str) -> Option<Transaction> {
         let state = self.state.read().unwrap();
-        state.transactions.iter().find(|t| t.id == id).cloned()
+        let mut tx = state.transactions.iter().find(|t| t.id == id).cloned()?;
+        timestamps::refresh(&mut tx);
+        Some(tx)
     }
 
     pub fn subscribe(&self) -> broadcast::Receiver<Transaction> {
@@ -127,25 +231,247 @@ impl Store {
         self.file_sender.subscribe()
     }
 
+    pub fn subscribe_to_command_output(&self) -> broadcast::Receiver<CommandOutputEvent> {
+        self.output_sender.subscribe()
+    }
+
+    /// Whether `status` may be set by hand. `APPLIED` and `COMMITTED` are refused while a
+    /// validation run blocks the transaction (see [`approval::validation_blocks`]) and was not
+    /// overridden before, unless `override_validation` is set, in which case the override is recorded.
+    pub fn allow_manual_status(&self, id: &str, status: &TransactionStatus, override_validation: bool) -> bool {
+        if !matches!(status, TransactionStatus::Applied | TransactionStatus::Committed) {
+            return true;
+        }
+        let blocked = {
+            let state = self.state.read().unwrap();
+            state
+                .transactions
+                .iter()
+                .find(|t| t.id == id)
+                .is_some_and(|tx| approval::validation_blocks(tx) && !tx.validation_overridden)
+        };
+        if blocked && override_validation {
+            self.override_validation(id);
+        }
+        !blocked || override_validation
+    }
+
+    pub fn override_validation(&self, id: &str) {
+        let mut state = self.state.write().unwrap();
+        if let Some(tx) = state.transactions.iter_mut().find(|t| t.id == id) {
+            tx.validation_overridden = true;
+            tx.approval = Some(approval::record(ApprovalMethod::Manual, ApprovalRule::ValidationOverride, None));
+            timestamps::touch(tx);
+        }
+    }
+
+    pub fn get_diagnostics(&self, id: &str) -> Option<relaycode_schema::DiagnosticsReport> {
+        let tx = self.get_transaction(id)?;
+        Some(relaycode_schema::DiagnosticsReport {
+            transaction_id: tx.id.clone(),
+            summary: tx.lint_summary.clone(),
+            diagnostics: diagnostics::collect(&tx),
+        })
+    }
+
+    pub fn get_watcher_status(&self) -> WatcherStatus {
+        let state = self.state.read().unwrap();
+        state.watcher.clone()
+    }
+
+    pub fn subscribe_to_watcher(&self) -> broadcast::Receiver<WatcherStatus> {
+        self.watcher_sender.subscribe()
+    }
+
+    pub fn subscribe_to_repairs(&self) -> broadcast::Receiver<RepairEvent> {
+        self.repair_sender.subscribe()
+    }
+
+    /// Applies `change`, re-derives the watcher state and announces the status if anything but
+    /// `errorCount` changed.
+    pub fn update_watcher(&self, change: impl FnOnce(&mut WatcherStatus)) -> WatcherStatus {
+        let mut state = self.state.write().unwrap();
+        let before = state.watcher.clone();
+        change(&mut state.watcher);
+        state.watcher.state = watcher::derive_state(&state.watcher);
+        let after = state.watcher.clone();
+        drop(state);
+        // `errorCount` alone ticks on every failed poll; it goes out with the next real change.
+        let changed = WatcherStatus { error_count: before.error_count, ..after.clone() } != before;
+        if changed {
+            let _ = self.watcher_sender.send(after.clone());
+        }
+        after
+    }
+
+    /// Picks up the paused flag persisted by a previous run.
+    pub fn restore_watcher_state(&self) {
+        let paused = watcher::load_paused();
+        self.update_watcher(|status| status.paused = paused);
+    }
+
+    pub fn set_watcher_paused(&self, paused: bool) -> std::io::Result<WatcherStatus> {
+        watcher::save_paused(paused)?;
+        Ok(self.update_watcher(|status| status.paused = paused))
+    }
+
+    pub fn get_all_transactions(&self) -> Vec<Transaction> {
+        let state = self.state.read().unwrap();
+        let mut transactions = state.transactions.clone();
+        transactions.iter_mut().for_each(timestamps::refresh);
+        transactions
+    }
+
+    pub fn get_hunks(&self, id: &str) -> Option<Vec<relaycode_schema::FileHunks>> {
+        let state = self.state.read().unwrap();
+        state.transactions.iter().find(|t| t.id == id).map(review::list_hunks)
+    }
+
+    /// Records hunk review decisions. `None` when the transaction does not exist.
+    pub fn review_hunks(
+        &self,
+        id: &str,
+        updates: &[relaycode_schema::HunkDecisionUpdate],
+    ) -> Option<Result<Vec<relaycode_schema::FileHunks>, Vec<String>>> {
+        let mut state = self.state.write().unwrap();
+        let tx = state.transactions.iter_mut().find(|t| t.id == id)?;
+        if let Err(errors) = review::apply_hunk_decisions(tx, updates) {
+            return Some(Err(errors));
+        }
+        let hunks = review::list_hunks(tx);
+        timestamps::touch(tx);
+        let _ = self.tx_sender.send(tx.clone());
+        Some(Ok(hunks))
+    }
+
+    /// Swaps a failed file's diff for a hand-edited one, keeping the old diff as a revision. Hunk
+    /// decisions refer to the old hunks, so they are dropped. `None` when the transaction does not exist.
+    pub fn replace_file_diff(
+        &self,
+        id: &str,
+        request: &relaycode_schema::UpdateFileDiffRequest,
+    ) -> Option<Result<Transaction, DiffEditError>> {
+        self.swap_file_diff(id, request, FileApplyStatus::Failed)
+    }
+
+    /// [`Store::replace_file_diff`] for a file an auto-repair holds in `APPLYING`.
+    pub(crate) fn replace_repairing_diff(
+        &self,
+        id: &str,
+        request: &relaycode_schema::UpdateFileDiffRequest,
+    ) -> Option<Result<Transaction, DiffEditError>> {
+        self.swap_file_diff(id, request, FileApplyStatus::Applying)
+    }
+
+    fn swap_file_diff(
+        &self,
+        id: &str,
+        request: &relaycode_schema::UpdateFileDiffRequest,
+        expected: FileApplyStatus,
+    ) -> Option<Result<Transaction, DiffEditError>> {
+        let mut state = self.state.write().unwrap();
+        let tx = state.transactions.iter_mut().find(|t| t.id == id)?;
+        let Some(file) = tx.files.iter().find(|f| f.path == request.file_path) else {
+            return Some(Err(DiffEditError::Invalid(vec![format!("{}: not part of this transaction", request.file_path)])));
+        };
+        if file.apply_status != expected {
+            let message = match expected {
+                FileApplyStatus::Failed => format!("{}: only failed files can be edited", request.file_path),
+                _ => format!("{}: is no longer being repaired", request.file_path),
+            };
+            return Some(Err(DiffEditError::NotFailed(message)));
+        }
+        if let Err(errors) = diff::validate(&request.diff) {
+            return Some(Err(DiffEditError::Invalid(errors)));
+        }
+
+        let replaced_at = chrono::Utc::now();
+        for file in tx.files.iter_mut().filter(|f| f.path == request.file_path) {
+            let previous = std::mem::replace(&mut file.diff, request.diff.clone());
+            file.revisions.push(relaycode_schema::DiffRevision {
+                diff: previous,
+                replaced_at,
+                replaced_by: request.author.clone(),
+            });
+            file.hunk_decisions.clear();
+            file.skipped_hunks.clear();
+            file.conflicts.clear();
+        }
+        timestamps::touch(tx);
+        let _ = self.tx_sender.send(tx.clone());
+        Some(Ok(tx.clone()))
+    }
+
+    /// Records a file-level review decision. `None` when the transaction does not exist.
+    pub fn review_files(
+        &self,
+        id: &str,
+        request: &relaycode_schema::ReviewFilesRequest,
+    ) -> Option<Result<Transaction, Vec<String>>> {
+        let mut state = self.state.write().unwrap();
+        let tx = state.transactions.iter_mut().find(|t| t.id == id)?;
+        if let Err(errors) = review::apply_file_decisions(tx, request) {
+            return Some(Err(errors));
+        }
+        timestamps::touch(tx);
+        let _ = self.tx_sender.send(tx.clone());
+        Some(Ok(tx.clone()))
+    }
+
+    /// Stores `message` as the commit message of every listed transaction. Nothing changes unless all exist.
+    pub fn set_commit_message(&self, ids: &[String], message: &str) -> Result<Vec<Transaction>, Vec<String>> {
+        let mut state = self.state.write().unwrap();
+        let missing: Vec<String> = ids
+            .iter()
+            .filter(|id| !state.transactions.iter().any(|t| &t.id == *id))
+            .map(|id| format!("No transaction {}", id))
+            .collect();
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+        let mut updated = Vec::new();
+        for tx in state.transactions.iter_mut().filter(|t| ids.contains(&t.id)) {
+            tx.commit_message = Some(message.to_string());
+            timestamps::touch(tx);
+            let _ = self.tx_sender.send(tx.clone());
+            updated.push(tx.clone());
+        }
+        Ok(updated)
+    }
+
+    pub fn get_batches(&self) -> Vec<Batch> {
+        let state = self.state.read().unwrap();
+        state.batches.clone()
+    }
+
+    pub fn get_batch(&self, id: &str) -> Option<Batch> {
+        let state = self.state.read().unwrap();
+        state.batches.iter().find(|b| b.id == id).cloned()
+    }
+
+    pub fn insert_batch(&self, batch: Batch) {
+        let mut state = self.state.write().unwrap();
+        state.batches.insert(0, batch);
+    }
+
+    /// Adds a new `PENDING` transaction to the top of the list and announces it.
+    pub fn insert_transaction(&self, tx: Transaction) {
+        let mut state = self.state.write().unwrap();
+        state.transactions.insert(0, tx.clone());
+        drop(state);
+        let _ = self.tx_sender.send(tx);
+    }
+
     pub fn update_file_apply_status(&self, tx_id: &str, file_path: &str, status: FileApplyStatus, error: Option<String>) -> Option<Transaction> {
         let mut state = self.state.write().unwrap();
         if let Some(tx) = state.transactions.iter_mut().find(|t| t.id == tx_id) {
-            for block in &mut tx.blocks {
-                if block.block_type == "file" {
-                    if let Some(ref mut file) = block.file {
-                        if file.path == file_path {
-                            file.apply_status = status.clone();
-                            file.error_message = error.clone();
-                        }
-                    }
-                }
-            }
             for file in &mut tx.files {
                 if file.path == file_path {
                     file.apply_status = status.clone();
                     file.error_message = error.clone();
                 }
             }
+            timestamps::touch(tx);
             let tx_clone = tx.clone();
             drop(state);
             let _ = self.tx_sender.send(tx_clone.clone());
@@ -158,15 +484,6 @@ impl Store {
         let state = self.state.read().unwrap();
         let mut failed_paths = Vec::new();
         if let Some(tx) = state.transactions.iter().find(|t| t.id == tx_id) {
-            for block in &tx.blocks {
-                if block.block_type == "file" {
-                    if let Some(ref file) = block.file {
-                        if file.apply_status == FileApplyStatus::Failed {
-                            failed_paths.push(file.path.clone());
-                        }
-                    }
-                }
-            }
             for file in &tx.files {
                 if file.apply_status == FileApplyStatus::Failed {
                     failed_paths.push(file.path.clone());
@@ -181,11 +498,155 @@ use std::sync::LazyLock;
 
 pub static STORE: LazyLock<Store> = LazyLock::new(|| {
     let store = Store::new();
-    store.load_data();
+    if let Err(err) = store.load_data() {
+        tracing::warn!("Mock data not loaded: {}", err);
+    }
     store
 });
 
-pub fn start_simulation(id: String, scenario: Option<SimulationScenario>) {
+/// Paths touched by a transaction, in file order.
+pub fn file_paths(tx: &Transaction) -> Vec<String> {
+    tx.files.iter().map(|file| file.path.clone()).collect()
+}
+
+fn generate_transaction_id() -> String {
+    use rand::Rng;
+    format!("tx-{:08x}", rand::thread_rng().gen::<u32>())
+}
+
+pub fn build_transaction(mut new_tx: NewTransaction) -> Transaction {
+    migrate::normalize(&mut new_tx.blocks, &mut new_tx.files);
+    let tokens = new_tx.tokens.unwrap_or_default();
+    let cost = new_tx
+        .cost
+        .or_else(|| pricing::estimate(&new_tx.provider, &new_tx.model, &tokens))
+        .unwrap_or_default();
+    let now = chrono::Utc::now();
+    let created_at = new_tx.created_at.unwrap_or(now);
+    Transaction {
+        id: generate_transaction_id(),
+        status: TransactionStatus::Pending,
+        description: new_tx.description,
+        timestamp: timestamps::relative(created_at, now),
+        created_at,
+        updated_at: now,
+        applied_at: None,
+        committed_at: None,
+        status_history: vec![StatusTransition { status: TransactionStatus::Pending, at: created_at }],
+        prompt_id: new_tx.prompt_id,
+        parent_id: new_tx.parent_id,
+        is_chain_root: None,
+        author: new_tx.author,
+        blocks: new_tx.blocks,
+        files: new_tx.files,
+        provider: new_tx.provider,
+        model: new_tx.model,
+        cost,
+        tokens,
+        reasoning: new_tx.reasoning,
+        validations: Vec::new(),
+        validation_overridden: false,
+        approval: None,
+        lint_summary: None,
+        batch_id: None,
+        conflicts_with: Vec::new(),
+        commit_message: None,
+    }
+}
+
+/// Stores a new transaction as `PENDING` and, when `patch.approvalMode` allows it, starts applying it.
+pub fn ingest_transaction(new_tx: NewTransaction) -> Transaction {
+    let tx = build_transaction(new_tx);
+    store_and_auto_apply(tx)
+}
+
+fn store_and_auto_apply(tx: Transaction) -> Transaction {
+    STORE.insert_transaction(tx.clone());
+
+    let config = STORE.get_config();
+    if let Ok(approval) = approval::check_auto_approval(&tx, &config.patch) {
+        start_simulation(tx.id.clone(), ApplyOptions {
+            scenario: None,
+            override_validation: false,
+            approval,
+        });
+    }
+
+    STORE.get_transaction(&tx.id).unwrap_or(tx)
+}
+
+/// Ingests a patch series as a linked chain: the first is the chain root and each later one
+/// names its predecessor as `parentId`. Under `patch.approvalMode: auto` the leading members that
+/// qualify are applied one after another, each once the previous one is `APPLIED`.
+pub fn ingest_chain(items: Vec<NewTransaction>) -> Vec<Transaction> {
+    let mut previous: Option<String> = None;
+    let transactions: Vec<Transaction> = items
+        .into_iter()
+        .map(|item| {
+            let mut tx = build_transaction(item);
+            match previous.replace(tx.id.clone()) {
+                None => tx.is_chain_root = Some(true),
+                Some(parent) => tx.parent_id = Some(parent),
+            }
+            STORE.insert_transaction(tx.clone());
+            tx
+        })
+        .collect();
+
+    let config = STORE.get_config();
+    let queue: Vec<(String, ApplyOptions)> = transactions
+        .iter()
+        .map_while(|tx| {
+            let approval = approval::check_auto_approval(tx, &config.patch).ok()?;
+            Some((tx.id.clone(), ApplyOptions { scenario: None, override_validation: false, approval }))
+        })
+        .collect();
+    if !queue.is_empty() {
+        chain::apply_in_order(queue);
+    }
+    transactions
+}
+
+/// Parses pasted patch text into pending transactions: a `git format-patch` series becomes one
+/// per message, anything else a single transaction.
+pub fn parse_patch_text(text: &str, source: &str) -> Vec<NewTransaction> {
+    if mbox::is_mbox(text) {
+        mbox::to_new_transactions(text, source)
+    } else {
+        patch::to_new_transaction(text, source).into_iter().collect()
+    }
+}
+
+/// Ingests pasted patch text, chaining a multi-message series.
+pub fn ingest_text(text: &str, source: &str) -> Vec<Transaction> {
+    let mut items = parse_patch_text(text, source);
+    match items.len() {
+        0 => Vec::new(),
+        1 => vec![ingest_transaction(items.remove(0))],
+        _ => ingest_chain(items),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApplyOptions {
+    pub scenario: Option<SimulationScenario>,
+    pub override_validation: bool,
+    pub approval: ApprovalRecord,
+}
+
+impl ApplyOptions {
+    pub fn manual(scenario: Option<SimulationScenario>, override_validation: bool) -> Self {
+        Self {
+            scenario,
+            override_validation,
+            approval: approval::record(ApprovalMethod::Manual, ApprovalRule::Manual, None),
+        }
+    }
+}
+
+pub fn start_simulation(id: String, options: ApplyOptions) {
+    let ApplyOptions { scenario, override_validation, approval } = options;
+    let auto_approved = approval.method == ApprovalMethod::Auto;
     let tx_sender = STORE.tx_sender.clone();
     let file_sender = STORE.file_sender.clone();
     let state = STORE.state.clone();
@@ -209,7 +670,11 @@ pub fn start_simulation(id: String, scenario: Option<SimulationScenario>) {
     {
         let mut state_guard = state.write().unwrap();
         if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
-            tx.status = TransactionStatus::Applying;
+            timestamps::set_status(tx, TransactionStatus::Applying);
+            tx.validations.clear();
+            tx.validation_overridden = false;
+            tx.approval = Some(approval);
+            tx.lint_summary = None;
             let tx_clone = tx.clone();
             let _ = tx_sender.send(tx_clone);
         }
@@ -218,73 +683,99 @@ pub fn start_simulation(id: String, scenario: Option<SimulationScenario>) {
     let file_paths: Vec<String> = {
         let state_guard = state.read().unwrap();
         if let Some(tx) = state_guard.transactions.iter().find(|t| t.id == id) {
-            let mut paths = Vec::new();
-            for block in &tx.blocks {
-                if block.block_type == "file" {
-                    if let Some(ref file) = block.file {
-                        paths.push(file.path.clone());
-                    }
-                }
-            }
-            for file in &tx.files {
-                paths.push(file.path.clone());
-            }
-            paths
+            file_paths(tx)
         } else {
             Vec::new()
         }
     };
 
     tokio::spawn(async move {
+        let config = STORE.get_config();
+        let mut validation_failed = false;
+
+        if !config.patch.pre_command.trim().is_empty() {
+            let run = run_validation(&id, ValidationKind::PreCommand, &config.patch.pre_command, &config).await;
+            validation_failed = !run.success;
+            record_validation(&state, &id, run);
+            if validation_failed && !override_validation {
+                let mut state_guard = state.write().unwrap();
+                if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
+                    timestamps::set_status(tx, TransactionStatus::Failed);
+                    let _ = tx_sender.send(tx.clone());
+                }
+                state_guard.active_simulations.remove(&id);
+                return;
+            }
+        }
+
+        // Lint once before touching files so the review can show which errors the apply introduced.
+        let lint_baseline = if config.patch.linter.trim().is_empty() {
+            None
+        } else {
+            Some(lint_baseline(&config).await)
+        };
+
         let duration = match scenario.as_ref() {
             Some(SimulationScenario::FastSuccess) => 500.0 + rand_float() * 500.0,
             Some(SimulationScenario::LongRunning) => 8000.0 + rand_float() * 4000.0,
             _ => 2000.0 + rand_float() * 4000.0,
         };
 
+        // Scenarios are demo runs; only a plain apply touches the disk.
+        let real_apply = config.patch.apply_to_disk && scenario.is_none();
+        // An auto approval is only final once the linter has run, so keep what it overwrites.
+        let mut snapshots: Vec<(String, apply::Snapshot)> = Vec::new();
+        let mut merge_conflict = false;
         let file_count = file_paths.len();
-        let file_delay = if file_count > 0 { duration / (file_count as f64 * 2.0) } else { 0.0 };
+        let file_delay = if file_count > 0 && !real_apply { duration / (file_count as f64 * 2.0) } else { 0.0 };
         
         for (idx, file_path) in file_paths.iter().enumerate() {
             tokio::time::sleep(tokio::time::Duration::from_millis(file_delay as u64)).await;
             
-            let file_status = match scenario.as_ref() {
-                Some(SimulationScenario::SimulatedFailure) => FileApplyStatus::Failed,
-                Some(SimulationScenario::PartialFailure) => {
-                    if idx % 3 == 2 {
-                        FileApplyStatus::Failed
-                    } else {
-                        FileApplyStatus::Applied
-                    }
-                }
-                _ => FileApplyStatus::Applied,
-            };
+            let reviewed = reviewed_file(&id, file_path);
+            let skipped_hunks = reviewed.skipped_hunks.clone();
 
-            let error_msg = if file_status == FileApplyStatus::Failed {
-                Some("Patch conflict: file content mismatch".to_string())
+            let (file_status, error_msg, conflicts) = if reviewed.untouched {
+                (FileApplyStatus::Skipped, None, Vec::new())
+            } else if real_apply {
+                if let Some(file) = reviewed.file.as_ref().filter(|_| auto_approved) {
+                    snapshots.push((file_path.clone(), apply::snapshot(&config::project_root(), file)));
+                }
+                apply_file_to_disk(reviewed.file).await
             } else {
-                None
+                let file_status = match scenario.as_ref() {
+                    Some(SimulationScenario::SimulatedFailure) => FileApplyStatus::Failed,
+                    Some(SimulationScenario::PartialFailure) => {
+                        if idx % 3 == 2 {
+                            FileApplyStatus::Failed
+                        } else {
+                            FileApplyStatus::Applied
+                        }
+                    }
+                    _ => FileApplyStatus::Applied,
+                };
+
+                let error_msg = if file_status == FileApplyStatus::Failed {
+                    Some("Patch conflict: file content mismatch".to_string())
+                } else {
+                    None
+                };
+                (file_status, error_msg, Vec::new())
             };
+            merge_conflict |= !conflicts.is_empty();
 
             {
                 let mut state_guard = state.write().unwrap();
                 if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
-                    for block in &mut tx.blocks {
-                        if block.block_type == "file" {
-                            if let Some(ref mut file) = block.file {
-                                if &file.path == file_path {
-                                    file.apply_status = file_status.clone();
-                                    file.error_message = error_msg.clone();
-                                }
-                            }
-                        }
-                    }
                     for file in &mut tx.files {
                         if &file.path == file_path {
                             file.apply_status = file_status.clone();
                             file.error_message = error_msg.clone();
+                            file.conflicts = conflicts.clone();
+                            file.skipped_hunks = skipped_hunks.clone();
                         }
                     }
+                    timestamps::touch(tx);
                 }
             }
 
@@ -299,46 +790,68 @@ pub fn start_simulation(id: String, scenario: Option<SimulationScenario>) {
         tokio::time::sleep(tokio::time::Duration::from_millis(file_delay as u64)).await;
 
         let final_status = match scenario.as_ref() {
+            // Files that truly collided hold conflict markers now; the whole transaction needs attention.
+            _ if merge_conflict => TransactionStatus::Failed,
             Some(SimulationScenario::SimulatedFailure) => TransactionStatus::Failed,
-            Some(SimulationScenario::PartialFailure) => {
-                let applied_count = {
-                    let state_guard = state.read().unwrap();
-                    if let Some(tx) = state_guard.transactions.iter().find(|t| t.id == id) {
-                        tx.blocks.iter().filter(|b| {
-                            b.block_type == "file" && 
-                            b.file.as_ref().map_or(false, |f| f.apply_status == FileApplyStatus::Applied)
-                        }).count() + tx.files.iter().filter(|f| f.apply_status == FileApplyStatus::Applied).count()
-                    } else {
-                        0
-                    }
-                };
-                let failed_count = {
-                    let state_guard = state.read().unwrap();
-                    if let Some(tx) = state_guard.transactions.iter().find(|t| t.id == id) {
-                        tx.blocks.iter().filter(|b| {
-                            b.block_type == "file" && 
-                            b.file.as_ref().map_or(false, |f| f.apply_status == FileApplyStatus::Failed)
-                        }).count() + tx.files.iter().filter(|f| f.apply_status == FileApplyStatus::Failed).count()
-                    } else {
-                        0
-                    }
-                };
-                
-                if failed_count == 0 {
-                    TransactionStatus::Applied
-                } else if applied_count == 0 {
-                    TransactionStatus::Failed
+            _ if real_apply || matches!(scenario, Some(SimulationScenario::PartialFailure)) => {
+                let state_guard = state.read().unwrap();
+                state_guard
+                    .transactions
+                    .iter()
+                    .find(|t| t.id == id)
+                    .map(status_from_files)
+                    .unwrap_or(TransactionStatus::Failed)
+            }
+            _ => TransactionStatus::Applied,
+        };
+
+        let mut linter_rejected = false;
+        if final_status != TransactionStatus::Failed {
+            let post_steps = [
+                (ValidationKind::Linter, &config.patch.linter),
+                (ValidationKind::PostCommand, &config.patch.post_command),
+            ];
+            for (kind, command) in post_steps {
+                if command.trim().is_empty() {
+                    continue;
+                }
+                let run = run_validation(&id, kind.clone(), command, &config).await;
+                // Auto approvals tolerate up to `approvalOnErrorCount` linter errors.
+                let blocking = if auto_approved && kind == ValidationKind::Linter {
+                    approval::linter_error_count(&run) > config.patch.approval_on_error_count as usize
                 } else {
-                    TransactionStatus::PartiallyApplied
+                    !run.success
+                };
+                validation_failed |= blocking;
+                linter_rejected |= blocking && auto_approved && kind == ValidationKind::Linter;
+                if kind == ValidationKind::Linter {
+                    record_diagnostics(&state, &id, &run, lint_baseline.as_deref().unwrap_or_default());
                 }
+                record_validation(&state, &id, run);
             }
-            _ => TransactionStatus::Applied,
+        }
+
+        let final_status = if validation_failed && !override_validation {
+            TransactionStatus::Failed
+        } else {
+            final_status
         };
 
+        if linter_rejected {
+            roll_back(&id, snapshots);
+        }
+
         {
             let mut state_guard = state.write().unwrap();
             if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
-                tx.status = final_status.clone();
+                tx.validation_overridden = validation_failed && override_validation;
+                if auto_approved {
+                    tx.approval = tx
+                        .approval
+                        .take()
+                        .map(|approval| approval::confirm_with_linter(approval, &tx.validations, &config.patch));
+                }
+                timestamps::set_status(tx, final_status.clone());
                 let tx_clone = tx.clone();
                 let _ = tx_sender.send(tx_clone);
             }
@@ -347,79 +860,219 @@ pub fn start_simulation(id: String, scenario: Option<SimulationScenario>) {
     });
 }
 
+/// Puts back the files an auto-approved apply wrote once the linter rejected it, last file first,
+/// and marks them `PENDING` again.
+fn roll_back(id: &str, snapshots: Vec<(String, apply::Snapshot)>) {
+    for (file_path, snapshot) in snapshots.into_iter().rev() {
+        if let Err(err) = apply::restore(&snapshot) {
+            tracing::warn!("Could not roll back {} of {}: {}", file_path, id, err);
+            continue;
+        }
+        let message = "Rolled back: the linter rejected the auto approval".to_string();
+        STORE.update_file_apply_status(id, &file_path, FileApplyStatus::Pending, Some(message.clone()));
+        let _ = STORE.file_sender.send(FileStatusEvent {
+            transaction_id: id.to_string(),
+            file_path,
+            apply_status: FileApplyStatus::Pending,
+            error_message: Some(message),
+        });
+    }
+}
+
+/// A file about to be applied, narrowed down to what review lets through.
+struct ReviewedFile {
+    /// The file with only its approved hunks; `None` if it is no longer part of the transaction.
+    file: Option<TransactionFile>,
+    skipped_hunks: Vec<String>,
+    /// Review rejected the file, or every hunk of it.
+    untouched: bool,
+}
+
+fn reviewed_file(id: &str, path: &str) -> ReviewedFile {
+    let current = STORE.get_transaction(id);
+    let file = current
+        .as_ref()
+        .and_then(|tx| tx.files.iter().find(|f| f.path == path).cloned());
+    let selected = match (&current, &file) {
+        (Some(tx), Some(file)) => review::file_selected(tx, file),
+        _ => true,
+    };
+    let approved = file.as_ref().filter(|_| selected).map(review::approved_diff);
+    ReviewedFile {
+        untouched: !selected || approved.as_ref().is_some_and(|a| a.nothing_approved),
+        skipped_hunks: approved.as_ref().map(|a| a.skipped.clone()).unwrap_or_default(),
+        file: file.map(|file| TransactionFile {
+            diff: approved.map(|a| a.diff).unwrap_or_default(),
+            ..file
+        }),
+    }
+}
+
+/// Writes one file of a transaction under the project root, on the blocking pool since merges
+/// may shell out to `git`. Returns what to record on the file.
+async fn apply_file_to_disk(file: Option<TransactionFile>) -> (FileApplyStatus, Option<String>, Vec<LineRange>) {
+    let Some(file) = file else {
+        return (FileApplyStatus::Failed, Some("File is no longer part of the transaction".to_string()), Vec::new());
+    };
+    let (file, result) = tokio::task::spawn_blocking(move || {
+        let result = apply::apply_file(&config::project_root(), &file);
+        (file, result)
+    })
+    .await
+    .expect("file apply panicked");
+    match result {
+        Ok(applied) => {
+            if applied == apply::Applied::Merged {
+                tracing::info!("Merged patch into drifted file {}", file.path);
+            }
+            (FileApplyStatus::Applied, None, Vec::new())
+        }
+        Err(apply::ApplyError::Conflict(regions)) => {
+            let message = apply::ApplyError::Conflict(regions.clone()).to_string();
+            (FileApplyStatus::Failed, Some(message), regions)
+        }
+        Err(err) => (FileApplyStatus::Failed, Some(err.to_string()), Vec::new()),
+    }
+}
+
+/// Whether a transaction's files may be on disk, so reverting it has something to undo.
+pub(crate) fn is_revertible(status: &TransactionStatus) -> bool {
+    matches!(
+        status,
+        TransactionStatus::Applied | TransactionStatus::PartiallyApplied | TransactionStatus::Committed
+    )
+}
+
+/// Reverse-applies the files of `tx` that were written, last file first.
+pub(crate) async fn revert_on_disk(tx: Transaction) -> Result<(), apply::ApplyError> {
+    tokio::task::spawn_blocking(move || {
+        let root = config::project_root();
+        for file in tx.files.iter().rev().filter(|f| f.apply_status == FileApplyStatus::Applied) {
+            apply::revert_file(&root, file)?;
+        }
+        Ok(())
+    })
+    .await
+    .expect("file revert panicked")
+}
+
+/// Marks `id` as `REVERTED`. With `patch.applyToDisk` on, the files of an applied or committed
+/// transaction are restored on disk first; if one cannot be, the status is left as it was.
+pub async fn revert_transaction(id: &str) -> Option<Result<Transaction, String>> {
+    let tx = STORE.get_transaction(id)?;
+    if STORE.get_config().patch.apply_to_disk && is_revertible(&tx.status) {
+        if let Err(err) = revert_on_disk(tx).await {
+            let message = format!("Could not revert {}: {}", id, err);
+            tracing::warn!("{}", message);
+            return Some(Err(message));
+        }
+    }
+    STORE.update_transaction_status(id, TransactionStatus::Reverted).map(Ok)
+}
+
+/// Runs [`revert_transaction`] on each id. Returns the reverted ids and the ids whose files could
+/// not be restored; unknown ids are in neither.
+pub async fn revert_transactions(ids: &[String]) -> (Vec<String>, Vec<String>) {
+    let (mut reverted, mut failed) = (Vec::new(), Vec::new());
+    for id in ids {
+        match revert_transaction(id).await {
+            Some(Ok(_)) => reverted.push(id.clone()),
+            Some(Err(_)) => failed.push(id.clone()),
+            None => {}
+        }
+    }
+    (reverted, failed)
+}
+
 pub fn reapply_file(id: String, file_path: String) {
-    let file_sender = STORE.file_sender.clone();
-    let state = STORE.state.clone();
+    set_file_status(&id, &file_path, FileApplyStatus::Applying, None);
+    tokio::spawn(async move {
+        apply_file_now(&id, &file_path).await;
+    });
+}
 
+/// Sets a file's apply status, announces it and settles the transaction's status.
+pub(crate) fn set_file_status(id: &str, file_path: &str, status: FileApplyStatus, error: Option<String>) {
     {
-        let mut state_guard = state.write().unwrap();
+        let mut state_guard = STORE.state.write().unwrap();
         if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
-            for block in &mut tx.blocks {
-                if block.block_type == "file" {
-                    if let Some(ref mut file) = block.file {
-                        if file.path == file_path {
-                            file.apply_status = FileApplyStatus::Applying;
-                            file.error_message = None;
-                        }
-                    }
-                }
-            }
-            for file in &mut tx.files {
-                if file.path == file_path {
-                    file.apply_status = FileApplyStatus::Applying;
-                    file.error_message = None;
-                }
+            for file in tx.files.iter_mut().filter(|f| f.path == file_path) {
+                file.apply_status = status.clone();
+                file.error_message = error.clone();
             }
+            timestamps::touch(tx);
         }
     }
 
-    let _ = file_sender.send(FileStatusEvent {
-        transaction_id: id.clone(),
-        file_path: file_path.clone(),
-        apply_status: FileApplyStatus::Applying,
-        error_message: None,
+    let _ = STORE.file_sender.send(FileStatusEvent {
+        transaction_id: id.to_string(),
+        file_path: file_path.to_string(),
+        apply_status: status,
+        error_message: error,
     });
+    settle_status(id);
+}
 
-    tokio::spawn(async move {
-        tokio::time::sleep(tokio::time::Duration::from_millis(800 + (rand_float() as u64 % 400))).await;
-        
-        let success = rand_float() > 300.0;
-        
-        let (status, error) = if success {
-            (FileApplyStatus::Applied, None)
-        } else {
-            (FileApplyStatus::Failed, Some("Retry failed: unresolved conflict".to_string()))
-        };
+/// Re-derives the status of a finished transaction from its files once none is still applying,
+/// so reapplying or repairing a file can move it between `APPLIED`, `PARTIALLYAPPLIED` and `FAILED`.
+fn settle_status(id: &str) {
+    let mut state_guard = STORE.state.write().unwrap();
+    let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) else {
+        return;
+    };
+    let finished = matches!(
+        tx.status,
+        TransactionStatus::Applied | TransactionStatus::PartiallyApplied | TransactionStatus::Failed
+    );
+    if !finished || tx.files.iter().any(|f| f.apply_status == FileApplyStatus::Applying) {
+        return;
+    }
+    let blocked = approval::validation_blocks(tx) && !tx.validation_overridden;
+    let status = if blocked { TransactionStatus::Failed } else { status_from_files(tx) };
+    if status != tx.status {
+        timestamps::set_status(tx, status);
+        let _ = STORE.tx_sender.send(tx.clone());
+    }
+}
 
-        {
-            let mut state_guard = state.write().unwrap();
-            if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
-                for block in &mut tx.blocks {
-                    if block.block_type == "file" {
-                        if let Some(ref mut file) = block.file {
-                            if file.path == file_path {
-                                file.apply_status = status.clone();
-                                file.error_message = error.clone();
-                            }
-                        }
-                    }
-                }
-                for file in &mut tx.files {
-                    if file.path == file_path {
-                        file.apply_status = status.clone();
-                        file.error_message = error.clone();
-                    }
-                }
+/// `APPLIED` when no file failed, `FAILED` when none applied, `PARTIALLYAPPLIED` otherwise.
+fn status_from_files(tx: &Transaction) -> TransactionStatus {
+    let count = |status: FileApplyStatus| tx.files.iter().filter(|f| f.apply_status == status).count();
+    let (applied, failed) = (count(FileApplyStatus::Applied), count(FileApplyStatus::Failed));
+    if failed == 0 {
+        TransactionStatus::Applied
+    } else if applied == 0 {
+        TransactionStatus::Failed
+    } else {
+        TransactionStatus::PartiallyApplied
+    }
+}
+
+/// Applies one file, honouring review decisions, and records the outcome. Like
+/// [`start_simulation`], only writes to disk when `patch.applyToDisk` is on; otherwise the file
+/// is simulated as applied.
+pub(crate) async fn apply_file_now(id: &str, file_path: &str) -> (FileApplyStatus, Option<String>) {
+    let reviewed = reviewed_file(id, file_path);
+    let skipped_hunks = reviewed.skipped_hunks.clone();
+    let (status, error, conflicts) = if reviewed.untouched {
+        (FileApplyStatus::Skipped, None, Vec::new())
+    } else if STORE.get_config().patch.apply_to_disk {
+        apply_file_to_disk(reviewed.file).await
+    } else {
+        (FileApplyStatus::Applied, None, Vec::new())
+    };
+
+    {
+        let mut state_guard = STORE.state.write().unwrap();
+        if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
+            for file in tx.files.iter_mut().filter(|f| f.path == file_path) {
+                file.conflicts = conflicts.clone();
+                file.skipped_hunks = skipped_hunks.clone();
             }
         }
-
-        let _ = file_sender.send(FileStatusEvent {
-            transaction_id: id,
-            file_path,
-            apply_status: status,
-            error_message: error,
-        });
-    });
+    }
+    set_file_status(id, file_path, status.clone(), error.clone());
+    (status, error)
 }
 
 pub fn reapply_all_failed(id: String) {
@@ -429,8 +1082,193 @@ pub fn reapply_all_failed(id: String) {
     }
 }
 
+async fn run_validation(id: &str, kind: ValidationKind, command: &str, config: &RelayConfig) -> ValidationRun {
+    let output_sender = STORE.output_sender.clone();
+    let transaction_id = id.to_string();
+    let event_kind = kind.clone();
+    let sink: runner::OutputSink = Arc::new(move |stream, line| {
+        let _ = output_sender.send(CommandOutputEvent {
+            transaction_id: transaction_id.clone(),
+            kind: event_kind.clone(),
+            stream,
+            line,
+        });
+    });
+    runner::run_command(
+        kind,
+        command,
+        &config::project_root(),
+        std::time::Duration::from_millis(config.patch.command_timeout),
+        sink,
+    )
+    .await
+}
+
+async fn lint_baseline(config: &RelayConfig) -> Vec<relaycode_schema::Diagnostic> {
+    let root = config::project_root();
+    let run = runner::run_command(
+        ValidationKind::Linter,
+        &config.patch.linter,
+        &root,
+        std::time::Duration::from_millis(config.patch.command_timeout),
+        Arc::new(|_, _| {}),
+    )
+    .await;
+    diagnostics::parse_output(&format!("{}{}", run.stdout, run.stderr), &root)
+}
+
+fn record_diagnostics(state: &RwLock<AppState>, id: &str, run: &ValidationRun, baseline: &[relaycode_schema::Diagnostic]) {
+    let found = diagnostics::parse_output(&format!("{}{}", run.stdout, run.stderr), &config::project_root());
+    let mut state_guard = state.write().unwrap();
+    if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
+        diagnostics::attach(tx, &found);
+        tx.lint_summary = Some(diagnostics::summarize(baseline, &found));
+        timestamps::touch(tx);
+    }
+}
+
+fn record_validation(state: &RwLock<AppState>, id: &str, run: ValidationRun) {
+    let mut state_guard = state.write().unwrap();
+    if let Some(tx) = state_guard.transactions.iter_mut().find(|t| t.id == id) {
+        tx.validations.push(run);
+        timestamps::touch(tx);
+    }
+}
+
 fn rand_float() -> f64 {
     use rand::Rng;
     let mut rng = rand::thread_rng();
     rng.gen::<f64>() * 1000.0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed_run(kind: ValidationKind) -> ValidationRun {
+        ValidationRun {
+            kind,
+            command: "false".to_string(),
+            started_at: chrono::Utc::now(),
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: String::new(),
+            duration_ms: 1,
+            timed_out: false,
+            success: false,
+        }
+    }
+
+    #[test]
+    fn bulk_status_changes_honour_failed_validation() {
+        let store = Store::new();
+        let clean = build_transaction(NewTransaction::default());
+        let mut failed = build_transaction(NewTransaction::default());
+        failed.validations.push(failed_run(ValidationKind::PostCommand));
+        store.insert_transaction(clean.clone());
+        store.insert_transaction(failed.clone());
+        let ids = [clean.id.clone(), failed.id.clone()];
+
+        let (updated, rejected) = store.update_transaction_status_bulk(&ids, TransactionStatus::Committed, false);
+        assert_eq!(updated, vec![clean.id.clone()]);
+        assert_eq!(rejected, vec![failed.id.clone()]);
+
+        let (updated, rejected) = store.update_transaction_status_bulk(&ids[1..], TransactionStatus::Reverted, false);
+        assert_eq!((updated, rejected.len()), (vec![failed.id.clone()], 0), "only APPLIED and COMMITTED are gated");
+
+        let (updated, rejected) = store.update_transaction_status_bulk(&ids[1..], TransactionStatus::Applied, true);
+        assert_eq!((updated, rejected.len()), (vec![failed.id.clone()], 0));
+        let overridden = store.get_transaction(&failed.id).unwrap();
+        assert!(overridden.validation_overridden);
+        assert_eq!(overridden.approval.unwrap().rule, ApprovalRule::ValidationOverride);
+        assert!(store.allow_manual_status(&failed.id, &TransactionStatus::Committed, false));
+    }
+
+    fn auto_lint(approval_on_error_count: u32) -> relaycode_schema::PatchConfig {
+        relaycode_schema::PatchConfig {
+            approval_mode: relaycode_schema::ApprovalMode::Auto,
+            approval_on_error_count,
+            linter: "echo 'lint.txt:1:1: error: unused value'; exit 1".to_string(),
+            apply_to_disk: true,
+            ..relaycode_schema::PatchConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_approval_within_the_error_budget_keeps_the_files() {
+        let _store = testing::lock().await;
+        let root = testing::configure(auto_lint(1));
+        std::fs::write(root.join("lint.txt"), "a\nb\n").unwrap();
+
+        let tx = ingest_transaction(testing::new_transaction(&[("lint.txt", "modified", "@@ -1,2 +1,2 @@\n a\n-b\n+c")]));
+        assert_eq!(testing::settled(&tx.id).await, TransactionStatus::Applied);
+        assert_eq!(std::fs::read_to_string(root.join("lint.txt")).unwrap(), "a\nc\n");
+        let approval = STORE.get_transaction(&tx.id).unwrap().approval.unwrap();
+        assert_eq!(approval.rule, ApprovalRule::LinterErrorThreshold);
+        assert!(approval.rejection.is_none());
+    }
+
+    #[tokio::test]
+    async fn auto_approval_rejected_by_the_linter_restores_the_files() {
+        let _store = testing::lock().await;
+        let root = testing::configure(auto_lint(0));
+        std::fs::write(root.join("lint.txt"), "a\nb\n").unwrap();
+        let _ = std::fs::remove_file(root.join("lint-new.txt"));
+
+        let tx = ingest_transaction(testing::new_transaction(&[
+            ("lint.txt", "modified", "@@ -1,2 +1,2 @@\n a\n-b\n+c"),
+            ("lint-new.txt", "created", "@@ -0,0 +1,1 @@\n+new"),
+        ]));
+        assert_eq!(testing::settled(&tx.id).await, TransactionStatus::Failed);
+        assert_eq!(std::fs::read_to_string(root.join("lint.txt")).unwrap(), "a\nb\n");
+        assert!(!root.join("lint-new.txt").exists());
+
+        let tx = STORE.get_transaction(&tx.id).unwrap();
+        let approval = tx.approval.unwrap();
+        assert_eq!(approval.rule, ApprovalRule::AutoMode);
+        assert!(approval.rejection.is_some());
+        assert!(tx.files.iter().all(|f| f.apply_status == FileApplyStatus::Pending));
+    }
+
+    #[tokio::test]
+    async fn reverting_a_transaction_restores_its_files() {
+        let _store = testing::lock().await;
+        let root = testing::configure(relaycode_schema::PatchConfig {
+            apply_to_disk: true,
+            ..relaycode_schema::PatchConfig::default()
+        });
+        std::fs::write(root.join("revert.txt"), "a\nb\n").unwrap();
+        let _ = std::fs::remove_file(root.join("revert-new.txt"));
+        let tx = ingest_transaction(testing::new_transaction(&[
+            ("revert.txt", "modified", "@@ -1,2 +1,2 @@\n a\n-b\n+c"),
+            ("revert-new.txt", "created", "@@ -0,0 +1,1 @@\n+new"),
+        ]));
+        start_simulation(tx.id.clone(), ApplyOptions::manual(None, false));
+        assert_eq!(testing::settled(&tx.id).await, TransactionStatus::Applied);
+        assert_eq!(std::fs::read_to_string(root.join("revert.txt")).unwrap(), "a\nc\n");
+
+        let (reverted, failed) = revert_transactions(&[tx.id.clone(), "tx-missing".to_string()]).await;
+        assert_eq!((reverted, failed.len()), (vec![tx.id.clone()], 0));
+        assert_eq!(std::fs::read_to_string(root.join("revert.txt")).unwrap(), "a\nb\n");
+        assert!(!root.join("revert-new.txt").exists());
+        assert_eq!(STORE.get_transaction(&tx.id).unwrap().status, TransactionStatus::Reverted);
+    }
+
+    #[tokio::test]
+    async fn a_revert_that_cannot_restore_the_files_keeps_the_status() {
+        let _store = testing::lock().await;
+        let root = testing::configure(relaycode_schema::PatchConfig {
+            apply_to_disk: true,
+            ..relaycode_schema::PatchConfig::default()
+        });
+        std::fs::write(root.join("drifted.txt"), "a\nb\n").unwrap();
+        let tx = ingest_transaction(testing::new_transaction(&[("drifted.txt", "modified", "@@ -1,2 +1,2 @@\n a\n-b\n+c")]));
+        start_simulation(tx.id.clone(), ApplyOptions::manual(None, false));
+        assert_eq!(testing::settled(&tx.id).await, TransactionStatus::Applied);
+        std::fs::write(root.join("drifted.txt"), "something else entirely\n").unwrap();
+
+        assert!(revert_transaction(&tx.id).await.unwrap().is_err());
+        assert_eq!(STORE.get_transaction(&tx.id).unwrap().status, TransactionStatus::Applied);
+        assert_eq!(std::fs::read_to_string(root.join("drifted.txt")).unwrap(), "something else entirely\n");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{detect, migrate, patch};
+use relaycode_schema::{NewTransaction, TransactionBlock};
+
+/// One line of the diffstat between the commit message and the diff.
+#[derive(Debug, Clone)]
+pub struct DiffstatEntry {
+    pub path: String,
+    /// The `2 +-` or `Bin 0 -> 1234 bytes` part.
+    pub summary: String,
+    pub binary: bool,
+}
+
+/// A single message of `git format-patch` output.
+#[derive(Debug, Clone)]
+pub struct MailPatch {
+    /// The `From:` header, e.g. `Jane Doe <jane@example.com>`.
+    pub author: Option<String>,
+    /// The `Date:` header in UTC.
+    pub date: Option<chrono::DateTime<chrono::Utc>>,
+    /// The subject with `[PATCH v2 1/3]` style tags removed.
+    pub subject: String,
+    /// The commit message body below the subject.
+    pub body: String,
+    pub diffstat: Vec<DiffstatEntry>,
+    /// Paths whose changes were sent as `GIT binary patch` data, which we cannot apply.
+    pub binary_paths: Vec<String>,
+    /// Everything from the first `diff --git` up to the signature, minus binary payloads.
+    pub diff: String,
+}
+
+/// True when `text` starts like `git format-patch` output or an mbox of it.
+pub fn is_mbox(text: &str) -> bool {
+    let first = text.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    detect::is_mbox_separator(first) || (first.starts_with("From: ") && text.contains("\nSubject: "))
+}
+
+/// Splits mbox text into messages and parses each one. Messages without a diff are skipped.
+pub fn parse_mbox(text: &str) -> Vec<MailPatch> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut starts: Vec<usize> = (0..lines.len()).filter(|&i| detect::is_mbox_separator(lines[i])).collect();
+    if starts.is_empty() {
+        starts = lines.iter().position(|line| line.starts_with("From: ")).into_iter().collect();
+    }
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(lines.len());
+            parse_message(&lines[start..end])
+        })
+        .collect()
+}
+
+fn strip_subject_tags(subject: &str) -> String {
+    let mut subject = subject.trim();
+    while let Some(rest) = subject.strip_prefix('[') {
+        match rest.split_once(']') {
+            Some((_, after)) => subject = after.trim_start(),
+            None => break,
+        }
+    }
+    subject.to_string()
+}
+
+fn parse_message(lines: &[&str]) -> Option<MailPatch> {
+    let mut i = usize::from(lines.first().is_some_and(|line| detect::is_mbox_separator(line)));
+
+    // Headers end at the first blank line; folded headers continue on indented lines.
+    let mut headers: Vec<(String, String)> = Vec::new();
+    while i < lines.len() && !lines[i].trim().is_empty() {
+        let line = lines[i];
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+        i += 1;
+    }
+    let header = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone());
+
+    let diff_start = i + lines[i..].iter().position(|line| line.starts_with("diff --git "))?;
+    // `---` separates the message from the diffstat.
+    let separator = lines[i..diff_start].iter().position(|line| *line == "---").map(|p| p + i);
+    let body = lines[i..separator.unwrap_or(diff_start)].join("\n").trim().to_string();
+
+    let diffstat = separator
+        .map(|separator| {
+            lines[separator + 1..diff_start]
+                .iter()
+                .filter_map(|line| {
+                    let (path, summary) = line.split_once(" | ")?;
+                    let summary = summary.trim().to_string();
+                    Some(DiffstatEntry {
+                        path: path.trim().to_string(),
+                        binary: summary.starts_with("Bin"),
+                        summary,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut diff_lines = Vec::new();
+    let mut binary_paths = Vec::new();
+    let mut current_path = None;
+    let mut in_binary = false;
+    for line in &lines[diff_start..] {
+        if *line == "-- " {
+            break;
+        }
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            in_binary = false;
+            current_path = rest.split_once(" b/").map(|(_, new)| new.to_string());
+        } else if line.starts_with("GIT binary patch") || line.starts_with("Binary files ") {
+            binary_paths.extend(current_path.clone());
+            in_binary = line.starts_with("GIT binary patch");
+            continue;
+        }
+        if !in_binary {
+            diff_lines.push(*line);
+        }
+    }
+    binary_paths.dedup();
+
+    Some(MailPatch {
+        author: header("from"),
+        date: header("date")
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(&date).ok())
+            .map(|date| date.to_utc()),
+        subject: header("subject").map(|s| strip_subject_tags(&s)).unwrap_or_default(),
+        body,
+        diffstat,
+        binary_paths,
+        diff: diff_lines.join("\n"),
+    })
+}
+
+impl MailPatch {
+    /// Maps the message onto a pending transaction; `source` is the author when the mail has none.
+    pub fn into_new_transaction(self, source: &str) -> Option<NewTransaction> {
+        let (patches, _) = patch::split_unified_diff(&self.diff);
+        if patches.is_empty() {
+            return None;
+        }
+
+        let mut notes = self.body.clone();
+        if !self.binary_paths.is_empty() {
+            let list: Vec<String> = self.binary_paths.iter().map(|path| format!("- `{}`", path)).collect();
+            notes = format!("{}\n\nBinary changes not included:\n{}", notes, list.join("\n"))
+                .trim()
+                .to_string();
+        }
+
+        let mut blocks = Vec::new();
+        let mut files = Vec::new();
+        if !notes.is_empty() {
+            blocks.push(TransactionBlock::Markdown { content: notes });
+        }
+        for patch in patches
+            .into_iter()
+            .filter(|patch| !patch.diff.is_empty() || !self.binary_paths.contains(&patch.path()))
+        {
+            blocks.push(migrate::attach_file(&mut files, patch.into_transaction_file()));
+        }
+
+        let description = if self.subject.is_empty() {
+            format!("Patch from {}", source)
+        } else {
+            self.subject
+        };
+        Some(NewTransaction {
+            description,
+            author: self.author.unwrap_or_else(|| source.to_string()),
+            created_at: self.date,
+            blocks,
+            files,
+            reasoning: self.body,
+            ..NewTransaction::default()
+        })
+    }
+}
+
+/// Parses a `git format-patch` series into transactions in series order.
+pub fn to_new_transactions(text: &str, source: &str) -> Vec<NewTransaction> {
+    parse_mbox(text)
+        .into_iter()
+        .filter_map(|mail| mail.into_new_transaction(source))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERIES: &str = "\
+From 1111111111111111111111111111111111111111 Mon Sep 17 00:00:00 2001
+From: Jane Doe <jane@example.com>
+Date: Tue, 3 Jun 2025 10:15:00 +0200
+Subject: [PATCH v2 1/2] Add greeting
+ to the readme
+
+Explain why.
+---
+ README.md | 1 +
+ logo.png  | Bin 0 -> 12 bytes
+ 2 files changed
+
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1 +1,2 @@
+ # Demo
++Hello
+diff --git a/logo.png b/logo.png
+new file mode 100644
+GIT binary patch
+literal 12
+Tcmb=eU|?Wi
+
+-- 
+2.45.0
+
+From 2222222222222222222222222222222222222222 Mon Sep 17 00:00:00 2001
+From: Jane Doe <jane@example.com>
+Subject: [PATCH v2 2/2] Cover letter only
+
+No diff here.
+";
+
+    #[test]
+    fn recognizes_format_patch_output() {
+        assert!(is_mbox(SERIES));
+        assert!(!is_mbox("diff --git a/a b/a\n"));
+    }
+
+    #[test]
+    fn parses_headers_diffstat_and_binary_paths() {
+        let mails = parse_mbox(SERIES);
+        assert_eq!(mails.len(), 1, "messages without a diff are skipped");
+        let mail = &mails[0];
+        assert_eq!(mail.subject, "Add greeting to the readme");
+        assert_eq!(mail.author.as_deref(), Some("Jane Doe <jane@example.com>"));
+        assert_eq!(mail.date.map(|date| date.to_rfc3339()).as_deref(), Some("2025-06-03T08:15:00+00:00"));
+        assert_eq!(mail.body, "Explain why.");
+        assert_eq!(mail.diffstat.len(), 2);
+        assert!(mail.diffstat[1].binary);
+        assert_eq!(mail.binary_paths, vec!["logo.png".to_string()]);
+        assert!(!mail.diff.contains("literal 12"));
+        assert!(!mail.diff.contains("2.45.0"));
+    }
+
+    #[test]
+    fn series_becomes_transactions_with_binary_note() {
+        let transactions = to_new_transactions(SERIES, "watcher");
+        assert_eq!(transactions.len(), 1);
+        let tx = &transactions[0];
+        assert_eq!(tx.description, "Add greeting to the readme");
+        assert_eq!(tx.files.len(), 1);
+        assert_eq!(tx.files[0].path, "README.md");
+        assert!(matches!(
+            &tx.blocks[0],
+            TransactionBlock::Markdown { content } if content.contains("- `logo.png`")
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::timestamps;
+use relaycode_schema::{TransactionBlock, TransactionFile};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// The first `file-N` not in `taken`.
+fn next_file_id<'a>(taken: impl Iterator<Item = &'a str> + Clone) -> String {
+    (1..)
+        .map(|n| format!("file-{}", n))
+        .find(|id| !taken.clone().any(|t| t == id))
+        .expect("file IDs are unbounded")
+}
+
+/// Adds `file` to `files` under a fresh ID and returns the block that points at it.
+pub fn attach_file(files: &mut Vec<TransactionFile>, mut file: TransactionFile) -> TransactionBlock {
+    file.id = next_file_id(files.iter().map(|f| f.id.as_str()));
+    let block = TransactionBlock::File { file_id: file.id.clone() };
+    files.push(file);
+    block
+}
+
+/// Gives every file a unique ID and drops `file` blocks that point at no file.
+pub fn normalize(blocks: &mut Vec<TransactionBlock>, files: &mut [TransactionFile]) {
+    for i in 0..files.len() {
+        let clash = files[i].id.is_empty() || files[..i].iter().any(|f| f.id == files[i].id);
+        if clash {
+            files[i].id = next_file_id(files.iter().map(|f| f.id.as_str()));
+        }
+    }
+    blocks.retain(|block| match block.file_id() {
+        Some(id) => {
+            let known = files.iter().any(|f| f.id == id);
+            if !known {
+                tracing::warn!("Dropping block for unknown file {}", id);
+            }
+            known
+        }
+        None => true,
+    });
+}
+
+/// Older records only had a human `timestamp` such as `12 mins ago`. Without a `createdAt` one
+/// is read back from it, and `updatedAt` starts out equal to `createdAt`.
+fn legacy_times(object: &mut Map<String, Value>) {
+    if !object.contains_key("createdAt") {
+        let now = chrono::Utc::now();
+        let text = object.get("timestamp").and_then(Value::as_str).unwrap_or_default();
+        let at = timestamps::parse_relative(text, now).unwrap_or_else(|| {
+            tracing::warn!("Unreadable timestamp {:?}; using the load time", text);
+            now
+        });
+        object.insert("createdAt".to_string(), json!(at));
+    }
+    if !object.contains_key("updatedAt") {
+        if let Some(created_at) = object.get("createdAt").cloned() {
+            object.insert("updatedAt".to_string(), created_at);
+        }
+    }
+}
+
+/// Rewrites a prompt stored with only a human `timestamp`.
+pub fn legacy_prompt(prompt: &mut Value) {
+    if let Some(object) = prompt.as_object_mut() {
+        legacy_times(object);
+    }
+}
+
+/// Rewrites a transaction in the older JSON shape, where `file` blocks embedded a full copy of
+/// the file and `files` often repeated it, into one where each path is stored once in `files`
+/// and blocks carry its `fileId`. When a path appears more than once the first copy wins,
+/// block copies before `files`. Human timestamps are read as in [`legacy_prompt`]. Already
+/// migrated transactions pass through unchanged.
+pub fn legacy_transaction(tx: &mut Value) {
+    let Some(object) = tx.as_object_mut() else {
+        return;
+    };
+    legacy_times(object);
+    let mut files: Vec<Value> = Vec::new();
+    let mut ids_by_path: HashMap<String, String> = HashMap::new();
+    let mut keep = |mut file: Value, files: &mut Vec<Value>| -> Option<String> {
+        let path = file.get("path")?.as_str()?.to_string();
+        if let Some(id) = ids_by_path.get(&path) {
+            return Some(id.clone());
+        }
+        let taken: Vec<&str> = files.iter().filter_map(|f| f["id"].as_str()).collect();
+        let id = match file.get("id").and_then(Value::as_str) {
+            Some(id) if !id.is_empty() && !taken.contains(&id) => id.to_string(),
+            _ => next_file_id(taken.iter().copied()),
+        };
+        file["id"] = json!(id);
+        files.push(file);
+        ids_by_path.insert(path, id.clone());
+        Some(id)
+    };
+
+    if let Some(Value::Array(blocks)) = object.get_mut("blocks") {
+        for block in blocks.iter_mut() {
+            if block["type"] != "file" {
+                continue;
+            }
+            if let Some(file) = block.get("file").cloned() {
+                if let Some(id) = keep(file, &mut files) {
+                    *block = json!({ "type": "file", "fileId": id });
+                }
+            }
+        }
+    }
+    if let Some(Value::Array(existing)) = object.remove("files") {
+        for file in existing {
+            keep(file, &mut files);
+        }
+    }
+    object.insert("files".to_string(), Value::Array(files));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_file_copies_collapse_into_files() {
+        let mut tx = json!({
+            "timestamp": "2 hours ago",
+            "blocks": [
+                { "type": "markdown", "content": "Why" },
+                { "type": "file", "file": { "path": "src/a.rs", "diff": "block copy" } },
+                { "type": "file", "file": { "path": "src/b.rs", "diff": "" } },
+            ],
+            "files": [
+                { "id": "x", "path": "src/a.rs", "diff": "files copy" },
+                { "path": "src/c.rs", "diff": "" },
+            ],
+        });
+        legacy_transaction(&mut tx);
+
+        let files = tx["files"].as_array().unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, ["src/a.rs", "src/b.rs", "src/c.rs"]);
+        assert_eq!(files[0]["diff"], "block copy");
+        assert_eq!(tx["blocks"][1], json!({ "type": "file", "fileId": "file-1" }));
+        assert_eq!(tx["blocks"][2], json!({ "type": "file", "fileId": "file-2" }));
+        assert_eq!(files[2]["id"], "file-3");
+
+        let created: chrono::DateTime<chrono::Utc> = serde_json::from_value(tx["createdAt"].clone()).unwrap();
+        let age = chrono::Utc::now() - created;
+        assert!(age >= chrono::Duration::hours(2) && age < chrono::Duration::hours(2) + chrono::Duration::minutes(1));
+        assert_eq!(tx["updatedAt"], tx["createdAt"]);
+    }
+
+    #[test]
+    fn migrated_transactions_pass_through() {
+        let mut tx = json!({
+            "createdAt": "2025-06-03T08:15:00Z",
+            "updatedAt": "2025-06-04T08:15:00Z",
+            "blocks": [{ "type": "file", "fileId": "file-1" }],
+            "files": [{ "id": "file-1", "path": "src/a.rs", "diff": "" }],
+        });
+        let before = tx.clone();
+        legacy_transaction(&mut tx);
+        assert_eq!(tx, before);
+    }
+
+    #[test]
+    fn unreadable_timestamps_fall_back_to_load_time() {
+        let mut prompt = json!({ "timestamp": "last Tuesday" });
+        let before = chrono::Utc::now();
+        legacy_prompt(&mut prompt);
+
+        let created: chrono::DateTime<chrono::Utc> = serde_json::from_value(prompt["createdAt"].clone()).unwrap();
+        assert!(created >= before && created <= chrono::Utc::now());
+        assert_eq!(prompt["updatedAt"], prompt["createdAt"]);
+    }
+
+    #[test]
+    fn normalize_renumbers_clashes_and_drops_dangling_blocks() {
+        let file = |id: &str, path: &str| -> TransactionFile {
+            serde_json::from_value(json!({ "id": id, "path": path, "status": "modified", "language": "text", "diff": "" }))
+                .unwrap()
+        };
+        let mut files = vec![file("file-1", "a"), file("file-1", "b"), file("", "c")];
+        let mut blocks = vec![
+            TransactionBlock::File { file_id: "file-1".to_string() },
+            TransactionBlock::File { file_id: "missing".to_string() },
+        ];
+        normalize(&mut blocks, &mut files);
+        let ids: Vec<&str> = files.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, ["file-1", "file-2", "file-3"]);
+        assert_eq!(blocks.len(), 1);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::migrate;
+use relaycode_schema::{FileApplyStatus, FileStatus, NewTransaction, TransactionBlock, TransactionFile};
+
+/// A file section cut out of a multi-file unified diff.
+#[derive(Debug, Clone)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub status: FileStatus,
+    /// The hunks only, starting at the first `@@` line.
+    pub diff: String,
+}
+
+impl FilePatch {
+    pub fn path(&self) -> String {
+        self.new_path.clone().or_else(|| self.old_path.clone()).unwrap_or_default()
+    }
+
+    pub fn into_transaction_file(self) -> TransactionFile {
+        let path = self.path();
+        let old_path = self.old_path.filter(|_| matches!(self.status, FileStatus::Renamed));
+        TransactionFile {
+            id: String::new(),
+            language: language_for_path(&path).to_string(),
+            path,
+            old_path,
+            status: self.status,
+            apply_status: FileApplyStatus::Pending,
+            error_message: None,
+            diff: self.diff,
+            diagnostics: Vec::new(),
+            base_content: None,
+            conflicts: Vec::new(),
+            hunk_decisions: Vec::new(),
+            skipped_hunks: Vec::new(),
+            review: None,
+            revisions: Vec::new(),
+        }
+    }
+}
+
+pub fn language_for_path(path: &str) -> &'static str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if name == "Dockerfile" {
+        return "dockerfile";
+    }
+    match name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("") {
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "rs" => "rust",
+        "py" => "python",
+        "go" => "go",
+        "json" => "json",
+        "yml" | "yaml" => "yaml",
+        "toml" => "toml",
+        "md" => "markdown",
+        "css" | "scss" => "css",
+        "html" => "html",
+        "sh" | "bash" | "example" => "bash",
+        "sql" => "sql",
+        "prisma" => "prisma",
+        _ => "text",
+    }
+}
+
+fn strip_path_prefix(raw: &str) -> Option<String> {
+    // `--- a/src/x.ts\t2024-01-01 00:00:00` → `src/x.ts`
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Git header lines that may sit between `diff --git` and the first hunk.
+pub(crate) const EXTENDED_HEADERS: &[&str] = &[
+    "index ",
+    "old mode ",
+    "new mode ",
+    "similarity index ",
+    "dissimilarity index ",
+    "copy from ",
+    "copy to ",
+    "Binary files ",
+];
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+/// Splits text into per-file patches and the surrounding prose.
+pub fn split_unified_diff(text: &str) -> (Vec<FilePatch>, String) {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches = Vec::new();
+    let mut prose = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let git_header = line.starts_with("diff --git ");
+        let plain_header = line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ "));
+        if !git_header && !plain_header {
+            if !is_fence(line) {
+                prose.push(line);
+            }
+            i += 1;
+            continue;
+        }
+
+        let mut patch = FilePatch {
+            old_path: None,
+            new_path: None,
+            status: FileStatus::Modified,
+            diff: String::new(),
+        };
+        let (mut created, mut deleted, mut renamed) = (false, false, false);
+        if git_header {
+            // `diff --git a/x b/y` names both sides even when no `---`/`+++` follow (pure renames, mode changes).
+            if let Some((old, new)) = line["diff --git ".len()..].split_once(" b/") {
+                patch.old_path = strip_path_prefix(old);
+                patch.new_path = Some(new.to_string());
+            }
+            i += 1;
+        }
+
+        let mut in_hunks = false;
+        let mut diff_lines = Vec::new();
+        while i < lines.len() {
+            let line = lines[i];
+            if line.starts_with("diff --git ") || is_fence(line) {
+                break;
+            }
+            if !in_hunks {
+                if let Some(rest) = line.strip_prefix("--- ") {
+                    patch.old_path = strip_path_prefix(rest);
+                    created |= patch.old_path.is_none();
+                } else if let Some(rest) = line.strip_prefix("+++ ") {
+                    patch.new_path = strip_path_prefix(rest);
+                    deleted |= patch.new_path.is_none();
+                } else if line.starts_with("new file mode") {
+                    created = true;
+                } else if line.starts_with("deleted file mode") {
+                    deleted = true;
+                } else if let Some(rest) = line.strip_prefix("rename from ") {
+                    patch.old_path = Some(rest.to_string());
+                    renamed = true;
+                } else if let Some(rest) = line.strip_prefix("rename to ") {
+                    patch.new_path = Some(rest.to_string());
+                    renamed = true;
+                } else if line.starts_with("@@") {
+                    in_hunks = true;
+                } else if !EXTENDED_HEADERS.iter().any(|header| line.starts_with(header)) {
+                    break;
+                }
+            } else if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ ")) {
+                // Next file of a plain (non-git) multi-file diff.
+                break;
+            } else if !matches!(line.chars().next(), None | Some('+' | '-' | ' ' | '@' | '\\')) {
+                break;
+            }
+            if in_hunks {
+                diff_lines.push(line);
+            }
+            i += 1;
+        }
+
+        patch.status = if created {
+            FileStatus::Created
+        } else if deleted {
+            FileStatus::Deleted
+        } else if renamed || (patch.old_path.is_some() && patch.old_path != patch.new_path) {
+            FileStatus::Renamed
+        } else {
+            FileStatus::Modified
+        };
+        if created {
+            patch.old_path = None;
+        }
+        if deleted {
+            patch.new_path = None;
+        }
+        patch.diff = diff_lines.join("\n");
+        if !patch.path().is_empty() {
+            patches.push(patch);
+        }
+    }
+
+    (patches, prose.join("\n").trim().to_string())
+}
+
+/// Builds a pending transaction from patch text, or `None` when it contains no file changes.
+pub fn to_new_transaction(text: &str, source: &str) -> Option<NewTransaction> {
+    let (patches, prose) = split_unified_diff(text);
+    if patches.is_empty() {
+        return None;
+    }
+
+    let description = prose
+        .lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.chars().take(120).collect())
+        .unwrap_or_else(|| format!("Patch from {}", source));
+
+    let mut blocks = Vec::new();
+    let mut files = Vec::new();
+    if !prose.is_empty() {
+        blocks.push(TransactionBlock::Markdown { content: prose.clone() });
+    }
+    for patch in patches {
+        blocks.push(migrate::attach_file(&mut files, patch.into_transaction_file()));
+    }
+
+    Some(NewTransaction {
+        description,
+        author: source.to_string(),
+        blocks,
+        files,
+        reasoning: prose,
+        ..NewTransaction::default()
+    })
+}
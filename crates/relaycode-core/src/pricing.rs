@@ -0,0 +1,136 @@
+use relaycode_schema::{Cost, TokenUsage};
+use rust_decimal::Decimal;
+
+/// USD per million tokens.
+struct Price {
+    provider: &'static str,
+    /// Matched against the start of the normalized model name; the longest match wins.
+    model: &'static str,
+    input: Decimal,
+    output: Decimal,
+    cached_input: Decimal,
+}
+
+/// Prices given in thousandths of a dollar per million tokens, to keep the table free of decimals.
+const fn price(provider: &'static str, model: &'static str, input: u32, output: u32, cached_input: u32) -> Price {
+    Price {
+        provider,
+        model,
+        input: Decimal::from_parts(input, 0, 0, false, 3),
+        output: Decimal::from_parts(output, 0, 0, false, 3),
+        cached_input: Decimal::from_parts(cached_input, 0, 0, false, 3),
+    }
+}
+
+const PRICES: &[Price] = &[
+    price("openai", "gpt-4o", 2500, 10000, 1250),
+    price("openai", "gpt-4o-mini", 150, 600, 75),
+    price("openai", "gpt-4.1", 2000, 8000, 500),
+    price("openai", "gpt-4.1-mini", 400, 1600, 100),
+    price("openai", "gpt-4.1-nano", 100, 400, 25),
+    price("openai", "o3", 2000, 8000, 500),
+    price("openai", "o4-mini", 1100, 4400, 275),
+    price("anthropic", "claude-3-opus", 15000, 75000, 1500),
+    price("anthropic", "claude-opus-4", 15000, 75000, 1500),
+    price("anthropic", "claude-3-5-sonnet", 3000, 15000, 300),
+    price("anthropic", "claude-3-7-sonnet", 3000, 15000, 300),
+    price("anthropic", "claude-sonnet-4", 3000, 15000, 300),
+    price("anthropic", "claude-3-5-haiku", 800, 4000, 80),
+    price("anthropic", "claude-3-haiku", 250, 1250, 30),
+    price("google", "gemini-2.5-pro", 1250, 10000, 310),
+    price("google", "gemini-2.5-flash", 300, 2500, 75),
+    price("google", "gemini-2.0-flash", 100, 400, 25),
+];
+
+/// Providers that never charge, whatever the model.
+const FREE_PROVIDERS: [&str; 2] = ["ollama", crate::ai::MOCK_PROVIDER];
+
+/// Lower case, with dots turned into dashes so `claude-3.5-sonnet` matches `claude-3-5-sonnet`.
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace('.', "-")
+}
+
+/// The table entry for `provider`/`model`. Routed names such as OpenRouter's
+/// `anthropic/claude-3-opus` are looked up under the vendor before the slash.
+fn lookup(provider: &str, model: &str) -> Option<&'static Price> {
+    let model = normalize(model);
+    let (provider, model) = match model.split_once('/') {
+        Some((vendor, name)) => (vendor.to_string(), name.to_string()),
+        None => (normalize(provider), model),
+    };
+    PRICES
+        .iter()
+        .filter(|p| p.provider == provider && model.starts_with(&normalize(p.model)))
+        .max_by_key(|p| p.model.len())
+}
+
+/// What `usage` costs at list price, or `None` when the model is not in the table.
+pub fn estimate(provider: &str, model: &str, usage: &TokenUsage) -> Option<Cost> {
+    let free = FREE_PROVIDERS.contains(&normalize(provider).as_str());
+    let amount = if free {
+        Decimal::ZERO
+    } else {
+        let price = lookup(provider, model)?;
+        let million = Decimal::from(1_000_000);
+        let cached = usage.cached_tokens.min(usage.input_tokens);
+        (Decimal::from(usage.input_tokens - cached) * price.input
+            + Decimal::from(cached) * price.cached_input
+            + Decimal::from(usage.output_tokens) * price.output)
+            / million
+    };
+    Some(Cost {
+        amount: amount.round_dp(6).normalize(),
+        currency: "USD".to_string(),
+        estimated: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(provider: &str, model: &str, usage: TokenUsage) -> Option<String> {
+        estimate(provider, model, &usage).map(|cost| {
+            assert!(cost.estimated);
+            assert_eq!(cost.currency, "USD");
+            cost.amount.to_string()
+        })
+    }
+
+    #[test]
+    fn the_longest_model_prefix_wins() {
+        let million = TokenUsage::new(1_000_000, 1_000_000, 0);
+        assert_eq!(amount("openai", "gpt-4o", million.clone()).as_deref(), Some("12.5"));
+        assert_eq!(amount("openai", "gpt-4o-2024-08-06", million.clone()).as_deref(), Some("12.5"));
+        assert_eq!(amount("openai", "gpt-4o-mini", million.clone()).as_deref(), Some("0.75"));
+        assert_eq!(amount("OpenAI", "GPT-4o-mini-2024-07-18", million.clone()).as_deref(), Some("0.75"));
+        assert_eq!(amount("anthropic", "claude-3.5-sonnet-latest", million.clone()).as_deref(), Some("18"));
+        assert_eq!(amount("openai", "gpt-5", million.clone()), None);
+        assert_eq!(amount("anthropic", "gpt-4o", million), None, "prices are per provider");
+    }
+
+    #[test]
+    fn cached_input_is_billed_at_the_cached_rate() {
+        let usage = TokenUsage::new(1_000_000, 0, 400_000);
+        assert_eq!(amount("openai", "gpt-4o", usage).as_deref(), Some("2"));
+        let over_reported = TokenUsage::new(100_000, 0, 500_000);
+        assert_eq!(amount("openai", "gpt-4o", over_reported).as_deref(), Some("0.125"));
+    }
+
+    #[test]
+    fn routed_names_are_priced_under_their_vendor() {
+        let usage = TokenUsage::new(1_000, 1_000, 0);
+        assert_eq!(amount("openrouter", "anthropic/claude-3.5-sonnet", usage.clone()).as_deref(), Some("0.018"));
+        assert_eq!(amount("openrouter", "openai/gpt-4o-mini", usage.clone()).as_deref(), Some("0.00075"));
+        assert_eq!(amount("openrouter", "meta-llama/llama-3-70b", usage.clone()), None);
+        assert_eq!(amount("openrouter", "claude-3-5-sonnet", usage), None, "no vendor, no price");
+    }
+
+    #[test]
+    fn free_providers_cost_nothing_for_any_model() {
+        let usage = TokenUsage::new(5_000, 5_000, 0);
+        assert_eq!(amount("ollama", "llama3.1:8b", usage.clone()).as_deref(), Some("0"));
+        assert_eq!(amount(" Ollama ", "anything", usage.clone()).as_deref(), Some("0"));
+        assert_eq!(amount(crate::ai::MOCK_PROVIDER, "mock", usage).as_deref(), Some("0"));
+    }
+}
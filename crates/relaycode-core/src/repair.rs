@@ -0,0 +1,198 @@
+use crate::ai::{self, AiError, AiProvider, CompletionRequest, RepairContext, Task};
+use crate::{apply, config, diff, system_prompt, STORE};
+use relaycode_schema::{FileApplyStatus, RepairEvent, RepairStage, UpdateFileDiffRequest};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Provider round trips per file before the repair gives up.
+const MAX_ATTEMPTS: u32 = 2;
+
+/// Appended to the project system prompt for repair requests.
+const REPAIR_INSTRUCTIONS: &str = "You repair unified diffs that no longer apply to the file they target. \
+Keep the intended change and fix only context lines and line numbers. Reply with the corrected diff \
+for the one file, hunks only, in a single ```diff fenced block.";
+
+/// Starts repairing each of `paths` in the background. Returns the provider doing the work.
+pub fn start(id: &str, paths: Vec<String>) -> Result<String, AiError> {
+    let provider = ai::provider_from_config(&STORE.get_config().ai_provider)?;
+    for path in paths {
+        let (id, provider) = (id.to_string(), provider.clone());
+        tokio::spawn(async move { repair_file(&id, &path, provider).await });
+    }
+    Ok(provider.name().to_string())
+}
+
+struct Progress<'a> {
+    id: &'a str,
+    path: &'a str,
+    started: Instant,
+}
+
+impl Progress<'_> {
+    fn emit(&self, stage: RepairStage, attempt: u32, message: Option<String>) {
+        let _ = STORE.repair_sender.send(RepairEvent {
+            transaction_id: self.id.to_string(),
+            file_path: self.path.to_string(),
+            stage,
+            attempt,
+            message,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+        });
+    }
+}
+
+async fn repair_file(id: &str, path: &str, provider: Arc<dyn AiProvider>) {
+    let progress = Progress {
+        id,
+        path,
+        started: Instant::now(),
+    };
+    progress.emit(RepairStage::ContextAnalysis, 0, None);
+    let Some(context) = gather_context(id, path) else {
+        progress.emit(RepairStage::Failed, 0, Some("File is no longer part of the transaction".to_string()));
+        return;
+    };
+    // Only now, so a file that cannot be repaired is never left in APPLYING.
+    crate::set_file_status(id, path, FileApplyStatus::Applying, None);
+
+    let mut last_error = context.error.clone();
+    let mut feedback: Option<String> = None;
+    for attempt in 1..=MAX_ATTEMPTS {
+        progress.emit(RepairStage::ApiRequest, attempt, Some(provider.name().to_string()));
+        let request = CompletionRequest {
+            system: system_prompt::compose(REPAIR_INSTRUCTIONS),
+            prompt: build_prompt(&context, feedback.as_deref()),
+            model: None,
+            task: Task::RepairPatch(context.clone()),
+        };
+        let completion = match provider.complete(&request).await {
+            Ok(completion) => completion,
+            Err(err) => {
+                last_error = err.to_string();
+                feedback = None;
+                continue;
+            }
+        };
+
+        progress.emit(RepairStage::Validation, attempt, None);
+        let candidate = extract_diff(&completion.text);
+        if let Err(err) = check_candidate(&context, &candidate) {
+            last_error = err.clone();
+            feedback = Some(err);
+            continue;
+        }
+
+        progress.emit(RepairStage::Applying, attempt, None);
+        let replaced = STORE.replace_repairing_diff(
+            id,
+            &UpdateFileDiffRequest {
+                file_path: path.to_string(),
+                diff: candidate,
+                author: Some(format!("ai:{}", provider.name())),
+            },
+        );
+        if !matches!(replaced, Some(Ok(_))) {
+            last_error = "Transaction changed during repair".to_string();
+            break;
+        }
+        let (status, error) = crate::apply_file_now(id, path).await;
+        if status == FileApplyStatus::Applied {
+            progress.emit(RepairStage::Succeeded, attempt, None);
+            return;
+        }
+        last_error = error.unwrap_or_else(|| "Repaired patch did not apply".to_string());
+        feedback = Some(last_error.clone());
+    }
+
+    let message = format!("Auto-repair failed: {}", last_error);
+    crate::set_file_status(id, path, FileApplyStatus::Failed, Some(message.clone()));
+    progress.emit(RepairStage::Failed, MAX_ATTEMPTS, Some(message));
+}
+
+fn gather_context(id: &str, path: &str) -> Option<RepairContext> {
+    let tx = STORE.get_transaction(id)?;
+    let file = tx.files.into_iter().find(|f| f.path == path)?;
+    let content = apply::resolve_in_root(&config::project_root(), path)
+        .ok()
+        .and_then(|full| std::fs::read_to_string(full).ok())
+        .unwrap_or_default();
+    let failed_hunks = apply::failed_hunks(&content, &file.diff);
+    Some(RepairContext {
+        path: file.path,
+        language: file.language,
+        content,
+        diff: file.diff,
+        failed_hunks,
+        error: file.error_message.unwrap_or_default(),
+    })
+}
+
+fn build_prompt(context: &RepairContext, feedback: Option<&str>) -> String {
+    let mut prompt = format!(
+        "The patch for `{path}` no longer applies: {error}\n\n\
+         Current content of `{path}`:\n```{language}\n{content}\n```\n\n\
+         Hunks that fail:\n```diff\n{failed}\n```\n\n\
+         Full patch:\n```diff\n{diff}\n```\n",
+        path = context.path,
+        error = if context.error.is_empty() { "unknown error" } else { &context.error },
+        language = context.language,
+        content = context.content.trim_end(),
+        failed = context.failed_hunks.join("\n"),
+        diff = context.diff.trim_end(),
+    );
+    if let Some(feedback) = feedback {
+        prompt.push_str(&format!("\nYour previous answer was rejected: {}\n", feedback));
+    }
+    prompt
+}
+
+/// The diff inside the reply's first ```diff block, else its first fenced block, else the whole reply.
+fn extract_diff(reply: &str) -> String {
+    let fenced = |opening: &str| {
+        let start = reply.find(opening)?;
+        let body = &reply[start + opening.len()..];
+        let body = &body[body.find('\n')? + 1..];
+        Some(body[..body.find("```").unwrap_or(body.len())].to_string())
+    };
+    fenced("```diff")
+        .or_else(|| fenced("```"))
+        .unwrap_or_else(|| reply.to_string())
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// A repaired diff must parse and apply to the file as it is now.
+fn check_candidate(context: &RepairContext, candidate: &str) -> Result<(), String> {
+    diff::validate(candidate).map_err(|errors| errors.join("; "))?;
+    apply::apply_hunks(&context.content, candidate).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn repair_rewrites_a_failed_file_and_applies_it() {
+        let _store = crate::testing::lock().await;
+        let root = crate::testing::configure(relaycode_schema::PatchConfig {
+            apply_to_disk: true,
+            ..relaycode_schema::PatchConfig::default()
+        });
+        std::fs::write(root.join("repair.rs"), "fn a() {\n        1\n}\n").unwrap();
+        let tx = crate::ingest_transaction(crate::testing::new_transaction(&[(
+            "repair.rs",
+            "modified",
+            "@@ -1,3 +1,3 @@\n fn a() {\n-    1\n+    2\n }",
+        )]));
+        crate::start_simulation(tx.id.clone(), crate::ApplyOptions::manual(None, false));
+        assert_eq!(crate::testing::settled(&tx.id).await, relaycode_schema::TransactionStatus::Failed);
+
+        repair_file(&tx.id, "repair.rs", Arc::new(ai::MockProvider)).await;
+        let tx = STORE.get_transaction(&tx.id).unwrap();
+        assert_eq!(tx.status, relaycode_schema::TransactionStatus::Applied);
+        assert_eq!(tx.files[0].apply_status, FileApplyStatus::Applied);
+        assert_eq!(tx.files[0].revisions.len(), 1);
+        assert_eq!(tx.files[0].revisions[0].replaced_by.as_deref(), Some("ai:mock"));
+        assert_eq!(std::fs::read_to_string(root.join("repair.rs")).unwrap(), "fn a() {\n    2\n}\n");
+    }
+}
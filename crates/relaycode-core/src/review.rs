@@ -0,0 +1,174 @@
+use crate::diff::{self, Hunk};
+use relaycode_schema::{
+    FileHunks, FileReview, HunkDecision, HunkDecisionUpdate, HunkReview, ReviewDecision, ReviewFilesRequest,
+    Transaction, TransactionFile,
+};
+
+/// FNV-1a, so ids stay the same across builds and restarts.
+fn fnv1a(text: &str) -> u32 {
+    text.bytes()
+        .fold(0x811c9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+/// A parsed hunk with its stable id and the diff lines it spans.
+struct AddressedHunk<'a> {
+    id: String,
+    hunk: Hunk,
+    text: Vec<&'a str>,
+}
+
+fn addressed_hunks(diff: &str) -> Vec<AddressedHunk<'_>> {
+    let lines: Vec<&str> = diff.lines().collect();
+    let hunks = diff::parse_hunks(diff);
+    let starts: Vec<usize> = hunks.iter().map(|hunk| hunk.header_index).collect();
+    let mut result: Vec<AddressedHunk> = Vec::new();
+    for (n, hunk) in hunks.into_iter().enumerate() {
+        let end = starts.get(n + 1).copied().unwrap_or(lines.len());
+        let text = lines[hunk.header_index..end].to_vec();
+        let base = format!("h-{:08x}", fnv1a(&text.join("\n")));
+        // Identical hunks in one file get `-2`, `-3`… in order.
+        let repeats = result.iter().filter(|h| h.id == base || h.id.starts_with(&format!("{}-", base))).count();
+        let id = if repeats == 0 { base } else { format!("{}-{}", base, repeats + 1) };
+        result.push(AddressedHunk { id, hunk, text });
+    }
+    result
+}
+
+/// Each hunk of `diff` as standalone diff text, header line included.
+pub fn hunk_texts(diff: &str) -> Vec<String> {
+    addressed_hunks(diff).into_iter().map(|hunk| hunk.text.join("\n")).collect()
+}
+
+fn decision_for(file: &TransactionFile, hunk_id: &str) -> ReviewDecision {
+    file.hunk_decisions
+        .iter()
+        .find(|d| d.hunk_id == hunk_id)
+        .map(|d| d.decision)
+        .unwrap_or_default()
+}
+
+pub fn file_hunks(file: &TransactionFile) -> FileHunks {
+    FileHunks {
+        path: file.path.clone(),
+        hunks: addressed_hunks(&file.diff)
+            .into_iter()
+            .map(|addressed| HunkReview {
+                decision: decision_for(file, &addressed.id),
+                skipped: file.skipped_hunks.contains(&addressed.id),
+                header: addressed.text.first().copied().unwrap_or_default().to_string(),
+                old_start: addressed.hunk.old_start,
+                old_count: addressed.hunk.old_count,
+                new_start: addressed.hunk.new_start,
+                new_count: addressed.hunk.new_count,
+                id: addressed.id,
+            })
+            .collect(),
+    }
+}
+
+pub fn list_hunks(tx: &Transaction) -> Vec<FileHunks> {
+    tx.files.iter().map(file_hunks).collect()
+}
+
+/// Records hunk decisions on the named files. Unknown paths or hunk ids are reported and nothing
+/// is changed.
+pub fn apply_hunk_decisions(tx: &mut Transaction, updates: &[HunkDecisionUpdate]) -> Result<(), Vec<String>> {
+    let errors: Vec<String> = updates
+        .iter()
+        .filter_map(|update| {
+            let file = tx.files.iter().find(|f| f.path == update.path);
+            match file {
+                None => Some(format!("{}: not part of this transaction", update.path)),
+                Some(file) if !addressed_hunks(&file.diff).iter().any(|h| h.id == update.hunk_id) => {
+                    Some(format!("{}: no hunk {}", update.path, update.hunk_id))
+                }
+                Some(_) => None,
+            }
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    for file in tx.files.iter_mut() {
+        for update in updates.iter().filter(|u| u.path == file.path) {
+            file.hunk_decisions.retain(|d| d.hunk_id != update.hunk_id);
+            if update.decision != ReviewDecision::Undecided {
+                file.hunk_decisions.push(HunkDecision {
+                    hunk_id: update.hunk_id.clone(),
+                    decision: update.decision,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The part of a file's diff that may be applied.
+pub struct ApprovedDiff {
+    pub diff: String,
+    pub skipped: Vec<String>,
+    /// Every hunk was left out, so the file should not be touched at all.
+    pub nothing_approved: bool,
+}
+
+/// Filters a file's diff by hunk review. Files nobody reviewed hunk by hunk apply in full; once a
+/// file has any hunk decision, only its approved hunks are applied.
+pub fn approved_diff(file: &TransactionFile) -> ApprovedDiff {
+    let hunks = addressed_hunks(&file.diff);
+    let reviewed = hunks.iter().any(|h| decision_for(file, &h.id) != ReviewDecision::Undecided);
+    if !reviewed {
+        return ApprovedDiff {
+            diff: file.diff.clone(),
+            skipped: Vec::new(),
+            nothing_approved: false,
+        };
+    }
+
+    let (kept, skipped): (Vec<_>, Vec<_>) = hunks
+        .into_iter()
+        .partition(|h| decision_for(file, &h.id) == ReviewDecision::Approved);
+    ApprovedDiff {
+        diff: kept.iter().flat_map(|h| h.text.iter().copied()).collect::<Vec<_>>().join("\n"),
+        nothing_approved: kept.is_empty(),
+        skipped: skipped.into_iter().map(|h| h.id).collect(),
+    }
+}
+
+/// Sets the file-level decision on the requested files. Unknown paths are reported and nothing
+/// is changed.
+pub fn apply_file_decisions(tx: &mut Transaction, request: &ReviewFilesRequest) -> Result<(), Vec<String>> {
+    let known = crate::file_paths(tx);
+    let errors: Vec<String> = request
+        .paths
+        .iter()
+        .filter(|path| !known.contains(path))
+        .map(|path| format!("{}: not part of this transaction", path))
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let review = FileReview {
+        decision: request.decision,
+        reviewer: request.reviewer.clone(),
+        decided_at: chrono::Utc::now(),
+    };
+    for file in tx.files.iter_mut().filter(|file| request.paths.contains(&file.path)) {
+        file.review = Some(review.clone());
+    }
+    Ok(())
+}
+
+fn file_decision(file: &TransactionFile) -> ReviewDecision {
+    file.review.as_ref().map(|review| review.decision).unwrap_or_default()
+}
+
+/// Whether the apply should touch `file`. Until a reviewer decides on some file, every file is
+/// applied; after that only approved files are, and rejected or undecided ones are left alone.
+pub fn file_selected(tx: &Transaction, file: &TransactionFile) -> bool {
+    let reviewed = tx.files
+        .iter()
+        .any(|f| file_decision(f) != ReviewDecision::Undecided);
+    !reviewed || file_decision(file) == ReviewDecision::Approved
+}
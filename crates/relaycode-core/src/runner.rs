@@ -0,0 +1,168 @@
+use relaycode_schema::{OutputStream, ValidationKind, ValidationRun};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+/// Receives each line of command output as it is produced.
+pub type OutputSink = Arc<dyn Fn(OutputStream, String) + Send + Sync>;
+
+/// How long to keep draining pipes after a timed-out command was killed.
+const DRAIN_GRACE: Duration = Duration::from_millis(500);
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// Runs `command` through the shell in `cwd`, streaming output to `sink` and capturing it in full.
+pub async fn run_command(
+    kind: ValidationKind,
+    command: &str,
+    cwd: &Path,
+    timeout: Duration,
+    sink: OutputSink,
+) -> ValidationRun {
+    let started_at = chrono::Utc::now();
+    let started = Instant::now();
+
+    let spawned = shell(command)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(err) => {
+            return ValidationRun {
+                kind,
+                command: command.to_string(),
+                started_at,
+                exit_code: None,
+                stdout: String::new(),
+                stderr: format!("failed to start command: {}", err),
+                duration_ms: started.elapsed().as_millis() as u64,
+                timed_out: false,
+                success: false,
+            };
+        }
+    };
+
+    let stdout = Arc::new(Mutex::new(String::new()));
+    let stderr = Arc::new(Mutex::new(String::new()));
+    let mut readers = Vec::new();
+    if let Some(pipe) = child.stdout.take() {
+        readers.push(tokio::spawn(collect_lines(pipe, OutputStream::Stdout, stdout.clone(), sink.clone())));
+    }
+    if let Some(pipe) = child.stderr.take() {
+        readers.push(tokio::spawn(collect_lines(pipe, OutputStream::Stderr, stderr.clone(), sink.clone())));
+    }
+
+    let (exit_code, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(Ok(status)) => (status.code(), false),
+        Ok(Err(_)) => (None, false),
+        Err(_) => {
+            let _ = child.kill().await;
+            (None, true)
+        }
+    };
+
+    // Grandchildren may keep the pipes open after the shell exits, so don't wait on them forever.
+    for reader in readers {
+        let abort = reader.abort_handle();
+        if tokio::time::timeout(DRAIN_GRACE, reader).await.is_err() {
+            abort.abort();
+        }
+    }
+
+    let stdout = stdout.lock().unwrap().clone();
+    let mut stderr = stderr.lock().unwrap().clone();
+    if timed_out {
+        stderr.push_str(&format!("command timed out after {}ms\n", timeout.as_millis()));
+    }
+
+    ValidationRun {
+        kind,
+        command: command.to_string(),
+        started_at,
+        exit_code,
+        stdout,
+        stderr,
+        duration_ms: started.elapsed().as_millis() as u64,
+        timed_out,
+        success: exit_code == Some(0),
+    }
+}
+
+async fn collect_lines<R>(pipe: R, stream: OutputStream, buffer: Arc<Mutex<String>>, sink: OutputSink)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut lines = BufReader::new(pipe).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        {
+            let mut buffer = buffer.lock().unwrap();
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+        sink(stream.clone(), line);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    async fn run(command: &str, timeout: Duration) -> (ValidationRun, Vec<(OutputStream, String)>) {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let seen = lines.clone();
+        let sink: OutputSink = Arc::new(move |stream, line| seen.lock().unwrap().push((stream, line)));
+        let run = run_command(ValidationKind::PostCommand, command, &std::env::temp_dir(), timeout, sink).await;
+        let lines = lines.lock().unwrap().clone();
+        (run, lines)
+    }
+
+    #[tokio::test]
+    async fn captures_and_streams_a_passing_command() {
+        let (run, lines) = run("echo one; echo two", Duration::from_secs(10)).await;
+        assert!(run.success);
+        assert_eq!(run.exit_code, Some(0));
+        assert_eq!(run.stdout, "one\ntwo\n");
+        assert_eq!(
+            lines,
+            [(OutputStream::Stdout, "one".to_string()), (OutputStream::Stdout, "two".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_a_failing_command() {
+        let (run, _) = run("echo broken >&2; exit 3", Duration::from_secs(10)).await;
+        assert!(!run.success);
+        assert!(!run.timed_out);
+        assert_eq!(run.exit_code, Some(3));
+        assert_eq!(run.stderr, "broken\n");
+    }
+
+    #[tokio::test]
+    async fn kills_a_command_that_runs_too_long() {
+        let (run, _) = run("echo started; sleep 30", Duration::from_millis(300)).await;
+        assert!(run.timed_out);
+        assert!(!run.success);
+        assert_eq!(run.exit_code, None);
+        assert_eq!(run.stdout, "started\n");
+        assert!(run.stderr.ends_with("command timed out after 300ms\n"));
+        assert!(run.duration_ms < 10_000);
+    }
+}
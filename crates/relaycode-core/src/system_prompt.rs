@@ -0,0 +1,212 @@
+use crate::{config, STORE};
+use relaycode_schema::{PatchStrategy, RelayConfig, SystemPrompt, SystemPromptPreview, SystemPromptVersion};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_TEMPLATE: &str = "You are a coding assistant working on the {{projectId}} project through Relaycode.
+
+Relaycode applies the changes you propose as patches. Use the {{preferredStrategy}} patch strategy \
+where you can; the available strategies are: {{availableStrategies}}.
+
+- Give every changed file its own patch, with paths relative to the project root.
+- Keep patches minimal and do not reformat code you are not changing.
+- Explain your reasoning briefly before the patches.
+";
+
+const ALL_STRATEGIES: [PatchStrategy; 4] = [
+    PatchStrategy::Auto,
+    PatchStrategy::Replace,
+    PatchStrategy::NewUnified,
+    PatchStrategy::MultiSearchReplace,
+];
+
+pub fn prompt_path() -> PathBuf {
+    config::relay_dir().join("prompts").join("system-prompt.md")
+}
+
+/// Every saved version, oldest first.
+fn history_path() -> PathBuf {
+    config::relay_dir().join("prompts").join("system-prompt.history.json")
+}
+
+pub fn versions() -> Vec<SystemPromptVersion> {
+    std::fs::read_to_string(history_path())
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Writes a sibling temp file and renames it over `path`, so a failed write leaves the old file whole.
+fn replace_file(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
+
+pub fn current() -> SystemPrompt {
+    let latest = versions().pop();
+    match std::fs::read_to_string(prompt_path()) {
+        Ok(content) => SystemPrompt {
+            version: latest.as_ref().map(|v| v.version).unwrap_or(0),
+            updated_at: latest.map(|v| v.saved_at),
+            content,
+            is_default: false,
+        },
+        Err(_) => SystemPrompt {
+            content: DEFAULT_TEMPLATE.to_string(),
+            version: 0,
+            updated_at: None,
+            is_default: true,
+        },
+    }
+}
+
+/// Writes the template and records it as a new version. A file edited by hand since the last
+/// save is recorded first, so that edit stays in the history too. The history is written before
+/// the template, so a failed save never leaves a template the history does not know.
+pub fn save(content: &str) -> io::Result<SystemPrompt> {
+    std::fs::create_dir_all(prompt_path().parent().expect("prompt path has a parent"))?;
+    let mut history = versions();
+    let now = chrono::Utc::now();
+    let record = |history: &mut Vec<SystemPromptVersion>, content: String| {
+        let version = history.last().map(|v| v.version).unwrap_or(0) + 1;
+        history.push(SystemPromptVersion {
+            version,
+            saved_at: now,
+            content,
+        });
+    };
+
+    if let Ok(on_disk) = std::fs::read_to_string(prompt_path()) {
+        if history.last().is_none_or(|latest| latest.content != on_disk) {
+            record(&mut history, on_disk);
+        }
+    }
+    record(&mut history, content.to_string());
+
+    replace_file(&history_path(), &(serde_json::to_string_pretty(&history)? + "\n"))?;
+    replace_file(&prompt_path(), content)?;
+    Ok(current())
+}
+
+/// Saves an earlier version's content as the newest version. `None` for an unknown version.
+pub fn restore(version: u32) -> Option<io::Result<SystemPrompt>> {
+    let content = versions().into_iter().find(|v| v.version == version)?.content;
+    Some(save(&content))
+}
+
+fn strategy_name(strategy: &PatchStrategy) -> String {
+    serde_json::to_value(strategy)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn variables(config: &RelayConfig) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("projectId".to_string(), config.project_id.clone()),
+        ("preferredStrategy".to_string(), strategy_name(&config.watcher.preferred_strategy)),
+        (
+            "availableStrategies".to_string(),
+            ALL_STRATEGIES.iter().map(strategy_name).collect::<Vec<_>>().join(", "),
+        ),
+    ])
+}
+
+/// Replaces `{{name}}` placeholders. Unknown names are left as written and returned; an
+/// unterminated `{{` is kept as text.
+fn fill(template: &str, variables: &BTreeMap<String, String>) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(template.len());
+    let mut unknown: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + 2 + len + 2];
+        let name = rest[start + 2..start + 2 + len].trim();
+        match variables.get(name) {
+            Some(value) => out.push_str(value),
+            None => {
+                out.push_str(placeholder);
+                if !unknown.iter().any(|u| u == name) {
+                    unknown.push(name.to_string());
+                }
+            }
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    out.push_str(rest);
+    (out, unknown)
+}
+
+/// Renders `draft`, or the saved template when there is none, with the current config.
+pub fn preview(draft: Option<&str>) -> SystemPromptPreview {
+    let template = draft.map(str::to_string).unwrap_or_else(|| current().content);
+    let variables = variables(&STORE.get_config());
+    let (rendered, unknown_variables) = fill(&template, &variables);
+    SystemPromptPreview {
+        rendered: rendered.trim_end().to_string(),
+        variables,
+        unknown_variables,
+    }
+}
+
+/// The system message for a provider request: the rendered project prompt, then the task's own instructions.
+pub fn compose(task_instructions: &str) -> String {
+    format!("{}\n\n{}", preview(None).rendered, task_instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> BTreeMap<String, String> {
+        BTreeMap::from([("projectId".to_string(), "relay".to_string()), ("preferredStrategy".to_string(), "replace".to_string())])
+    }
+
+    #[test]
+    fn fill_replaces_known_placeholders_everywhere() {
+        let (out, unknown) = fill("{{projectId}} uses {{ preferredStrategy }}; {{projectId}} again", &vars());
+        assert_eq!(out, "relay uses replace; relay again");
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn fill_keeps_and_reports_unknown_names_once() {
+        let (out, unknown) = fill("{{who}} and {{projectId}} and {{who}} and {{ when }}", &vars());
+        assert_eq!(out, "{{who}} and relay and {{who}} and {{ when }}");
+        assert_eq!(unknown, vec!["who".to_string(), "when".to_string()]);
+    }
+
+    #[test]
+    fn fill_keeps_an_unterminated_placeholder_as_text() {
+        let (out, unknown) = fill("{{projectId}} then {{projectId", &vars());
+        assert_eq!(out, "relay then {{projectId");
+        assert!(unknown.is_empty());
+        assert_eq!(fill("no placeholders {{", &vars()).0, "no placeholders {{");
+        assert_eq!(fill("}} {{}}", &vars()), ("}} {{}}".to_string(), vec![String::new()]));
+    }
+
+    #[tokio::test]
+    async fn save_records_hand_edits_and_each_saved_version() {
+        let _store = crate::testing::lock().await;
+        crate::testing::project_root();
+        let _ = std::fs::remove_dir_all(prompt_path().parent().unwrap());
+        assert!(current().is_default);
+
+        assert_eq!(save("first").unwrap().version, 1);
+        std::fs::write(prompt_path(), "edited by hand").unwrap();
+        let saved = save("second").unwrap();
+        assert_eq!((saved.version, saved.content.as_str(), saved.is_default), (3, "second", false));
+        let contents: Vec<String> = versions().into_iter().map(|v| v.content).collect();
+        assert_eq!(contents, vec!["first", "edited by hand", "second"]);
+
+        assert_eq!(restore(1).unwrap().unwrap().content, "first");
+        assert!(restore(99).is_none());
+        assert!(!history_path().with_extension("json.tmp").exists());
+    }
+}
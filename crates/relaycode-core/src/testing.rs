@@ -0,0 +1,79 @@
+//! Helpers for tests that drive the global [`STORE`].
+
+use crate::STORE;
+use relaycode_schema::{NewTransaction, PatchConfig, RelayConfig, TransactionFile, TransactionStatus};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Tests that use the global store share its config and project root, so they take turns.
+pub async fn lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::const_new(());
+    LOCK.lock().await
+}
+
+/// The scratch directory [`crate::config::project_root`] points at for the whole test run.
+pub fn project_root() -> PathBuf {
+    static ROOT: OnceLock<PathBuf> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        let root = std::env::temp_dir().join(format!("relaycode-project-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::env::set_var("RELAYCODE_CONFIG_PATH", root.join(crate::config::CONFIG_FILE_NAME));
+        root
+    })
+    .clone()
+}
+
+/// Swaps in `patch` as the store's patch config and returns the project root.
+pub fn configure(patch: PatchConfig) -> PathBuf {
+    let root = project_root();
+    STORE.state.write().unwrap().config = RelayConfig { patch, ..RelayConfig::default() };
+    root
+}
+
+/// A transaction changing each `(path, status, diff)`.
+pub fn new_transaction(files: &[(&str, &str, &str)]) -> NewTransaction {
+    let files: Vec<TransactionFile> = files
+        .iter()
+        .map(|(path, status, diff)| {
+            serde_json::from_value(serde_json::json!({
+                "path": path,
+                "status": status,
+                "language": "text",
+                "diff": diff,
+            }))
+            .unwrap()
+        })
+        .collect();
+    NewTransaction {
+        description: "test".to_string(),
+        author: "test".to_string(),
+        files,
+        ..NewTransaction::default()
+    }
+}
+
+/// Waits until `id` has finished applying.
+pub async fn settled(id: &str) -> TransactionStatus {
+    tokio::time::timeout(Duration::from_secs(30), crate::chain::wait_until_settled(id))
+        .await
+        .expect("transaction settles")
+        .expect("transaction exists")
+}
+
+/// Waits until `id` has left `PENDING` and `APPLYING`, for members that start applying later.
+pub async fn finished(id: &str) -> TransactionStatus {
+    tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            let status = STORE.get_transaction(id).expect("transaction exists").status;
+            if !matches!(status, TransactionStatus::Pending | TransactionStatus::Applying) {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("transaction finishes")
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Duration, Utc};
+use relaycode_schema::{Prompt, StatusTransition, Transaction, TransactionStatus};
+
+/// `at` as read at `now`: `just now`, `5 mins ago`, `3 hours ago`, `2 days ago`, `3 weeks ago`,
+/// then the date itself once it is more than a month old.
+pub fn relative(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now - at;
+    let ago = |count: i64, unit: &str| format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" });
+    if elapsed < Duration::minutes(1) {
+        "just now".to_string()
+    } else if elapsed < Duration::hours(1) {
+        ago(elapsed.num_minutes(), "min")
+    } else if elapsed < Duration::days(1) {
+        ago(elapsed.num_hours(), "hour")
+    } else if elapsed < Duration::weeks(1) {
+        ago(elapsed.num_days(), "day")
+    } else if elapsed < Duration::days(31) {
+        ago(elapsed.num_weeks(), "week")
+    } else {
+        at.format("%b %-d, %Y").to_string()
+    }
+}
+
+/// Reads the older human strings (`12 mins ago`, `1 hour ago`, `Just now`, `Yesterday`) back
+/// into an instant relative to `now`. Months count as 30 days and years as 365.
+pub fn parse_relative(text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let text = text.trim().to_lowercase();
+    match text.as_str() {
+        "just now" | "now" => return Some(now),
+        "yesterday" => return Some(now - Duration::days(1)),
+        _ => {}
+    }
+    let mut words = text.strip_suffix(" ago")?.split_whitespace();
+    let count: i64 = match words.next()? {
+        "a" | "an" => 1,
+        count => count.parse().ok()?,
+    };
+    let unit = words.next()?;
+    if words.next().is_some() {
+        return None;
+    }
+    let elapsed = match unit.trim_end_matches('s') {
+        "sec" | "second" => Duration::seconds(count),
+        "min" | "minute" => Duration::minutes(count),
+        "hour" | "hr" => Duration::hours(count),
+        "day" => Duration::days(count),
+        "week" => Duration::weeks(count),
+        "month" => Duration::days(30 * count),
+        "year" => Duration::days(365 * count),
+        _ => return None,
+    };
+    Some(now - elapsed)
+}
+
+/// Fills the derived `timestamp`.
+pub fn refresh(tx: &mut Transaction) {
+    tx.timestamp = relative(tx.created_at, Utc::now());
+}
+
+pub fn refresh_prompt(prompt: &mut Prompt) {
+    prompt.timestamp = relative(prompt.created_at, Utc::now());
+}
+
+/// Marks a transaction as changed just now.
+pub fn touch(tx: &mut Transaction) {
+    tx.updated_at = Utc::now();
+    refresh(tx);
+}
+
+/// Moves a transaction to `status`, recording the transition and stamping `appliedAt` or
+/// `committedAt`. Setting the current status again only touches it.
+pub fn set_status(tx: &mut Transaction, status: TransactionStatus) {
+    touch(tx);
+    if tx.status == status {
+        return;
+    }
+    let at = tx.updated_at;
+    match status {
+        TransactionStatus::Applied | TransactionStatus::PartiallyApplied => tx.applied_at = Some(at),
+        TransactionStatus::Committed => tx.committed_at = Some(at),
+        _ => {}
+    }
+    tx.status_history.push(StatusTransition { status: status.clone(), at });
+    tx.status = status;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        "2025-06-03T08:15:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn relative_picks_the_largest_whole_unit() {
+        let cases = [
+            (Duration::seconds(59), "just now"),
+            (Duration::minutes(1), "1 min ago"),
+            (Duration::minutes(59), "59 mins ago"),
+            (Duration::hours(1), "1 hour ago"),
+            (Duration::hours(23), "23 hours ago"),
+            (Duration::days(1), "1 day ago"),
+            (Duration::days(6), "6 days ago"),
+            (Duration::weeks(1), "1 week ago"),
+            (Duration::days(30), "4 weeks ago"),
+            (Duration::days(31), "May 3, 2025"),
+        ];
+        for (elapsed, expected) in cases {
+            assert_eq!(relative(now() - elapsed, now()), expected, "{:?}", elapsed);
+        }
+    }
+
+    #[test]
+    fn relative_treats_future_instants_as_now() {
+        assert_eq!(relative(now() + Duration::hours(2), now()), "just now");
+    }
+
+    #[test]
+    fn parse_relative_reads_legacy_strings() {
+        let cases = [
+            ("Just now", Duration::zero()),
+            ("now", Duration::zero()),
+            ("Yesterday", Duration::days(1)),
+            ("12 mins ago", Duration::minutes(12)),
+            ("1 minute ago", Duration::minutes(1)),
+            ("30 secs ago", Duration::seconds(30)),
+            ("an hour ago", Duration::hours(1)),
+            ("3 hrs ago", Duration::hours(3)),
+            ("  2 Days ago ", Duration::days(2)),
+            ("a week ago", Duration::weeks(1)),
+            ("2 months ago", Duration::days(60)),
+            ("1 year ago", Duration::days(365)),
+        ];
+        for (text, elapsed) in cases {
+            assert_eq!(parse_relative(text, now()), Some(now() - elapsed), "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_relative_rejects_other_text() {
+        for text in ["", "last Tuesday", "12 mins", "ago", "many days ago", "2 fortnights ago", "2 big days ago", "2025-06-03"] {
+            assert_eq!(parse_relative(text, now()), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_relative_round_trips_relative() {
+        for elapsed in [Duration::minutes(5), Duration::hours(3), Duration::days(2), Duration::weeks(3)] {
+            let text = relative(now() - elapsed, now());
+            assert_eq!(parse_relative(&text, now()), Some(now() - elapsed), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,527 @@
+use crate::{batch, detect};
+use crate::STORE;
+use relaycode_schema::{NewTransaction, Transaction, WatchSource, WatcherConfig, WatcherState, WatcherStatus};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+/// An input the watcher polls for candidate patch text.
+pub trait PatchSource: Send {
+    /// Short label such as `file:/tmp/patch.diff`, used as the transaction author.
+    fn describe(&self) -> String;
+
+    /// Returns new content since the previous poll, or `None` when nothing changed.
+    fn poll(&mut self) -> io::Result<Option<String>>;
+}
+
+/// Remembers the last value seen and reports only changes. The first read just primes it,
+/// so content that was already there when the watcher started is not ingested.
+#[derive(Default)]
+struct ChangeTracker {
+    last: Option<String>,
+    primed: bool,
+}
+
+impl ChangeTracker {
+    fn observe(&mut self, content: String) -> Option<String> {
+        let primed = std::mem::replace(&mut self.primed, true);
+        if self.last.as_ref() == Some(&content) {
+            return None;
+        }
+        self.last = Some(content.clone());
+        (primed && !content.trim().is_empty()).then_some(content)
+    }
+}
+
+pub struct FileSource {
+    path: PathBuf,
+    tracker: ChangeTracker,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            tracker: ChangeTracker::default(),
+        }
+    }
+}
+
+impl PatchSource for FileSource {
+    fn describe(&self) -> String {
+        format!("file:{}", self.path.display())
+    }
+
+    fn poll(&mut self) -> io::Result<Option<String>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => Ok(self.tracker.observe(content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(self.tracker.observe(String::new())),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Reads the clipboard by running a paste command.
+pub struct ClipboardSource {
+    command: String,
+    tracker: ChangeTracker,
+}
+
+const CLIPBOARD_COMMANDS: &[&str] = &[
+    "pbpaste",
+    "wl-paste --no-newline",
+    "xclip -selection clipboard -o",
+    "xsel --clipboard --output",
+    "powershell.exe -NoProfile -Command Get-Clipboard",
+];
+
+impl ClipboardSource {
+    /// Uses `command` when given, otherwise the first paste command that works on this machine.
+    pub fn new(command: Option<String>) -> io::Result<Self> {
+        let command = match command {
+            Some(command) => command,
+            None => CLIPBOARD_COMMANDS
+                .iter()
+                .find(|candidate| read_clipboard(candidate).is_ok())
+                .map(|candidate| candidate.to_string())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "no clipboard command found; set watcher.source.command",
+                    )
+                })?,
+        };
+        Ok(Self {
+            command,
+            tracker: ChangeTracker::default(),
+        })
+    }
+}
+
+fn read_clipboard(command: &str) -> io::Result<String> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    let output = Command::new(program).args(parts).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "`{}` failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl PatchSource for ClipboardSource {
+    fn describe(&self) -> String {
+        "clipboard".to_string()
+    }
+
+    fn poll(&mut self) -> io::Result<Option<String>> {
+        let content = read_clipboard(&self.command)?;
+        Ok(self.tracker.observe(content))
+    }
+}
+
+/// A blocking byte stream read on a background thread; each EOF ends one payload.
+pub struct StreamSource {
+    name: String,
+    receiver: mpsc::Receiver<io::Result<String>>,
+}
+
+impl StreamSource {
+    /// Reads one payload from `reader`.
+    pub fn from_reader(name: impl Into<String>, mut reader: impl Read + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut content = String::new();
+            let _ = sender.send(reader.read_to_string(&mut content).map(|_| content));
+        });
+        Self {
+            name: name.into(),
+            receiver,
+        }
+    }
+
+    pub fn stdin() -> Self {
+        Self::from_reader("stdin", io::stdin())
+    }
+
+    /// Re-opens the named pipe after every writer closes it, so it can receive any number of payloads.
+    pub fn pipe(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let name = format!("pipe:{}", path.display());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || loop {
+            let result = std::fs::read_to_string(&path);
+            let failed = result.is_err();
+            if sender.send(result).is_err() || failed {
+                break;
+            }
+        });
+        Self { name, receiver }
+    }
+}
+
+impl PatchSource for StreamSource {
+    fn describe(&self) -> String {
+        self.name.clone()
+    }
+
+    fn poll(&mut self) -> io::Result<Option<String>> {
+        match self.receiver.try_recv() {
+            Ok(Ok(content)) if !content.trim().is_empty() => Ok(Some(content)),
+            Ok(Ok(_)) => Ok(None),
+            Ok(Err(err)) => Err(err),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+pub fn source_from_config(source: &WatchSource) -> io::Result<Box<dyn PatchSource>> {
+    let resolve = |path: &str| crate::config::project_root().join(path);
+    Ok(match source {
+        WatchSource::Clipboard { command } => Box::new(ClipboardSource::new(command.clone())?),
+        WatchSource::File { path } => Box::new(FileSource::new(resolve(path))),
+        WatchSource::Pipe { path } => Box::new(StreamSource::pipe(resolve(path))),
+        WatchSource::Stdin => Box::new(StreamSource::stdin()),
+    })
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.trim().hash(&mut hasher);
+    hasher.finish()
+}
+
+struct BulkSettings {
+    size: usize,
+    timeout: Duration,
+}
+
+/// Turns content from a `PatchSource` into pending transactions, skipping anything seen before.
+pub struct Watcher {
+    source: Box<dyn PatchSource>,
+    seen: HashSet<u64>,
+    bulk: Option<BulkSettings>,
+    pending: Vec<NewTransaction>,
+    batch_started: Option<Instant>,
+}
+
+impl Watcher {
+    pub fn new(source: Box<dyn PatchSource>) -> Self {
+        Self {
+            source,
+            seen: HashSet::new(),
+            bulk: None,
+            pending: Vec::new(),
+            batch_started: None,
+        }
+    }
+
+    /// Collects detected patches into batches of up to `size`, flushing early once `timeout`
+    /// has passed since the first patch of the batch arrived.
+    pub fn with_bulk(mut self, size: usize, timeout: Duration) -> Self {
+        self.bulk = Some(BulkSettings { size, timeout });
+        self
+    }
+
+    pub fn describe(&self) -> String {
+        self.source.describe()
+    }
+
+    fn detect(&mut self) -> io::Result<Vec<NewTransaction>> {
+        let Some(content) = self.source.poll()? else {
+            return Ok(Vec::new());
+        };
+        if !self.seen.insert(content_hash(&content)) {
+            return Ok(Vec::new());
+        }
+        let detection = detect::classify(&content);
+        if detection.is_patch && !detection.ingestible {
+            tracing::warn!(
+                "Skipping {:?} patch from {}: only unified diffs and format-patch text can be ingested",
+                detection.format,
+                self.describe()
+            );
+        }
+        if !detection.ingestible {
+            return Ok(Vec::new());
+        }
+        Ok(crate::parse_patch_text(&content, &self.source.describe()))
+    }
+
+    /// Polls the source once and ingests new patches. Returns every transaction created this round,
+    /// which is several when a batch is flushed.
+    pub fn poll_once(&mut self) -> io::Result<Vec<Transaction>> {
+        let (mut detected, error) = match self.detect() {
+            Ok(detected) => (detected, None),
+            Err(err) => (Vec::new(), Some(err)),
+        };
+
+        let mut ingested = Vec::new();
+        match (&self.bulk, detected.len()) {
+            (_, 0) => {}
+            // A `git format-patch` series is already ordered, so it stays its own chain.
+            (_, 2..) => ingested.extend(crate::ingest_chain(detected)),
+            (None, _) => ingested.push(crate::ingest_transaction(detected.remove(0))),
+            (Some(_), _) => {
+                self.batch_started.get_or_insert_with(Instant::now);
+                self.pending.append(&mut detected);
+            }
+        }
+        // A failing source must not hold back a batch whose timeout already expired.
+        if let Some(bulk) = &self.bulk {
+            let full = self.pending.len() >= bulk.size;
+            let expired = self.batch_started.is_some_and(|started| started.elapsed() >= bulk.timeout);
+            if full || expired {
+                ingested.extend(self.flush());
+            }
+        }
+
+        match error {
+            Some(err) => Err(err),
+            None => Ok(ingested),
+        }
+    }
+
+    /// Polls the source but throws the content away, so nothing copied while paused is ingested on resume.
+    pub fn skip_once(&mut self) -> io::Result<Vec<Transaction>> {
+        self.source.poll().map(|_| Vec::new())
+    }
+
+    fn flush(&mut self) -> Vec<Transaction> {
+        self.batch_started = None;
+        let mut items = std::mem::take(&mut self.pending);
+        match items.len() {
+            0 => Vec::new(),
+            1 => vec![crate::ingest_transaction(items.remove(0))],
+            _ => batch::ingest_batch(items)
+                .map(|batch| {
+                    batch
+                        .transaction_ids
+                        .iter()
+                        .filter_map(|id| crate::STORE.get_transaction(id))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+pub(crate) fn derive_state(status: &WatcherStatus) -> WatcherState {
+    match (&status.source, status.paused, &status.last_error) {
+        (None, _, Some(_)) => WatcherState::Error,
+        (None, _, None) => WatcherState::Stopped,
+        (Some(_), true, _) => WatcherState::Paused,
+        (Some(_), false, Some(_)) => WatcherState::Error,
+        (Some(_), false, None) => WatcherState::Running,
+    }
+}
+
+fn state_path() -> PathBuf {
+    crate::config::relay_dir().join("state.json")
+}
+
+fn read_state_file() -> serde_json::Map<String, serde_json::Value> {
+    std::fs::read_to_string(state_path())
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Whether `.relay/state.json` says the watcher was paused when the server last ran.
+pub fn load_paused() -> bool {
+    read_state_file()
+        .get("watcherPaused")
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Records the paused flag in `.relay/state.json`, keeping any other keys in the file.
+pub fn save_paused(paused: bool) -> io::Result<()> {
+    let mut state = read_state_file();
+    state.insert("watcherPaused".to_string(), serde_json::Value::Bool(paused));
+    let path = state_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&state)? + "\n")
+}
+
+/// The polling task started by [`restart`], if any.
+static RUNNING: Mutex<Option<tokio::task::JoinHandle<()>>> = Mutex::new(None);
+
+/// Stops the running watcher, then starts a new one from `config` when `enabled` is set.
+/// A disabled watcher reports `stopped`.
+pub fn restart(config: &WatcherConfig) -> io::Result<()> {
+    let mut running = RUNNING.lock().unwrap();
+    if let Some(handle) = running.take() {
+        handle.abort();
+    }
+    if !config.enabled {
+        STORE.update_watcher(|status| {
+            status.source = None;
+            status.last_error = None;
+        });
+        return Ok(());
+    }
+    *running = Some(spawn(config)?);
+    Ok(())
+}
+
+/// Starts polling the configured source every `clipboardPollInterval` milliseconds, batching
+/// detections when `enableBulkProcessing` is on. While paused the source is still drained but
+/// nothing is ingested.
+fn spawn(config: &WatcherConfig) -> io::Result<tokio::task::JoinHandle<()>> {
+    let source = source_from_config(&config.source).inspect_err(|err| {
+        STORE.update_watcher(|status| status.last_error = Some(err.to_string()));
+    })?;
+    let mut watcher = Watcher::new(source);
+    if config.enable_bulk_processing {
+        watcher = watcher.with_bulk(config.bulk_size as usize, Duration::from_millis(config.bulk_timeout));
+    }
+    let interval = Duration::from_millis(config.clipboard_poll_interval);
+    STORE.update_watcher(|status| {
+        status.source = Some(watcher.describe());
+        status.last_error = None;
+    });
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let paused = STORE.get_watcher_status().paused;
+            // Sources block (file reads, paste commands), so keep them off the async workers.
+            let (returned, result) = tokio::task::spawn_blocking(move || {
+                let result = if paused { watcher.skip_once() } else { watcher.poll_once() };
+                (watcher, result)
+            })
+            .await
+            .expect("watcher poll panicked");
+            watcher = returned;
+
+            match result {
+                Ok(ingested) => {
+                    for tx in &ingested {
+                        tracing::info!("Detected patch from {}: {}", watcher.describe(), tx.id);
+                    }
+                    STORE.update_watcher(|status| {
+                        if !ingested.is_empty() {
+                            status.detected_count += ingested.len() as u64;
+                            status.last_detection_at = Some(chrono::Utc::now());
+                        }
+                        status.last_error = None;
+                    });
+                }
+                Err(err) => {
+                    let message = err.to_string();
+                    let previous = STORE.get_watcher_status().last_error;
+                    if previous.as_ref() != Some(&message) {
+                        tracing::warn!("Watcher source {} failed: {}", watcher.describe(), message);
+                    }
+                    STORE.update_watcher(|status| {
+                        status.error_count += 1;
+                        status.last_error = Some(message);
+                    });
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,2 +1,2 @@\n fn a() {\n-    1\n+    2\n";
+
+    fn scratch_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("relaycode-watcher-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn file_source_reports_changes_after_the_first_read() {
+        let path = scratch_file("changes");
+        std::fs::write(&path, "already there").unwrap();
+        let mut source = FileSource::new(&path);
+
+        assert_eq!(source.poll().unwrap(), None, "existing content only primes the source");
+        std::fs::write(&path, DIFF).unwrap();
+        assert_eq!(source.poll().unwrap().as_deref(), Some(DIFF));
+        assert_eq!(source.poll().unwrap(), None, "unchanged content is not reported twice");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(source.poll().unwrap(), None, "a missing file reads as empty");
+    }
+
+    #[test]
+    fn stream_source_delivers_one_payload_per_reader() {
+        let mut source = StreamSource::from_reader("test", io::Cursor::new(DIFF.as_bytes().to_vec()));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let payload = loop {
+            if let Some(payload) = source.poll().unwrap() {
+                break payload;
+            }
+            assert!(Instant::now() < deadline, "no payload from the reader");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(payload, DIFF);
+        assert_eq!(source.poll().unwrap(), None);
+    }
+
+    #[test]
+    fn watcher_turns_only_new_ingestible_patches_into_transactions() {
+        let path = scratch_file("detect");
+        let mut watcher = Watcher::new(Box::new(FileSource::new(&path)));
+        assert!(watcher.detect().unwrap().is_empty());
+
+        std::fs::write(&path, "Meeting notes: ship the release on Friday.").unwrap();
+        assert!(watcher.detect().unwrap().is_empty(), "prose is not a patch");
+
+        std::fs::write(&path, "src/a.rs\n<<<<<<< SEARCH\n    1\n=======\n    2\n>>>>>>> REPLACE\n").unwrap();
+        assert!(watcher.detect().unwrap().is_empty(), "search/replace blocks are not ingested");
+
+        std::fs::write(&path, DIFF).unwrap();
+        let detected = watcher.detect().unwrap();
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].files[0].path, "src/a.rs");
+
+        std::fs::write(&path, "something else").unwrap();
+        watcher.detect().unwrap();
+        std::fs::write(&path, DIFF).unwrap();
+        assert!(watcher.detect().unwrap().is_empty(), "the same patch is ingested once");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn restart_runs_the_watcher_only_while_enabled() {
+        let _store = crate::testing::lock().await;
+        crate::testing::project_root();
+        let mut config = WatcherConfig {
+            source: WatchSource::File { path: "watched.diff".to_string() },
+            ..WatcherConfig::default()
+        };
+
+        restart(&config).unwrap();
+        assert_eq!(STORE.get_watcher_status().state, WatcherState::Stopped);
+        assert!(RUNNING.lock().unwrap().is_none());
+
+        config.enabled = true;
+        restart(&config).unwrap();
+        let status = STORE.get_watcher_status();
+        assert_eq!(status.state, WatcherState::Running);
+        assert!(status.source.unwrap().ends_with("watched.diff"));
+        assert!(RUNNING.lock().unwrap().is_some());
+
+        config.enabled = false;
+        restart(&config).unwrap();
+        assert_eq!(STORE.get_watcher_status().state, WatcherState::Stopped);
+        assert!(RUNNING.lock().unwrap().is_none());
+    }
+}
@@ -1,3 +1,5 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -29,6 +31,8 @@ pub enum FileApplyStatus {
     Applying,
     Applied,
     Failed,
+    /// Left untouched because every change in it was rejected in review.
+    Skipped,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -47,7 +51,13 @@ pub enum FileStatus {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TransactionFile {
+    /// Unique within the transaction; `file` blocks point here. Assigned on ingest when empty.
+    #[serde(default)]
+    pub id: String,
     pub path: String,
+    /// The path before a rename; set when `status` is `renamed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
     pub status: FileStatus,
     #[serde(default = "default_file_apply_status")]
     pub apply_status: FileApplyStatus,
@@ -55,19 +65,68 @@ pub struct TransactionFile {
     pub error_message: Option<String>,
     pub language: String,
     pub diff: String,
+    /// Linter findings for this file from the latest apply.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+    /// The file as the patch author saw it; enables a three-way merge when the file has drifted since.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_content: Option<String>,
+    /// Conflict-marker regions left in the file by a failed three-way merge.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<LineRange>,
+    /// Reviewer decisions on individual hunks; see `GET /api/transactions/{id}/hunks`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hunk_decisions: Vec<HunkDecision>,
+    /// Hunks the latest apply left out because they were not approved.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_hunks: Vec<String>,
+    /// The reviewer's decision on the whole file, if one was made.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<FileReview>,
+    /// Earlier versions of `diff` replaced by manual edits, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revisions: Vec<DiffRevision>,
 }
 
 fn default_file_apply_status() -> FileApplyStatus {
     FileApplyStatus::Pending
 }
 
+/// One entry of a transaction's stream, tagged by `type`.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
-#[serde(rename_all = "camelCase")]
-pub struct TransactionBlock {
-    #[serde(rename = "type")]
-    pub block_type: String,
-    pub content: Option<String>,
-    pub file: Option<TransactionFile>,
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TransactionBlock {
+    /// Prose from the model between file changes.
+    Markdown { content: String },
+    /// Where one of the transaction's `files` sits in the stream.
+    #[serde(rename_all = "camelCase")]
+    File { file_id: String },
+    /// A shell command the model ran or asks to run.
+    #[serde(rename_all = "camelCase")]
+    Command {
+        command: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+    },
+    /// A step of the plan the model is working through.
+    PlanStep {
+        content: String,
+        #[serde(default)]
+        done: bool,
+    },
+    /// A remark that is not part of the model's reasoning, e.g. from a reviewer.
+    Note { content: String },
+}
+
+impl TransactionBlock {
+    pub fn file_id(&self) -> Option<&str> {
+        match self {
+            TransactionBlock::File { file_id } => Some(file_id),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -76,21 +135,369 @@ pub struct Transaction {
     pub id: String,
     pub status: TransactionStatus,
     pub description: String,
+    /// `createdAt` relative to when it was read, e.g. `12 mins ago`. Derived; ignored on input.
+    #[serde(default, skip_deserializing)]
     pub timestamp: String,
-    pub created_at: String,
+    pub created_at: DateTime<Utc>,
+    /// Last change of any kind, including review decisions and apply progress.
+    pub updated_at: DateTime<Utc>,
+    /// When the transaction last became `APPLIED` or `PARTIALLYAPPLIED`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committed_at: Option<DateTime<Utc>>,
+    /// Every status change, oldest first. New transactions start with `PENDING` at `createdAt`.
+    #[serde(default)]
+    pub status_history: Vec<StatusTransition>,
     pub prompt_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_chain_root: Option<bool>,
     pub author: String,
+    /// The stream shown to reviewers; `file` blocks refer to `files` by ID.
+    pub blocks: Vec<TransactionBlock>,
+    /// Every file change, stored once.
+    pub files: Vec<TransactionFile>,
+    pub provider: String,
+    pub model: String,
+    pub cost: Cost,
+    pub tokens: TokenUsage,
+    pub reasoning: String,
+    #[serde(default)]
+    pub validations: Vec<ValidationRun>,
+    /// Set when the transaction was marked applied despite a failed validation command.
+    #[serde(default)]
+    pub validation_overridden: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ApprovalRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lint_summary: Option<LintSummary>,
+    /// Set on every member of a watcher batch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    /// Pending or applied transactions whose hunks overlap this one's; filled in on the list response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts_with: Vec<String>,
+    /// The commit message accepted for this transaction; see `POST /api/commit-message/generate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusTransition {
+    pub status: TransactionStatus,
+    pub at: DateTime<Utc>,
+}
+
+/// Tokens a transaction's generation used. Older data stores a single count such as `"4,200"`
+/// or `"1.5k"`; it is read into `totalTokens` with the split left at zero.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", try_from = "TokenUsageRepr")]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Input tokens served from the provider's prompt cache; included in `inputTokens`.
+    pub cached_tokens: u64,
+    /// `inputTokens + outputTokens`, or the legacy count when the split is unknown.
+    pub total_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn new(input_tokens: u64, output_tokens: u64, cached_tokens: u64) -> Self {
+        Self {
+            input_tokens,
+            output_tokens,
+            cached_tokens,
+            total_tokens: input_tokens + output_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenUsageRepr {
+    Count(u64),
+    Legacy(String),
+    Structured {
+        #[serde(default, rename = "inputTokens")]
+        input_tokens: u64,
+        #[serde(default, rename = "outputTokens")]
+        output_tokens: u64,
+        #[serde(default, rename = "cachedTokens")]
+        cached_tokens: u64,
+        #[serde(default, rename = "totalTokens")]
+        total_tokens: Option<u64>,
+    },
+}
+
+impl TryFrom<TokenUsageRepr> for TokenUsage {
+    type Error = String;
+
+    fn try_from(repr: TokenUsageRepr) -> Result<Self, Self::Error> {
+        match repr {
+            TokenUsageRepr::Count(total_tokens) => Ok(Self { total_tokens, ..Self::default() }),
+            TokenUsageRepr::Legacy(text) => parse_legacy_tokens(&text)
+                .map(|total_tokens| Self { total_tokens, ..Self::default() })
+                .ok_or_else(|| format!("invalid token count {:?}", text)),
+            TokenUsageRepr::Structured { input_tokens, output_tokens, cached_tokens, total_tokens } => Ok(Self {
+                input_tokens,
+                output_tokens,
+                cached_tokens,
+                total_tokens: total_tokens.unwrap_or(input_tokens + output_tokens),
+            }),
+        }
+    }
+}
+
+/// Reads `"4,200"`, `"4200 tokens"`, `"1.5k"` or `"2M"`, rounding to a whole count; an empty
+/// string is no tokens.
+fn parse_legacy_tokens(text: &str) -> Option<u64> {
+    use rust_decimal::prelude::ToPrimitive;
+
+    let lower = text.trim().to_ascii_lowercase();
+    let number = lower.strip_suffix("tokens").or_else(|| lower.strip_suffix("token")).unwrap_or(&lower).trim();
+    if number.is_empty() {
+        return Some(0);
+    }
+    let (number, scale) = match number.strip_suffix('k') {
+        Some(rest) => (rest, Decimal::from(1_000)),
+        None => match number.strip_suffix('m') {
+            Some(rest) => (rest, Decimal::from(1_000_000)),
+            None => (number, Decimal::ONE),
+        },
+    };
+    let amount: Decimal = number.trim().replace(',', "").parse().ok()?;
+    if amount.is_sign_negative() {
+        return None;
+    }
+    (amount * scale).round().to_u64()
+}
+
+/// What a transaction's generation cost. Older data stores a display string such as `"$0.089"`,
+/// which is still accepted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", try_from = "CostRepr")]
+pub struct Cost {
+    #[serde(with = "rust_decimal::serde::float")]
+    #[schema(value_type = f64)]
+    pub amount: Decimal,
+    /// ISO 4217 code, e.g. `USD`.
+    pub currency: String,
+    /// Computed from token counts with the pricing table because the provider reported no cost.
+    pub estimated: bool,
+}
+
+impl Default for Cost {
+    fn default() -> Self {
+        Self {
+            amount: Decimal::ZERO,
+            currency: "USD".to_string(),
+            estimated: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CostRepr {
+    Legacy(String),
+    Structured {
+        #[serde(with = "rust_decimal::serde::float")]
+        amount: Decimal,
+        #[serde(default = "default_currency")]
+        currency: String,
+        #[serde(default)]
+        estimated: bool,
+    },
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+impl TryFrom<CostRepr> for Cost {
+    type Error = String;
+
+    fn try_from(repr: CostRepr) -> Result<Self, Self::Error> {
+        match repr {
+            CostRepr::Structured { amount, currency, estimated } => Ok(Self { amount, currency, estimated }),
+            CostRepr::Legacy(text) => parse_legacy_cost(&text).ok_or_else(|| format!("invalid cost {:?}", text)),
+        }
+    }
+}
+
+/// Reads `"$0.089"`, `"€1.20"`, `"0.5 EUR"` or a bare number; an empty string is no cost.
+fn parse_legacy_cost(text: &str) -> Option<Cost> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(Cost::default());
+    }
+    let (currency, rest) = match text.chars().next()? {
+        '$' => ("USD".to_string(), &text[1..]),
+        '€' => ("EUR".to_string(), &text['€'.len_utf8()..]),
+        '£' => ("GBP".to_string(), &text['£'.len_utf8()..]),
+        _ => match text.rsplit_once(' ') {
+            Some((amount, code)) if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) => {
+                (code.to_ascii_uppercase(), amount)
+            }
+            _ => (default_currency(), text),
+        },
+    };
+    let amount: Decimal = rest.trim().replace(',', "").parse().ok()?;
+    Some(Cost {
+        amount,
+        currency,
+        estimated: false,
+    })
+}
+
+/// A path changed by more than one transaction of the same batch.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOverlap {
+    pub path: String,
+    pub transaction_ids: Vec<String>,
+}
+
+/// Patches detected together while bulk processing, reviewed and approved as one unit.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Batch {
+    pub id: String,
+    /// The chain root; every other member has it as `parentId`.
+    pub root_id: String,
+    /// Members in detection order.
+    pub transaction_ids: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub overlaps: Vec<FileOverlap>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// A single linter or compiler finding.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub file_path: String,
+    pub line: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Zero-based line within the file's `diff` that this finding points at, when the diff shows it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff_line: Option<usize>,
+}
+
+/// Linter totals for the whole project before and after an apply.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LintSummary {
+    pub errors_before: usize,
+    pub errors_after: usize,
+    pub warnings_before: usize,
+    pub warnings_after: usize,
+    /// `errorsAfter - errorsBefore`; positive when the apply introduced errors.
+    pub error_delta: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub transaction_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<LintSummary>,
+    /// Findings in transaction files, ordered by file then line for next/previous navigation.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalMethod {
+    Auto,
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApprovalRule {
+    /// A reviewer started the apply.
+    Manual,
+    /// `patch.approvalMode` is `auto` and no linter is configured.
+    AutoMode,
+    /// Auto mode, and the linter reported no more than `patch.approvalOnErrorCount` errors.
+    LinterErrorThreshold,
+    /// A reviewer accepted the transaction despite a failed validation command.
+    ValidationOverride,
+}
+
+/// Audit record of who or what approved a transaction for application.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRecord {
+    pub method: ApprovalMethod,
+    pub rule: ApprovalRule,
+    pub decided_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Set when a check after the files were written withdrew an auto approval; the record above
+    /// still shows what allowed the write.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<ApprovalRejection>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRejection {
+    pub decided_at: DateTime<Utc>,
+    pub reason: String,
+}
+
+/// Payload for ingesting a new `PENDING` transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NewTransaction {
+    pub description: String,
+    pub prompt_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    pub author: String,
+    /// `file` blocks name entries of `files`. Blocks that embed the file itself, the older shape,
+    /// are still accepted.
     pub blocks: Vec<TransactionBlock>,
     pub files: Vec<TransactionFile>,
     pub provider: String,
     pub model: String,
-    pub cost: String,
-    pub tokens: String,
     pub reasoning: String,
+    /// Creation time to keep, e.g. the `Date:` of an imported mail; defaults to now.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Token counts the provider reported, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<TokenUsage>,
+    /// Cost the provider reported; estimated from `tokens` with the pricing table when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<Cost>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPatchRequest {
+    /// A unified diff or `git format-patch` output, optionally wrapped in prose.
+    pub text: String,
+    /// Used as the author when the text carries none.
+    #[serde(default)]
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -98,13 +505,21 @@ pub struct Transaction {
 pub struct BulkActionRequest {
     pub ids: Vec<String>,
     pub action: TransactionStatus,
+    /// Allow `APPLIED` or `COMMITTED` even though a pre/post command or the linter failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_validation: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BulkActionResponse {
+    /// False when any id was rejected.
     pub success: bool,
     pub updated_ids: Vec<String>,
+    /// Ids left unchanged because a validation run failed and `overrideValidation` was not set, or,
+    /// when reverting, because their files could not be restored on disk.
+    #[serde(default)]
+    pub rejected_ids: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -113,7 +528,11 @@ pub struct Prompt {
     pub id: String,
     pub title: String,
     pub content: String,
+    /// `createdAt` relative to when it was read, e.g. `12 mins ago`. Derived; ignored on input.
+    #[serde(default, skip_deserializing)]
     pub timestamp: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub status: PromptStatus,
 }
 
@@ -137,6 +556,46 @@ pub struct FileStatusEvent {
     pub error_message: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValidationKind {
+    PreCommand,
+    Linter,
+    PostCommand,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Captured result of one validation command run in the project root.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationRun {
+    pub kind: ValidationKind,
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+    pub timed_out: bool,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandOutputEvent {
+    pub transaction_id: String,
+    pub kind: ValidationKind,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum SimulationScenario {
@@ -152,6 +611,9 @@ pub struct UpdateStatusRequest {
     pub status: TransactionStatus,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scenario: Option<SimulationScenario>,
+    /// Allow `APPLIED` or `COMMITTED` even though a pre/post command or the linter failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub override_validation: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -165,3 +627,805 @@ pub struct ReapplyFileRequest {
 pub struct ReapplyAllFailedRequest {
     pub transaction_id: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationErrors {
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Silent,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatchStrategy {
+    Auto,
+    Replace,
+    NewUnified,
+    MultiSearchReplace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalMode {
+    Auto,
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitBranchTemplate {
+    GitCommitMsg,
+    TransactionId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct CoreConfig {
+    pub log_level: LogLevel,
+    pub enable_notifications: bool,
+    pub watch_config: bool,
+}
+
+impl Default for CoreConfig {
+    fn default() -> Self {
+        Self {
+            log_level: LogLevel::Info,
+            enable_notifications: false,
+            watch_config: false,
+        }
+    }
+}
+
+/// Where `relay watch` reads candidate patches from.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WatchSource {
+    /// Poll the system clipboard, optionally through a custom read command such as `wl-paste`.
+    Clipboard {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        command: Option<String>,
+    },
+    /// Re-read a file whenever its content changes.
+    File { path: String },
+    /// Read a named pipe; each writer's output up to EOF is one payload.
+    Pipe { path: String },
+    /// Read standard input up to EOF as a single payload.
+    Stdin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct WatcherConfig {
+    /// Poll `source` for patches. Off by default so the server never reads the clipboard unasked.
+    pub enabled: bool,
+    pub source: WatchSource,
+    /// Milliseconds between polls of the watch source.
+    #[schema(minimum = 100)]
+    pub clipboard_poll_interval: u64,
+    pub preferred_strategy: PatchStrategy,
+    pub enable_bulk_processing: bool,
+    #[schema(minimum = 1)]
+    pub bulk_size: u32,
+    /// Milliseconds to wait for a batch to fill before processing it.
+    #[schema(minimum = 1)]
+    pub bulk_timeout: u64,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: WatchSource::Clipboard { command: None },
+            clipboard_poll_interval: 2000,
+            preferred_strategy: PatchStrategy::Auto,
+            enable_bulk_processing: false,
+            bulk_size: 5,
+            bulk_timeout: 30000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct PatchConfig {
+    pub approval_mode: ApprovalMode,
+    pub approval_on_error_count: u32,
+    pub linter: String,
+    pub pre_command: String,
+    pub post_command: String,
+    pub min_file_changes: u32,
+    /// Milliseconds before a pre/post command or linter run is killed.
+    #[schema(minimum = 1)]
+    pub command_timeout: u64,
+    /// Write patches to files under the project root instead of simulating the apply.
+    pub apply_to_disk: bool,
+}
+
+impl Default for PatchConfig {
+    fn default() -> Self {
+        Self {
+            approval_mode: ApprovalMode::Manual,
+            approval_on_error_count: 0,
+            linter: String::new(),
+            pre_command: String::new(),
+            post_command: String::new(),
+            min_file_changes: 0,
+            command_timeout: 120000,
+            apply_to_disk: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct GitConfig {
+    pub auto_git_branch: bool,
+    pub git_branch_prefix: String,
+    pub git_branch_template: GitBranchTemplate,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            auto_git_branch: false,
+            git_branch_prefix: "relay/".to_string(),
+            git_branch_template: GitBranchTemplate::GitCommitMsg,
+        }
+    }
+}
+
+/// The model used for auto-repair and other generated text.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct AiProviderConfig {
+    /// `mock` answers locally and deterministically; any other name is an OpenAI-compatible API.
+    pub name: String,
+    /// Base URL of the API, e.g. `http://localhost:11434/v1`. Known provider names have a default.
+    pub base_url: String,
+    /// Overridden by the `RELAYCODE_API_KEY` environment variable. The API returns a stored key as
+    /// `********`; sending that or an empty string back keeps it.
+    pub api_key: String,
+    pub default_model: String,
+    /// Milliseconds before a provider request is abandoned.
+    #[schema(minimum = 1)]
+    pub timeout: u64,
+}
+
+impl Default for AiProviderConfig {
+    fn default() -> Self {
+        Self {
+            name: "mock".to_string(),
+            base_url: String::new(),
+            api_key: String::new(),
+            default_model: String::new(),
+            timeout: 60000,
+        }
+    }
+}
+
+/// Typed form of `relay.config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RelayConfig {
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[schema(min_length = 1)]
+    pub project_id: String,
+    #[serde(default)]
+    pub core: CoreConfig,
+    #[serde(default)]
+    pub watcher: WatcherConfig,
+    #[serde(default)]
+    pub patch: PatchConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+    #[serde(default)]
+    pub ai_provider: AiProviderConfig,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            schema: None,
+            project_id: "relay-project".to_string(),
+            core: CoreConfig::default(),
+            watcher: WatcherConfig::default(),
+            patch: PatchConfig::default(),
+            git: GitConfig::default(),
+            ai_provider: AiProviderConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WatcherState {
+    /// No watcher has been started.
+    #[default]
+    Stopped,
+    Running,
+    Paused,
+    Error,
+}
+
+/// Live state of the patch watcher, also pushed on the events stream whenever it changes.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherStatus {
+    pub state: WatcherState,
+    /// Persisted in `.relay/state.json` so a pause survives restarts.
+    pub paused: bool,
+    /// Label of the polled source, e.g. `clipboard` or `file:/tmp/patch.diff`.
+    pub source: Option<String>,
+    pub last_detection_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Patches ingested since the watcher started.
+    pub detected_count: u64,
+    /// Polls that failed since the watcher started.
+    pub error_count: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatchFormat {
+    UnifiedDiff,
+    /// `git format-patch` output or an mbox of several such messages.
+    FormatPatch,
+    /// `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` blocks.
+    SearchReplace,
+    /// A fenced code block whose first line names the file it replaces.
+    WholeFile,
+    /// The trailing YAML block with `projectId`, `uuid` and `changeSummary`.
+    RelaycodeYaml,
+}
+
+/// One piece of a patch found in free-form text.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchSegment {
+    pub format: PatchFormat,
+    /// Target file, when the segment names one.
+    pub path: Option<String>,
+    pub content: String,
+    /// One-based line range of the segment within the input.
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchDetection {
+    /// The format carrying the file changes; `None` when nothing recognisable was found.
+    pub format: Option<PatchFormat>,
+    /// 0.0 to 1.0.
+    pub confidence: f64,
+    /// `confidence` is high enough to treat the text as a patch rather than ordinary content.
+    pub is_patch: bool,
+    /// `isPatch`, and in a format that can become a transaction: a unified diff or
+    /// `git format-patch` text. The other formats are recognised but not ingested yet.
+    pub ingestible: bool,
+    pub segments: Vec<PatchSegment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectPatchRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainNode {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub status: TransactionStatus,
+    pub description: String,
+    /// Distance from the chain root.
+    pub depth: usize,
+    pub children: Vec<String>,
+}
+
+/// Every transaction linked to the requested one through `parentId`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionChain {
+    pub transaction_id: String,
+    pub root_id: String,
+    /// In apply order: every parent comes before its children.
+    pub nodes: Vec<ChainNode>,
+    /// Root first, ending with the requested transaction's parent.
+    pub ancestor_ids: Vec<String>,
+    /// Members whose `parentId` names a transaction that does not exist.
+    pub orphan_ids: Vec<String>,
+    /// The loop of ids found while walking up, if `parentId` links form one.
+    pub cycle: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainFileDiff {
+    /// The path after the chain's last rename of this file.
+    pub path: String,
+    /// The path before the chain first renamed this file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_path: Option<String>,
+    /// Transactions touching this path, in apply order.
+    pub transaction_ids: Vec<String>,
+}
+
+/// The chain's changes as one patch, for `patch -p1` or review.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainDiff {
+    pub root_id: String,
+    /// Members included, in apply order. Reverted members are left out.
+    pub transaction_ids: Vec<String>,
+    pub files: Vec<ChainFileDiff>,
+    /// One file section per member and path, in apply order, so later hunks apply on top of earlier ones.
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictKind {
+    /// Hunks touch the same lines; applying one will break the other.
+    Lines,
+    /// Same file, separate regions; usually applies with an offset.
+    File,
+}
+
+/// One-based, inclusive line range within a file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileConflict {
+    pub path: String,
+    pub kind: ConflictKind,
+    /// Ranges of the requested transaction that collide.
+    pub ranges: Vec<LineRange>,
+    pub other_ranges: Vec<LineRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionConflict {
+    pub transaction_id: String,
+    pub status: TransactionStatus,
+    pub files: Vec<FileConflict>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictReport {
+    pub transaction_id: String,
+    pub conflicts: Vec<TransactionConflict>,
+    /// The pending transactions linked to this one by line conflicts, in the order least likely to fail.
+    pub suggested_order: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewDecision {
+    #[default]
+    Undecided,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkDecision {
+    pub hunk_id: String,
+    pub decision: ReviewDecision,
+}
+
+/// A hunk of a file diff as shown for review. The id is derived from the hunk's content, so it
+/// survives re-parsing and changes only when the hunk itself does.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkReview {
+    pub id: String,
+    /// The `@@ -a,b +c,d @@` line.
+    pub header: String,
+    pub old_start: usize,
+    pub old_count: usize,
+    pub new_start: usize,
+    pub new_count: usize,
+    pub decision: ReviewDecision,
+    /// Left out by the latest apply.
+    pub skipped: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHunks {
+    pub path: String,
+    pub hunks: Vec<HunkReview>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkDecisionUpdate {
+    pub path: String,
+    pub hunk_id: String,
+    pub decision: ReviewDecision,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewHunksRequest {
+    pub decisions: Vec<HunkDecisionUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReview {
+    pub decision: ReviewDecision,
+    pub reviewer: Option<String>,
+    pub decided_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewFilesRequest {
+    pub paths: Vec<String>,
+    pub decision: ReviewDecision,
+    #[serde(default)]
+    pub reviewer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffRevision {
+    /// The diff as it was before the edit.
+    pub diff: String,
+    pub replaced_at: DateTime<Utc>,
+    pub replaced_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateFileDiffRequest {
+    pub file_path: String,
+    pub diff: String,
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// Steps of an auto-repair, in the order they happen.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepairStage {
+    ContextAnalysis,
+    ApiRequest,
+    Validation,
+    Applying,
+    Succeeded,
+    Failed,
+}
+
+/// Progress of one file's auto-repair, pushed on the events stream.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairEvent {
+    pub transaction_id: String,
+    pub file_path: String,
+    pub stage: RepairStage,
+    /// One-based provider round trip this event belongs to.
+    pub attempt: u32,
+    pub message: Option<String>,
+    /// Milliseconds since the repair started.
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairFileRequest {
+    pub file_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairStarted {
+    pub transaction_id: String,
+    pub file_paths: Vec<String>,
+    pub provider: String,
+}
+
+/// How much surrounding code a prompt carries, as in a plan's `context_files`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextTier {
+    Compact,
+    #[default]
+    Medium,
+    Extended,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PackFormat {
+    #[default]
+    Markdown,
+    Xml,
+}
+
+/// Per-tier file lists; each tier is complete on its own rather than adding to the one below.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(default)]
+pub struct ContextFiles {
+    pub compact: Vec<String>,
+    pub medium: Vec<String>,
+    pub extended: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PackContextRequest {
+    /// Paths or globs relative to the project root, packed whatever the tier. A directory means
+    /// everything under it; patterns reaching outside the root are rejected.
+    pub files: Vec<String>,
+    /// The list for `tier` is added to `files`.
+    pub context_files: Option<ContextFiles>,
+    pub tier: ContextTier,
+    pub format: PackFormat,
+    /// Files larger than this many bytes are left out. Defaults to 1 MiB.
+    pub max_file_size: Option<u64>,
+    /// Estimated token budget for the packed files. Defaults to 8k, 32k or 128k by tier.
+    pub max_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PackedFile {
+    pub path: String,
+    pub language: String,
+    pub bytes: u64,
+    pub tokens: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// No file matched the path or glob.
+    NotFound,
+    /// Excluded by `.gitignore` or another ignore file.
+    Ignored,
+    TooLarge,
+    Binary,
+    /// Packing it would exceed the token budget.
+    OverBudget,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextPack {
+    pub tier: ContextTier,
+    pub format: PackFormat,
+    pub files: Vec<PackedFile>,
+    pub skipped: Vec<SkippedFile>,
+    /// Estimate for the whole document, headings and directory tree included.
+    pub total_tokens: u64,
+    pub document: String,
+}
+
+/// The template in `.relay/prompts/system-prompt.md`, before variables are filled in.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemPrompt {
+    pub content: String,
+    /// Latest saved version; 0 while the built-in default is in use.
+    pub version: u32,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// No prompt file exists yet, so `content` is the built-in default.
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemPromptVersion {
+    pub version: u32,
+    pub saved_at: DateTime<Utc>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSystemPromptRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PreviewSystemPromptRequest {
+    /// A draft to render instead of the saved template.
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemPromptPreview {
+    /// The system prompt as providers receive it, ahead of any task-specific instructions.
+    pub rendered: String,
+    pub variables: std::collections::BTreeMap<String, String>,
+    /// `{{name}}` placeholders with no value, left in the output as written.
+    pub unknown_variables: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateCommitMessageRequest {
+    pub transaction_ids: Vec<String>,
+    /// Skip the provider and summarize the transactions directly.
+    #[serde(default)]
+    pub deterministic: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitMessageSource {
+    Ai,
+    Fallback,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitMessageSuggestion {
+    pub transaction_ids: Vec<String>,
+    /// A conventional-commit header, a blank line, then the body.
+    pub message: String,
+    pub source: CommitMessageSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Why the provider's answer was not used, when the fallback replaced it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptCommitMessageRequest {
+    pub transaction_ids: Vec<String>,
+    /// The suggestion as generated or as edited by hand.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsBucket {
+    Hour,
+    #[default]
+    Day,
+    /// ISO weeks, starting Monday 00:00 UTC.
+    Week,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsGroupBy {
+    Provider,
+    Model,
+    Author,
+    Prompt,
+}
+
+/// Aggregates over a set of transactions.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageStats {
+    pub transactions: u64,
+    /// Transaction count per status, keyed like `TransactionStatus`.
+    pub by_status: std::collections::BTreeMap<String, u64>,
+    /// Applied or committed transactions over all that finished applying, failed and partial
+    /// ones included; `None` when none finished.
+    pub apply_success_rate: Option<f64>,
+    pub avg_files_per_transaction: f64,
+    pub tokens: TokenUsage,
+    /// One total per currency.
+    pub costs: Vec<Cost>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsPoint {
+    /// Start of the bucket.
+    pub start: DateTime<Utc>,
+    pub stats: UsageStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsSeries {
+    /// The group's provider, model, author or prompt ID; `all` when ungrouped.
+    pub key: String,
+    /// Display name, e.g. the prompt's title.
+    pub label: String,
+    pub totals: UsageStats,
+    /// Buckets holding at least one transaction, oldest first.
+    pub points: Vec<AnalyticsPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsReport {
+    pub bucket: AnalyticsBucket,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<AnalyticsGroupBy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    pub totals: UsageStats,
+    /// Largest total token count first.
+    pub series: Vec<AnalyticsSeries>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_cost_strings_keep_amount_and_currency() {
+        let cost = |text: &str| parse_legacy_cost(text).map(|cost| (cost.amount.to_string(), cost.currency));
+        assert_eq!(cost("$0.089"), Some(("0.089".to_string(), "USD".to_string())));
+        assert_eq!(cost("€1.20"), Some(("1.20".to_string(), "EUR".to_string())));
+        assert_eq!(cost("0.5 eur"), Some(("0.5".to_string(), "EUR".to_string())));
+        assert_eq!(cost("1,250.75"), Some(("1250.75".to_string(), "USD".to_string())));
+        assert_eq!(parse_legacy_cost(""), Some(Cost::default()));
+        assert_eq!(parse_legacy_cost("about a dollar"), None);
+    }
+
+    #[test]
+    fn legacy_token_strings_become_the_total() {
+        let usage = TokenUsage::try_from(TokenUsageRepr::Legacy("4,200 tokens".to_string())).unwrap();
+        assert_eq!(usage, TokenUsage { total_tokens: 4200, ..TokenUsage::default() });
+
+        let cases = [
+            ("1.5k", Some(1_500)),
+            ("2M tokens", Some(2_000_000)),
+            ("0.25m", Some(250_000)),
+            ("12.6", Some(13)),
+            ("1,234", Some(1_234)),
+            ("", Some(0)),
+            ("-5", None),
+            ("1.5 thousand", None),
+            ("about 4k", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_legacy_tokens(text), expected, "{:?}", text);
+        }
+        let err = TokenUsage::try_from(TokenUsageRepr::Legacy("lots".to_string())).unwrap_err();
+        assert_eq!(err, "invalid token count \"lots\"");
+
+        let usage = TokenUsage::try_from(TokenUsageRepr::Structured {
+            input_tokens: 100,
+            output_tokens: 20,
+            cached_tokens: 0,
+            total_tokens: None,
+        })
+        .unwrap();
+        assert_eq!(usage.total_tokens, 120);
+    }
+}